@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE games ADD COLUMN enabled TINYINT NOT NULL DEFAULT 1;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE save_revisions;
+ALTER TABLE saves DROP COLUMN revision;
+ALTER TABLE games DROP COLUMN save_revisions;
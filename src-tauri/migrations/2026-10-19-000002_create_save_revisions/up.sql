@@ -0,0 +1,18 @@
+-- Your SQL goes here
+ALTER TABLE games ADD COLUMN save_revisions INTEGER NOT NULL DEFAULT 5;
+ALTER TABLE saves ADD COLUMN revision INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE save_revisions (
+  row_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  file_name VARCHAR(255) NOT NULL,
+  revision INTEGER NOT NULL,
+  data BLOB NOT NULL,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  UNIQUE(user_id, game_id, file_name, revision)
+);
+
+-- existing saves become the first revision
+INSERT INTO save_revisions (user_id, game_id, file_name, revision, data, time_stamp)
+SELECT user_id, game_id, file_name, revision, data, time_stamp FROM saves;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE games DROP COLUMN max_game_bytes;
+ALTER TABLE games DROP COLUMN max_saves_per_user;
+ALTER TABLE games DROP COLUMN max_save_bytes;
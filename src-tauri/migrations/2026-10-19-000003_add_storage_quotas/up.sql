@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE games ADD COLUMN max_save_bytes BIGINT NOT NULL DEFAULT 1048576;
+ALTER TABLE games ADD COLUMN max_saves_per_user BIGINT NOT NULL DEFAULT 100;
+ALTER TABLE games ADD COLUMN max_game_bytes BIGINT NOT NULL DEFAULT 67108864;
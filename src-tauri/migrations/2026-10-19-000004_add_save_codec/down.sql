@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+-- compressed data can't be read without the codec column
+DELETE FROM save_revisions WHERE codec != 'raw';
+DELETE FROM saves WHERE codec != 'raw';
+ALTER TABLE save_revisions DROP COLUMN codec;
+ALTER TABLE saves DROP COLUMN codec;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- existing data stays uncompressed until it's next read or written
+ALTER TABLE saves ADD COLUMN codec TEXT NOT NULL DEFAULT 'raw';
+ALTER TABLE save_revisions ADD COLUMN codec TEXT NOT NULL DEFAULT 'raw';
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE leaderboard_metrics (
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  value_name TEXT NOT NULL,
+  integer_only BOOLEAN NOT NULL DEFAULT 0,
+  PRIMARY KEY (game_id, value_name)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE leaderboard_attachments;
+ALTER TABLE leaderboard DROP COLUMN details;
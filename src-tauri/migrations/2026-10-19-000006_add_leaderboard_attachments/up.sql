@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE leaderboard ADD COLUMN details TEXT;
+CREATE TABLE leaderboard_attachments (
+  entry_id INTEGER NOT NULL PRIMARY KEY REFERENCES leaderboard(row_id) ON DELETE CASCADE,
+  data BLOB NOT NULL
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE leaderboard_members;
+ALTER TABLE leaderboard DROP COLUMN team_name;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE leaderboard ADD COLUMN team_name TEXT;
+CREATE TABLE leaderboard_members (
+  entry_id INTEGER NOT NULL REFERENCES leaderboard(row_id) ON DELETE CASCADE,
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  PRIMARY KEY (entry_id, user_id)
+);
@@ -0,0 +1,24 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = OFF;
+BEGIN;
+CREATE TABLE leaderboard_old (
+  row_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  value_name TEXT NOT NULL,
+  value_num DOUBLE NOT NULL DEFAULT 0,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  details TEXT,
+  team_name TEXT,
+  UNIQUE(user_id, game_id, value_name, value_num)
+);
+-- entries only differing in their tie-breakers can't all be kept
+INSERT OR IGNORE INTO leaderboard_old (row_id, user_id, game_id, value_name, value_num, time_stamp, details, team_name)
+  SELECT row_id, user_id, game_id, value_name, value_num, time_stamp, details, team_name FROM leaderboard ORDER BY row_id;
+DROP TABLE leaderboard;
+ALTER TABLE leaderboard_old RENAME TO leaderboard;
+DELETE FROM leaderboard_attachments WHERE entry_id NOT IN (SELECT row_id FROM leaderboard);
+DELETE FROM leaderboard_members WHERE entry_id NOT IN (SELECT row_id FROM leaderboard);
+ALTER TABLE leaderboard_metrics DROP COLUMN components;
+COMMIT;
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,24 @@
+-- Your SQL goes here
+PRAGMA foreign_keys = OFF;
+BEGIN;
+CREATE TABLE leaderboard_new (
+  row_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  value_name TEXT NOT NULL,
+  value_num DOUBLE NOT NULL DEFAULT 0,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  details TEXT,
+  team_name TEXT,
+  tie_breaker_1 DOUBLE NOT NULL DEFAULT 0,
+  tie_breaker_2 DOUBLE NOT NULL DEFAULT 0,
+  tie_breaker_3 DOUBLE NOT NULL DEFAULT 0,
+  UNIQUE(user_id, game_id, value_name, value_num, tie_breaker_1, tie_breaker_2, tie_breaker_3)
+);
+INSERT INTO leaderboard_new (row_id, user_id, game_id, value_name, value_num, time_stamp, details, team_name)
+  SELECT row_id, user_id, game_id, value_name, value_num, time_stamp, details, team_name FROM leaderboard;
+DROP TABLE leaderboard;
+ALTER TABLE leaderboard_new RENAME TO leaderboard;
+ALTER TABLE leaderboard_metrics ADD COLUMN components TEXT;
+COMMIT;
+PRAGMA foreign_keys = ON;
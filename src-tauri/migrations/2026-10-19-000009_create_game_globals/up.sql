@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE game_globals (
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  name TEXT NOT NULL,
+  value TEXT NOT NULL,
+  revision INTEGER NOT NULL DEFAULT 1,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  PRIMARY KEY (game_id, name)
+);
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE player_stats (
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  name TEXT NOT NULL,
+  value BIGINT NOT NULL DEFAULT 0,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  PRIMARY KEY (user_id, game_id, name)
+);
@@ -0,0 +1,28 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = OFF;
+BEGIN;
+CREATE TABLE leaderboard_old (
+  row_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  value_name TEXT NOT NULL,
+  value_num DOUBLE NOT NULL DEFAULT 0,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  details TEXT,
+  team_name TEXT,
+  tie_breaker_1 DOUBLE NOT NULL DEFAULT 0,
+  tie_breaker_2 DOUBLE NOT NULL DEFAULT 0,
+  tie_breaker_3 DOUBLE NOT NULL DEFAULT 0,
+  UNIQUE(user_id, game_id, value_name, value_num, tie_breaker_1, tie_breaker_2, tie_breaker_3)
+);
+-- daily challenge entries can't be told apart from the others without their date
+INSERT INTO leaderboard_old (row_id, user_id, game_id, value_name, value_num, time_stamp, details, team_name, tie_breaker_1, tie_breaker_2, tie_breaker_3)
+  SELECT row_id, user_id, game_id, value_name, value_num, time_stamp, details, team_name, tie_breaker_1, tie_breaker_2, tie_breaker_3 FROM leaderboard
+  WHERE challenge_date IS NULL;
+DROP TABLE leaderboard;
+ALTER TABLE leaderboard_old RENAME TO leaderboard;
+DELETE FROM leaderboard_attachments WHERE entry_id NOT IN (SELECT row_id FROM leaderboard);
+DELETE FROM leaderboard_members WHERE entry_id NOT IN (SELECT row_id FROM leaderboard);
+DROP TABLE console_secrets;
+COMMIT;
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,21 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = OFF;
+BEGIN;
+CREATE TABLE saves_old (
+  row_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  file_name VARCHAR(255) NOT NULL,
+  data BLOB NOT NULL,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  revision INTEGER NOT NULL DEFAULT 1,
+  codec TEXT NOT NULL DEFAULT 'raw',
+  UNIQUE(user_id, file_name)
+);
+-- saves of the same name in different games can't all be kept
+INSERT OR IGNORE INTO saves_old (row_id, user_id, game_id, file_name, data, time_stamp, revision, codec)
+  SELECT row_id, user_id, game_id, file_name, data, time_stamp, revision, codec FROM saves ORDER BY row_id;
+DROP TABLE saves;
+ALTER TABLE saves_old RENAME TO saves;
+COMMIT;
+PRAGMA foreign_keys = ON;
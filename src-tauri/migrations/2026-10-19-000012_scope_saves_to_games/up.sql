@@ -0,0 +1,20 @@
+-- Your SQL goes here
+PRAGMA foreign_keys = OFF;
+BEGIN;
+CREATE TABLE saves_new (
+  row_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+  user_id CHAR(32) NOT NULL REFERENCES users(id),
+  game_id CHAR(32) NOT NULL REFERENCES games(id),
+  file_name VARCHAR(255) NOT NULL,
+  data BLOB NOT NULL,
+  time_stamp TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL,
+  revision INTEGER NOT NULL DEFAULT 1,
+  codec TEXT NOT NULL DEFAULT 'raw',
+  UNIQUE(user_id, game_id, file_name)
+);
+INSERT INTO saves_new (row_id, user_id, game_id, file_name, data, time_stamp, revision, codec)
+  SELECT row_id, user_id, game_id, file_name, data, time_stamp, revision, codec FROM saves;
+DROP TABLE saves;
+ALTER TABLE saves_new RENAME TO saves;
+COMMIT;
+PRAGMA foreign_keys = ON;
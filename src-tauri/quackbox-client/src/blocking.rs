@@ -0,0 +1,257 @@
+//! A blocking client for games that don't use an async runtime.
+//!
+//! Don't use this client from inside an async runtime, use [`crate::Client`] instead.
+
+use crate::{
+    error::from_status,
+    normalize_base_url,
+    types::{
+        DailyChallenge, GlobalEntry, GlobalIncrement, GlobalPost, LeaderboardBatch,
+        LeaderboardBatchResult, LeaderboardEntry, LeaderboardGetParams, LeaderboardPost,
+        LeaderboardStats, LeaderboardStatsParams, SaveDataBatch, SaveDataBatchResult,
+        SaveDataEntry, SaveDataGetParams, SaveDataPatch, SaveDataPatchParams, SaveDataPost,
+        StatEntry, StatIncrement, StatsGetParams, StorageGetParams, StorageUsage,
+    },
+    Result,
+};
+use serde::de::DeserializeOwned;
+
+/// A blocking client for the game dev API
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::blocking::Client,
+    base_url: String,
+}
+
+impl Client {
+    /// Creates a client for the API at the given base url, such as
+    /// `http://127.0.0.1:6174/api/v1`
+    pub fn new(base_url: &str) -> Result<Self> {
+        Ok(Client {
+            http: reqwest::blocking::Client::new(),
+            base_url: normalize_base_url(base_url)?,
+        })
+    }
+
+    /// Creates a client for the API the launcher started this game with
+    pub fn from_env() -> Result<Self> {
+        Client::new(&crate::api_url_from_env())
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Adds an entry to the leaderboard of the running game
+    pub fn set_leaderboard(&self, entry: &LeaderboardPost) -> Result<LeaderboardPost> {
+        let request = self
+            .http
+            .post(format!("{}/leaderboard", self.base_url))
+            .json(entry);
+        read_response(request.send()?)
+    }
+
+    /// Adds several leaderboard entries of the running game at once. Returns whether each entry
+    /// was stored.
+    pub fn set_leaderboard_batch(
+        &self,
+        batch: &LeaderboardBatch,
+    ) -> Result<Vec<LeaderboardBatchResult>> {
+        let request = self
+            .http
+            .post(format!("{}/leaderboard/batch", self.base_url))
+            .json(batch);
+        read_response(request.send()?)
+    }
+
+    /// Gets leaderboard entries of the running game
+    pub fn get_leaderboard(&self, params: &LeaderboardGetParams) -> Result<Vec<LeaderboardEntry>> {
+        let request = self
+            .http
+            .get(format!("{}/leaderboard", self.base_url))
+            .query(params);
+        read_response(request.send()?)
+    }
+
+    /// Gets statistics of every entry of a leaderboard metric of the running game
+    pub fn get_leaderboard_stats(
+        &self,
+        params: &LeaderboardStatsParams,
+    ) -> Result<LeaderboardStats> {
+        let request = self
+            .http
+            .get(format!("{}/leaderboard/stats", self.base_url))
+            .query(params);
+        read_response(request.send()?)
+    }
+
+    /// Gets today's daily challenge of the running game, with the seed every player uses
+    pub fn get_daily_challenge(&self) -> Result<DailyChallenge> {
+        let request = self.http.get(format!("{}/daily-challenge", self.base_url));
+        read_response(request.send()?)
+    }
+
+    /// Gets the attachment of a leaderboard entry of the running game, such as the replay of the
+    /// top score
+    pub fn get_leaderboard_attachment(&self, id: i32) -> Result<Vec<u8>> {
+        let request = self
+            .http
+            .get(format!("{}/leaderboard/{}/attachment", self.base_url, id));
+        let response = request.send()?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(from_status(status, response.text().unwrap_or_default()));
+        }
+        Ok(response.bytes()?.to_vec())
+    }
+
+    /// Creates or replaces a save file of the running game
+    pub fn set_save_data(&self, save: &SaveDataPost) -> Result<SaveDataPost> {
+        let request = self
+            .http
+            .post(format!("{}/save-data", self.base_url))
+            .json(save);
+        read_response(request.send()?)
+    }
+
+    /// Replaces a save file of the running game only if it's still at the given revision. See
+    /// [`crate::Client::set_save_data_if`].
+    pub fn set_save_data_if(&self, save: &SaveDataPost, revision: i32) -> Result<SaveDataPost> {
+        let request = self
+            .http
+            .post(format!("{}/save-data", self.base_url))
+            .header(reqwest::header::IF_MATCH, crate::etag(revision))
+            .json(save);
+        read_response(request.send()?)
+    }
+
+    /// Applies a patch to a JSON save file of the running game and returns the patched save file
+    pub fn patch_save_data(
+        &self,
+        params: &SaveDataPatchParams,
+        patch: &SaveDataPatch,
+    ) -> Result<SaveDataEntry> {
+        let request = self
+            .http
+            .patch(format!("{}/save-data", self.base_url))
+            .query(params)
+            .header(reqwest::header::CONTENT_TYPE, patch.content_type())
+            .json(patch.body());
+        read_response(request.send()?)
+    }
+
+    /// Writes, patches and deletes several save files of the running game at once. If any
+    /// operation fails, none of them are applied.
+    pub fn write_save_data_batch(&self, batch: &SaveDataBatch) -> Result<Vec<SaveDataBatchResult>> {
+        let request = self
+            .http
+            .post(format!("{}/save-data/batch", self.base_url))
+            .json(batch);
+        read_response(request.send()?)
+    }
+
+    /// Gets save files of the running game
+    pub fn get_save_data(&self, params: &SaveDataGetParams) -> Result<Vec<SaveDataEntry>> {
+        let request = self
+            .http
+            .get(format!("{}/save-data", self.base_url))
+            .query(params);
+        read_response(request.send()?)
+    }
+
+    /// Gets a global of the running game, shared by every player
+    pub fn get_global(&self, key: &str) -> Result<GlobalEntry> {
+        let request = self.http.get(format!("{}/globals/{}", self.base_url, key));
+        read_response(request.send()?)
+    }
+
+    /// Sets a global of the running game, replacing whatever it held
+    pub fn set_global(&self, key: &str, value: &serde_json::Value) -> Result<GlobalEntry> {
+        let request = self
+            .http
+            .post(format!("{}/globals/{}", self.base_url, key))
+            .json(&GlobalPost {
+                value: value.clone(),
+            });
+        read_response(request.send()?)
+    }
+
+    /// Replaces a global of the running game only if it's still at the given revision. Returns
+    /// [`Error::RevisionMismatch`](crate::Error::RevisionMismatch) if it was written since, in
+    /// which case the global should be read again.
+    pub fn set_global_if(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+        revision: i32,
+    ) -> Result<GlobalEntry> {
+        let request = self
+            .http
+            .post(format!("{}/globals/{}", self.base_url, key))
+            .header(reqwest::header::IF_MATCH, crate::etag(revision))
+            .json(&GlobalPost {
+                value: value.clone(),
+            });
+        read_response(request.send()?)
+    }
+
+    /// Sets a global of the running game only if it was never set, such as a shared world seed.
+    /// Returns [`Error::RevisionMismatch`](crate::Error::RevisionMismatch) if another session
+    /// set it first.
+    pub fn create_global(&self, key: &str, value: &serde_json::Value) -> Result<GlobalEntry> {
+        let request = self
+            .http
+            .post(format!("{}/globals/{}", self.base_url, key))
+            .header(reqwest::header::IF_NONE_MATCH, "*")
+            .json(&GlobalPost {
+                value: value.clone(),
+            });
+        read_response(request.send()?)
+    }
+
+    /// Adds to a global of the running game holding a number and returns the new value. The
+    /// console adds it atomically, so increments from several sessions are never lost.
+    pub fn increment_global(&self, key: &str, by: f64) -> Result<GlobalEntry> {
+        let request = self
+            .http
+            .post(format!("{}/globals/{}/increment", self.base_url, key))
+            .json(&GlobalIncrement { by });
+        read_response(request.send()?)
+    }
+
+    /// Adds to a lifetime stat of a player in the running game, such as total jumps, and returns
+    /// the new value. The console adds it atomically, so no increment is lost.
+    pub fn increment_stat(&self, increment: &StatIncrement) -> Result<StatEntry> {
+        let request = self
+            .http
+            .post(format!("{}/stats/increment", self.base_url))
+            .json(increment);
+        read_response(request.send()?)
+    }
+
+    /// Gets lifetime stats of the players of the running game
+    pub fn get_stats(&self, params: &StatsGetParams) -> Result<Vec<StatEntry>> {
+        let request = self
+            .http
+            .get(format!("{}/stats", self.base_url))
+            .query(params);
+        read_response(request.send()?)
+    }
+
+    /// Gets how much of its storage quotas the running game uses
+    pub fn get_storage(&self, params: &StorageGetParams) -> Result<StorageUsage> {
+        let request = self
+            .http
+            .get(format!("{}/storage", self.base_url))
+            .query(params);
+        read_response(request.send()?)
+    }
+}
+
+fn read_response<T: DeserializeOwned>(response: reqwest::blocking::Response) -> Result<T> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(from_status(status, response.text().unwrap_or_default()));
+    }
+    Ok(response.json()?)
+}
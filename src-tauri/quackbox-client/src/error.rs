@@ -0,0 +1,90 @@
+use crate::types::{QuotaError, ValidationError};
+use std::fmt;
+
+/// Errors returned by the game dev API clients
+#[derive(Debug)]
+pub enum Error {
+    /// The base url is not a valid http url
+    InvalidUrl(String),
+    /// The launcher isn't running a game, so there is no game to read or write data for
+    NoGameRunning,
+    /// A save was refused because it would go over one of the game's storage quotas
+    QuotaExceeded(QuotaError),
+    /// A leaderboard entry broke one of the validation rules, such as a value that isn't finite
+    Invalid(ValidationError),
+    /// A conditional save or global write was refused because it was written since it was
+    /// read, or a global to create already exists
+    RevisionMismatch,
+    /// The API rejected the request, such as when asking for more than 100 leaderboard entries
+    Api { status: u16, message: String },
+    /// The request couldn't be sent or the response couldn't be read
+    Http(reqwest::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidUrl(url) => write!(f, "Invalid game dev API url: {}", url),
+            Error::NoGameRunning => write!(f, "No game is currently running"),
+            Error::QuotaExceeded(error) => write!(f, "{}", error.message),
+            Error::Invalid(error) => write!(f, "{}", error.message),
+            Error::RevisionMismatch => {
+                write!(f, "The save file or global was changed since it was read")
+            }
+            Error::Api { status, message } if message.is_empty() => {
+                write!(f, "Game dev API responded with status {}", status)
+            }
+            Error::Api { status, message } => {
+                write!(
+                    f,
+                    "Game dev API responded with status {}: {}",
+                    status, message
+                )
+            }
+            Error::Http(e) => write!(f, "Game dev API request failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+/// Turns an unsuccessful response into an [`Error`]
+pub(crate) fn from_status(status: reqwest::StatusCode, message: String) -> Error {
+    if status == reqwest::StatusCode::CONFLICT {
+        return Error::NoGameRunning;
+    }
+    if status == reqwest::StatusCode::UNPROCESSABLE_ENTITY {
+        if let Ok(error) = serde_json::from_str::<ValidationError>(&message) {
+            return Error::Invalid(error);
+        }
+    }
+    if status == reqwest::StatusCode::PRECONDITION_FAILED {
+        return Error::RevisionMismatch;
+    }
+    if status == reqwest::StatusCode::PAYLOAD_TOO_LARGE
+        || status == reqwest::StatusCode::INSUFFICIENT_STORAGE
+    {
+        if let Ok(error) = serde_json::from_str::<QuotaError>(&message) {
+            return Error::QuotaExceeded(error);
+        }
+    }
+    Error::Api {
+        status: status.as_u16(),
+        message,
+    }
+}
@@ -0,0 +1,372 @@
+//! A typed client for the QuackBox game dev API, for Rust games running on the console.
+//!
+//! The launcher tells games where the API is through the `QUACKBOX_API_URL` environment
+//! variable, so [`Client::from_env`] is all most games need.
+//!
+//! ```no_run
+//! use quackbox_client::{types::LeaderboardPost, Client};
+//!
+//! async fn submit_score(score: f64) -> quackbox_client::Result<()> {
+//!     let client = Client::from_env()?;
+//!     client
+//!         .set_leaderboard(&LeaderboardPost {
+//!             value_name: "Score".to_string(),
+//!             value_num: Some(score),
+//!             player_slot: 1,
+//!             ..Default::default()
+//!         })
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Games without an async runtime can use [`blocking::Client`] instead, which has the same
+//! methods. Disable the default features to only use the [`types`].
+
+pub mod types;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(any(feature = "async", feature = "blocking"))]
+mod error;
+
+#[cfg(any(feature = "async", feature = "blocking"))]
+pub use error::{Error, Result};
+
+/// Environment variable the launcher sets to the base url of the game dev API
+pub const API_URL_ENV: &str = "QUACKBOX_API_URL";
+
+/// Base url of the game dev API on a console with the default settings
+pub const DEFAULT_API_URL: &str = "http://127.0.0.1:6174/api/v1";
+
+/// Returns the base url from [`API_URL_ENV`], falling back to [`DEFAULT_API_URL`] when it isn't
+/// set, such as when the game is started outside of the launcher.
+pub fn api_url_from_env() -> String {
+    std::env::var(API_URL_ENV).unwrap_or_else(|_| DEFAULT_API_URL.to_string())
+}
+
+/// Entity tag of a save file at the given revision, as sent in `ETag` and `If-Match` headers
+pub fn etag(revision: i32) -> String {
+    format!("\"{}\"", revision)
+}
+
+/// Checks that the base url is an http url and removes any trailing slashes
+#[cfg(any(feature = "async", feature = "blocking"))]
+fn normalize_base_url(base_url: &str) -> Result<String> {
+    let url = reqwest::Url::parse(base_url).map_err(|_| Error::InvalidUrl(base_url.to_string()))?;
+    if !["http", "https"].contains(&url.scheme()) {
+        return Err(Error::InvalidUrl(base_url.to_string()));
+    }
+    Ok(base_url.trim_end_matches('/').to_string())
+}
+
+#[cfg(feature = "async")]
+pub use async_client::Client;
+
+#[cfg(feature = "async")]
+mod async_client {
+    use crate::{
+        error::from_status,
+        normalize_base_url,
+        types::{
+            DailyChallenge, GlobalEntry, GlobalIncrement, GlobalPost, LeaderboardBatch,
+            LeaderboardBatchResult, LeaderboardEntry, LeaderboardGetParams, LeaderboardPost,
+            LeaderboardStats, LeaderboardStatsParams, SaveDataBatch, SaveDataBatchResult,
+            SaveDataEntry, SaveDataGetParams, SaveDataPatch, SaveDataPatchParams, SaveDataPost,
+            StatEntry, StatIncrement, StatsGetParams, StorageGetParams, StorageUsage,
+        },
+        Result,
+    };
+    use serde::de::DeserializeOwned;
+
+    /// An async client for the game dev API
+    #[derive(Debug, Clone)]
+    pub struct Client {
+        http: reqwest::Client,
+        base_url: String,
+    }
+
+    impl Client {
+        /// Creates a client for the API at the given base url, such as
+        /// `http://127.0.0.1:6174/api/v1`
+        pub fn new(base_url: &str) -> Result<Self> {
+            Ok(Client {
+                http: reqwest::Client::new(),
+                base_url: normalize_base_url(base_url)?,
+            })
+        }
+
+        /// Creates a client for the API the launcher started this game with
+        pub fn from_env() -> Result<Self> {
+            Client::new(&crate::api_url_from_env())
+        }
+
+        pub fn base_url(&self) -> &str {
+            &self.base_url
+        }
+
+        /// Adds an entry to the leaderboard of the running game
+        pub async fn set_leaderboard(&self, entry: &LeaderboardPost) -> Result<LeaderboardPost> {
+            let request = self
+                .http
+                .post(format!("{}/leaderboard", self.base_url))
+                .json(entry);
+            read_response(request.send().await?).await
+        }
+
+        /// Adds several leaderboard entries of the running game at once, such as every player's
+        /// score at the end of a match. Returns whether each entry was stored.
+        pub async fn set_leaderboard_batch(
+            &self,
+            batch: &LeaderboardBatch,
+        ) -> Result<Vec<LeaderboardBatchResult>> {
+            let request = self
+                .http
+                .post(format!("{}/leaderboard/batch", self.base_url))
+                .json(batch);
+            read_response(request.send().await?).await
+        }
+
+        /// Gets leaderboard entries of the running game
+        pub async fn get_leaderboard(
+            &self,
+            params: &LeaderboardGetParams,
+        ) -> Result<Vec<LeaderboardEntry>> {
+            let request = self
+                .http
+                .get(format!("{}/leaderboard", self.base_url))
+                .query(params);
+            read_response(request.send().await?).await
+        }
+
+        /// Gets statistics of every entry of a leaderboard metric of the running game
+        pub async fn get_leaderboard_stats(
+            &self,
+            params: &LeaderboardStatsParams,
+        ) -> Result<LeaderboardStats> {
+            let request = self
+                .http
+                .get(format!("{}/leaderboard/stats", self.base_url))
+                .query(params);
+            read_response(request.send().await?).await
+        }
+
+        /// Gets today's daily challenge of the running game, with the seed every player uses
+        pub async fn get_daily_challenge(&self) -> Result<DailyChallenge> {
+            let request = self.http.get(format!("{}/daily-challenge", self.base_url));
+            read_response(request.send().await?).await
+        }
+
+        /// Gets the attachment of a leaderboard entry of the running game, such as the replay
+        /// of the top score
+        pub async fn get_leaderboard_attachment(&self, id: i32) -> Result<Vec<u8>> {
+            let request = self
+                .http
+                .get(format!("{}/leaderboard/{}/attachment", self.base_url, id));
+            let response = request.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(from_status(
+                    status,
+                    response.text().await.unwrap_or_default(),
+                ));
+            }
+            Ok(response.bytes().await?.to_vec())
+        }
+
+        /// Creates or replaces a save file of the running game
+        pub async fn set_save_data(&self, save: &SaveDataPost) -> Result<SaveDataPost> {
+            let request = self
+                .http
+                .post(format!("{}/save-data", self.base_url))
+                .json(save);
+            read_response(request.send().await?).await
+        }
+
+        /// Replaces a save file of the running game only if it's still at the given revision,
+        /// so a read-modify-write doesn't lose progress written in between. Returns
+        /// [`Error::RevisionMismatch`](crate::Error::RevisionMismatch) if it was written since,
+        /// in which case the save should be read again.
+        pub async fn set_save_data_if(
+            &self,
+            save: &SaveDataPost,
+            revision: i32,
+        ) -> Result<SaveDataPost> {
+            let request = self
+                .http
+                .post(format!("{}/save-data", self.base_url))
+                .header(reqwest::header::IF_MATCH, crate::etag(revision))
+                .json(save);
+            read_response(request.send().await?).await
+        }
+
+        /// Applies a patch to a JSON save file of the running game and returns the patched
+        /// save file, so only the changed parts of a large save have to be sent
+        pub async fn patch_save_data(
+            &self,
+            params: &SaveDataPatchParams,
+            patch: &SaveDataPatch,
+        ) -> Result<SaveDataEntry> {
+            let request = self
+                .http
+                .patch(format!("{}/save-data", self.base_url))
+                .query(params)
+                .header(reqwest::header::CONTENT_TYPE, patch.content_type())
+                .json(patch.body());
+            read_response(request.send().await?).await
+        }
+
+        /// Writes, patches and deletes several save files of the running game at once. If any
+        /// operation fails, none of them are applied.
+        pub async fn write_save_data_batch(
+            &self,
+            batch: &SaveDataBatch,
+        ) -> Result<Vec<SaveDataBatchResult>> {
+            let request = self
+                .http
+                .post(format!("{}/save-data/batch", self.base_url))
+                .json(batch);
+            read_response(request.send().await?).await
+        }
+
+        /// Gets save files of the running game
+        pub async fn get_save_data(
+            &self,
+            params: &SaveDataGetParams,
+        ) -> Result<Vec<SaveDataEntry>> {
+            let request = self
+                .http
+                .get(format!("{}/save-data", self.base_url))
+                .query(params);
+            read_response(request.send().await?).await
+        }
+
+        /// Gets a global of the running game, shared by every player
+        pub async fn get_global(&self, key: &str) -> Result<GlobalEntry> {
+            let request = self.http.get(format!("{}/globals/{}", self.base_url, key));
+            read_response(request.send().await?).await
+        }
+
+        /// Sets a global of the running game, replacing whatever it held
+        pub async fn set_global(
+            &self,
+            key: &str,
+            value: &serde_json::Value,
+        ) -> Result<GlobalEntry> {
+            let request = self
+                .http
+                .post(format!("{}/globals/{}", self.base_url, key))
+                .json(&GlobalPost {
+                    value: value.clone(),
+                });
+            read_response(request.send().await?).await
+        }
+
+        /// Replaces a global of the running game only if it's still at the given revision. Returns
+        /// [`Error::RevisionMismatch`](crate::Error::RevisionMismatch) if it was written since, in
+        /// which case the global should be read again.
+        pub async fn set_global_if(
+            &self,
+            key: &str,
+            value: &serde_json::Value,
+            revision: i32,
+        ) -> Result<GlobalEntry> {
+            let request = self
+                .http
+                .post(format!("{}/globals/{}", self.base_url, key))
+                .header(reqwest::header::IF_MATCH, crate::etag(revision))
+                .json(&GlobalPost {
+                    value: value.clone(),
+                });
+            read_response(request.send().await?).await
+        }
+
+        /// Sets a global of the running game only if it was never set, such as a shared world seed.
+        /// Returns [`Error::RevisionMismatch`](crate::Error::RevisionMismatch) if another session
+        /// set it first.
+        pub async fn create_global(
+            &self,
+            key: &str,
+            value: &serde_json::Value,
+        ) -> Result<GlobalEntry> {
+            let request = self
+                .http
+                .post(format!("{}/globals/{}", self.base_url, key))
+                .header(reqwest::header::IF_NONE_MATCH, "*")
+                .json(&GlobalPost {
+                    value: value.clone(),
+                });
+            read_response(request.send().await?).await
+        }
+
+        /// Adds to a global of the running game holding a number and returns the new value. The
+        /// console adds it atomically, so increments from several sessions are never lost.
+        pub async fn increment_global(&self, key: &str, by: f64) -> Result<GlobalEntry> {
+            let request = self
+                .http
+                .post(format!("{}/globals/{}/increment", self.base_url, key))
+                .json(&GlobalIncrement { by });
+            read_response(request.send().await?).await
+        }
+
+        /// Adds to a lifetime stat of a player in the running game, such as total jumps, and returns
+        /// the new value. The console adds it atomically, so no increment is lost.
+        pub async fn increment_stat(&self, increment: &StatIncrement) -> Result<StatEntry> {
+            let request = self
+                .http
+                .post(format!("{}/stats/increment", self.base_url))
+                .json(increment);
+            read_response(request.send().await?).await
+        }
+
+        /// Gets lifetime stats of the players of the running game
+        pub async fn get_stats(&self, params: &StatsGetParams) -> Result<Vec<StatEntry>> {
+            let request = self
+                .http
+                .get(format!("{}/stats", self.base_url))
+                .query(params);
+            read_response(request.send().await?).await
+        }
+
+        /// Gets how much of its storage quotas the running game uses
+        pub async fn get_storage(&self, params: &StorageGetParams) -> Result<StorageUsage> {
+            let request = self
+                .http
+                .get(format!("{}/storage", self.base_url))
+                .query(params);
+            read_response(request.send().await?).await
+        }
+    }
+
+    async fn read_response<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+        let status = response.status();
+        if !status.is_success() {
+            return Err(from_status(
+                status,
+                response.text().await.unwrap_or_default(),
+            ));
+        }
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(all(test, any(feature = "async", feature = "blocking")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_base_url() {
+        assert_eq!(
+            normalize_base_url("http://127.0.0.1:6174/api/v1/").unwrap(),
+            "http://127.0.0.1:6174/api/v1"
+        );
+        assert!(matches!(
+            normalize_base_url("127.0.0.1:6174"),
+            Err(Error::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            normalize_base_url("ftp://127.0.0.1/api/v1"),
+            Err(Error::InvalidUrl(_))
+        ));
+    }
+}
@@ -0,0 +1,466 @@
+//! Request and response bodies of the game dev API. These are shared with the console's
+//! backend, so they always match what the API accepts and returns.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Body of a leaderboard post request. The API responds with the same fields.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardPost {
+    /// Name of the value, such as `score` or `time`
+    pub value_name: String,
+    /// The value. Has to be given unless the metric is composite, which takes it from `values`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_num: Option<f64>,
+    /// Player slot (1 to 8) of the player the entry belongs to
+    pub player_slot: i16,
+    /// Any JSON value giving context to the entry, such as the character, level or seed used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    /// Base64 encoded binary data stored with the entry, such as a replay or ghost. Can be
+    /// fetched by other players with the entry's `id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<String>,
+    /// The team that set the entry together, such as the players of a co-op run.
+    /// `player_slot` is always one of its members.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<LeaderboardTeam>,
+    /// Every value of an entry for a composite metric by name, such as
+    /// `{"score": 1200, "time": 83.2}`. The console declares which values a composite metric
+    /// is ranked by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<BTreeMap<String, f64>>,
+    /// Day of the daily challenge the entry was set in, as given by the daily challenge
+    /// endpoint. Entries can be set in today's or yesterday's challenge, and are only listed on
+    /// the leaderboard of their challenge.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge_date: Option<String>,
+}
+
+/// A group of players sharing a leaderboard entry
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardTeam {
+    /// Name of the team, at most 64 characters
+    pub name: String,
+    /// Player slots of every member of the team, at most 8
+    pub player_slots: Vec<i16>,
+}
+
+/// Body of a leaderboard batch post request, such as every player's score at the end of a match
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardBatch {
+    pub entries: Vec<LeaderboardPost>,
+}
+
+/// What happened to one entry of a leaderboard batch
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardEntryStatus {
+    Stored,
+    /// The player already has an entry with the same name and value, so nothing was added
+    Deduplicated,
+    Rejected,
+}
+
+/// Result of one entry of a leaderboard batch
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardBatchResult {
+    pub value_name: String,
+    pub player_slot: i16,
+    pub status: LeaderboardEntryStatus,
+    /// Why the entry was rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Body of a save data post request. The API responds with the same fields.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SaveDataPost {
+    /// Name of the save file. Posting the same file name again replaces the file.
+    pub file_name: String,
+    /// Any JSON value
+    pub data: serde_json::Value, // This data should be stored in the database as BSON data, is this the correct type?
+    pub player_slot: i16,
+}
+
+/// Query parameters of a leaderboard get request. Every parameter is optional.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema, utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct LeaderboardGetParams {
+    /// Number of entries to return, at most 100. Defaults to 100.
+    pub count: Option<i64>,
+    /// Sort from lowest to highest value instead of highest to lowest. Entries of a composite
+    /// metric are sorted by each of its values in turn, and this reverses all of them.
+    pub ascending: Option<bool>,
+    pub value_name: Option<String>,
+    /// Number of entries to skip
+    pub offset: Option<i64>,
+    /// Only return entries of this player slot
+    pub player_slot: Option<i16>,
+    /// Return entries of the daily challenge of this day, such as `2026-10-19`, instead of
+    /// entries set outside of daily challenges
+    pub challenge_date: Option<String>,
+}
+
+/// Query parameters of a leaderboard statistics request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema, utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct LeaderboardStatsParams {
+    pub value_name: String,
+    /// Comma separated percentiles to compute, each from 0 to 100, such as `50,90,99`. At most
+    /// 20 can be asked for. Defaults to `25,50,75,90,99`.
+    pub percentiles: Option<String>,
+    /// Number of equally wide histogram buckets, from 1 to 100. Defaults to 10.
+    pub buckets: Option<u32>,
+    /// A value to find the percentile of, such as the score of the run just played
+    pub value: Option<f64>,
+    /// Lower values are better, such as for times. Only changes the percentile of `value`, and
+    /// is ignored for composite metrics, which are ranked by their first component.
+    pub ascending: Option<bool>,
+    /// Use the entries of the daily challenge of this day instead of entries set outside of
+    /// daily challenges
+    pub challenge_date: Option<String>,
+}
+
+/// Query parameters of a save data get request. Every parameter is optional, but
+/// `file_name` and `regex` can't be used together.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema, utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct SaveDataGetParams {
+    pub file_name: Option<String>,
+    /// Only return save files with names matching this regular expression
+    pub regex: Option<String>,
+    /// Only return save files of this player slot
+    pub player_slot: Option<i16>,
+}
+
+/// Query parameters of a save data patch request, naming the save file to patch
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema, utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct SaveDataPatchParams {
+    pub file_name: String,
+    pub player_slot: i16,
+}
+
+/// Content type of an RFC 6902 JSON Patch body
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+
+/// Content type of an RFC 7396 JSON Merge Patch body
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// Body of a save data patch request
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveDataPatch {
+    /// A list of RFC 6902 operations, such as `{"op": "add", "path": "/items/-", "value": "key"}`
+    JsonPatch(serde_json::Value),
+    /// An RFC 7396 document of fields to replace. Fields set to `null` are removed.
+    MergePatch(serde_json::Value),
+}
+
+impl SaveDataPatch {
+    /// Content type the patch is sent with
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SaveDataPatch::JsonPatch(_) => JSON_PATCH_CONTENT_TYPE,
+            SaveDataPatch::MergePatch(_) => MERGE_PATCH_CONTENT_TYPE,
+        }
+    }
+
+    pub fn body(&self) -> &serde_json::Value {
+        match self {
+            SaveDataPatch::JsonPatch(body) | SaveDataPatch::MergePatch(body) => body,
+        }
+    }
+}
+
+/// One operation of a save data batch
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SaveDataOperation {
+    /// Creates or replaces a save file
+    Set {
+        file_name: String,
+        player_slot: i16,
+        data: serde_json::Value,
+        /// Only write the save file if it's at this revision
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        if_revision: Option<i32>,
+    },
+    /// Applies an RFC 7396 JSON Merge Patch to an existing JSON save file
+    Merge {
+        file_name: String,
+        player_slot: i16,
+        patch: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        if_revision: Option<i32>,
+    },
+    /// Deletes a save file along with its revisions
+    Delete {
+        file_name: String,
+        player_slot: i16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        if_revision: Option<i32>,
+    },
+}
+
+/// Body of a save data batch request. The operations are applied in order, and either all of
+/// them are applied or none are.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SaveDataBatch {
+    pub operations: Vec<SaveDataOperation>,
+}
+
+/// Result of one operation of a save data batch
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SaveDataBatchResult {
+    pub file_name: String,
+    pub player_slot: i16,
+    /// Revision of the save file after the operation, or null if it was deleted
+    pub revision: Option<i32>,
+}
+
+/// A single leaderboard entry returned by a leaderboard get request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardEntry {
+    /// Id of the entry, used to get its attachment
+    #[serde(default)]
+    pub id: i32,
+    pub value_name: String,
+    pub value_num: f64,
+    pub player_slot: i16,
+    /// When the entry was posted
+    pub time_stamp: String,
+    /// The `details` the entry was posted with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    /// Whether the entry was posted with an attachment
+    #[serde(default)]
+    pub has_attachment: bool,
+    /// The team that set the entry, with every member's player slot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<LeaderboardTeam>,
+    /// Every value of an entry for a composite metric, in the order the entries are ranked by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<LeaderboardComponent>>,
+    /// Day of the daily challenge the entry was set in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub challenge_date: Option<String>,
+}
+
+/// One of the values of a leaderboard entry for a composite metric
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardComponent {
+    pub name: String,
+    pub value: f64,
+}
+
+/// Today's daily challenge of the running game, which every player plays with the same seed
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DailyChallenge {
+    /// The day of the challenge in the console's time zone, such as `2026-10-19`. Post entries
+    /// of the challenge with it as their `challenge_date`.
+    pub date: String,
+    /// Seed to generate the challenge's level from. It's the same all day, but can't be worked
+    /// out before the day starts.
+    pub seed: u32,
+}
+
+/// Statistics of every entry of a leaderboard metric. Everything but `count` is left out when
+/// there are no entries.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardStats {
+    pub count: i64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    /// The requested percentiles, interpolated between the two closest entries
+    pub percentiles: Vec<LeaderboardPercentile>,
+    /// Number of entries in equally wide buckets from `min` to `max`
+    pub histogram: Vec<HistogramBucket>,
+    /// Percentage of entries the requested `value` beats, from 0 to 100. Entries with the same
+    /// value aren't beaten.
+    pub value_percentile: Option<f64>,
+}
+
+/// A percentile of a leaderboard metric
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LeaderboardPercentile {
+    /// The percentile, from 0 to 100
+    pub percentile: f64,
+    pub value: f64,
+}
+
+/// A bucket of a leaderboard histogram, holding the entries from `min` up to but not including
+/// `max`. The last bucket includes `max`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HistogramBucket {
+    pub min: f64,
+    pub max: f64,
+    pub count: i64,
+}
+
+/// A single save file returned by a save data get request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SaveDataEntry {
+    pub file_name: String,
+    pub data: serde_json::Value,
+    pub player_slot: i16,
+    /// When the save file was last written
+    pub time_stamp: String,
+    /// Goes up by one every time the save file is written. Pass it to `set_save_data_if` to
+    /// only replace the save file if nothing else wrote it since it was read.
+    #[serde(default)]
+    pub revision: i32,
+}
+
+/// Body of a global set request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct GlobalPost {
+    pub value: serde_json::Value,
+}
+
+/// Body of a global increment request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct GlobalIncrement {
+    /// Amount to add, which can be negative
+    pub by: f64,
+}
+
+/// A value of the running game shared by every player, such as a community goal
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct GlobalEntry {
+    pub key: String,
+    pub value: serde_json::Value,
+    /// Goes up by one every time the global is written. Pass it to `set_global_if` to only
+    /// replace the global if nothing else wrote it since it was read.
+    pub revision: i32,
+    /// When the global was last written
+    pub time_stamp: String,
+}
+
+/// Body of a stat increment request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StatIncrement {
+    pub player_slot: i16,
+    /// Name of the stat, such as `jumps`
+    pub name: String,
+    /// Amount to add, which can be negative. At most a billion either way.
+    pub by: i64,
+}
+
+/// Query parameters of a stats get request. Every parameter is optional.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema, utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct StatsGetParams {
+    /// Only return stats of this player slot
+    pub player_slot: Option<i16>,
+    /// Only return stats with this name
+    pub name: Option<String>,
+}
+
+/// A lifetime stat of a player, such as total jumps
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StatEntry {
+    pub player_slot: i16,
+    pub name: String,
+    pub value: i64,
+    /// When the stat was last changed
+    pub time_stamp: String,
+}
+
+/// Query parameters of a storage get request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema, utoipa::IntoParams),
+    into_params(parameter_in = Query)
+)]
+pub struct StorageGetParams {
+    /// Count the save files of this player slot instead of every player
+    pub player_slot: Option<i16>,
+}
+
+/// How much of its storage quotas the running game uses
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StorageUsage {
+    /// Total size of the game's save files in bytes. Large save files are compressed, so this
+    /// can be less than the size of the JSON that was posted.
+    pub used_bytes: i64,
+    /// Largest total size of the game's save files in bytes
+    pub max_bytes: i64,
+    /// Number of save files, of the given player slot if there is one
+    pub save_count: i64,
+    /// Largest number of save files each player can have
+    pub max_saves_per_player: i64,
+    /// Largest size of a single save file in bytes
+    pub max_save_bytes: i64,
+}
+
+/// Body of the response when a save is refused because it would go over a storage quota
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct QuotaError {
+    /// One of `save_too_large`, `too_many_saves` or `storage_full`
+    pub code: String,
+    pub message: String,
+    /// The quota that would be exceeded
+    pub limit: i64,
+    /// What the quota would have been used up to with the save
+    pub requested: i64,
+}
+
+/// Body of the response when a leaderboard entry breaks one of the validation rules
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ValidationError {
+    /// One of `value_not_finite`, `value_name_empty`, `value_name_too_long`,
+    /// `value_name_invalid`, `value_not_integer`, `team_name_empty`, `team_name_too_long`,
+    /// `team_too_large`, `component_missing`, `component_unknown` or `values_not_allowed`
+    pub code: String,
+    pub message: String,
+    /// Field of the request that broke the rule, such as `value_name`
+    pub field: String,
+}
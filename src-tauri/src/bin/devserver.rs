@@ -0,0 +1,244 @@
+//! A standalone copy of the game dev API, so game developers can test their leaderboard and
+//! save data integration without running the whole launcher.
+//!
+//! ```sh
+//! cargo run --bin quackbox-devserver -- --game-id 12 --players 2 --username 1=Alice
+//! ```
+//!
+//! While running, commands typed into the terminal switch or clear the running game.
+//! Every request and response is printed, so developers can see what their game sent.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use clap::Parser;
+use quackbox_backend::{
+    db::{make_sure_game_exists, setup_db, upsert_user},
+    game_dev_api::{
+        create_router,
+        handlers::{GameState, GameStateShared},
+    },
+};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    sync::{watch, Notify, RwLock},
+};
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+/// Request bodies larger than this are rejected instead of printed
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "quackbox-devserver",
+    about = "Runs the QuackBox game dev API against a local SQLite file"
+)]
+struct Args {
+    /// SQLite database file for leaderboard and save data. Created if it doesn't exist.
+    #[arg(long, default_value = "quackbox-dev.db")]
+    db: PathBuf,
+
+    /// Address to listen on
+    #[arg(long, default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    host: IpAddr,
+
+    /// Port to listen on. Games expect 6174.
+    #[arg(long, default_value_t = 6174)]
+    port: u16,
+
+    /// Id of the fake game that is running when the server starts
+    #[arg(long, default_value_t = 1)]
+    game_id: u64,
+
+    /// Start without a running game, so requests are rejected until `game <id>` is entered
+    #[arg(long, conflicts_with = "game_id")]
+    no_game: bool,
+
+    /// Number of player slots to create users for
+    #[arg(long, default_value_t = 4)]
+    players: i16,
+
+    /// Username of a player slot as SLOT=NAME. Can be repeated.
+    #[arg(long = "username", value_name = "SLOT=NAME", value_parser = parse_username)]
+    usernames: Vec<(i16, String)>,
+}
+
+fn parse_username(s: &str) -> Result<(i16, String), String> {
+    let (slot, name) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected SLOT=NAME, found {s}"))?;
+    let slot = slot
+        .parse::<i16>()
+        .map_err(|e| format!("invalid player slot {slot}: {e}"))?;
+    Ok((slot, name.to_string()))
+}
+
+/// Prints the method, uri and body of every request, and the status of every response
+async fn trace_requests(request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!(method = %parts.method, uri = %parts.uri, error = %e, "Failed to read request body");
+            return (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()).into_response();
+        }
+    };
+
+    if bytes.is_empty() {
+        info!(method = %parts.method, uri = %parts.uri, "Request");
+    } else {
+        info!(
+            method = %parts.method,
+            uri = %parts.uri,
+            body = %String::from_utf8_lossy(&bytes),
+            "Request"
+        );
+    }
+
+    let start = Instant::now();
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    if response.status().is_success() {
+        info!(status = %response.status(), elapsed_ms, "Response");
+    } else {
+        warn!(status = %response.status(), elapsed_ms, "Response");
+    }
+    response
+}
+
+/// Makes sure the fake game exists so its leaderboard and save data can be stored
+fn create_game(game_id: u64, db_path: &str) {
+    make_sure_game_exists(
+        &format!("Dev Game {game_id}"),
+        &game_id.to_string(),
+        db_path,
+    );
+}
+
+/// Sends a game id to the router and waits until it has been applied
+async fn set_running_game(
+    game_id: Option<u64>,
+    tx: &watch::Sender<Option<u64>>,
+    notifier: &Notify,
+    db_path: &str,
+) {
+    if let Some(game_id) = game_id {
+        create_game(game_id, db_path);
+    }
+    tx.send(game_id)
+        .expect("The router should listen to the game channel while the server runs");
+    notifier.notified().await;
+}
+
+fn print_help() {
+    println!("Commands:");
+    println!("  game <id>  run a different fake game");
+    println!("  clear      stop the running game, so requests are rejected");
+    println!("  status     show the running game");
+    println!("  help       show this message");
+    println!("  quit       stop the server");
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("info,quackbox_backend=debug")),
+        )
+        .init();
+
+    let db_path = args.db.to_string_lossy().into_owned();
+    setup_db(&db_path);
+
+    for slot in 1..=args.players {
+        let name = args
+            .usernames
+            .iter()
+            .rev()
+            .find(|(username_slot, _)| *username_slot == slot)
+            .map(|(_, name)| name.clone())
+            .unwrap_or_else(|| format!("Player {slot}"));
+        upsert_user(&slot.to_string(), &name, &db_path);
+        println!("Player slot {slot}: {name}");
+    }
+
+    let initial_game = if args.no_game {
+        None
+    } else {
+        create_game(args.game_id, &db_path);
+        Some(args.game_id)
+    };
+
+    let (game_tx, game_rx) = watch::channel(initial_game);
+    let notifier = Arc::new(Notify::new());
+    let game_state: GameStateShared = Arc::new(GameState {
+        id: Arc::new(RwLock::new(None)),
+        notifier: Arc::clone(&notifier),
+        channel: game_rx,
+    });
+
+    let app = create_router(&db_path, Arc::clone(&game_state))
+        .await
+        .layer(middleware::from_fn(trace_requests));
+
+    let address = SocketAddr::new(args.host, args.port);
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind to {address}: {e}"));
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    println!("Game dev API listening on http://{address}/api/v1 and http://{address}/api/v2");
+    println!("Database: {db_path}");
+    match initial_game {
+        Some(game_id) => println!("Running game: {game_id}"),
+        None => println!("No game is running"),
+    }
+    print_help();
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("game"), Some(game_id)) => match game_id.parse::<u64>() {
+                Ok(game_id) => {
+                    set_running_game(Some(game_id), &game_tx, &notifier, &db_path).await;
+                    println!("Running game: {game_id}");
+                }
+                Err(e) => println!("Invalid game id {game_id}: {e}"),
+            },
+            (Some("clear"), None) => {
+                set_running_game(None, &game_tx, &notifier, &db_path).await;
+                println!("No game is running");
+            }
+            (Some("status"), None) => match *game_state.id.read().await {
+                Some(game_id) => println!("Running game: {game_id}"),
+                None => println!("No game is running"),
+            },
+            (Some("quit" | "exit"), None) => return,
+            (None, _) => {}
+            _ => print_help(),
+        }
+    }
+
+    // stdin was closed, for example when running in the background, so keep serving
+    server
+        .await
+        .expect("Server task panicked")
+        .expect("Server failed");
+}
@@ -0,0 +1,103 @@
+//! Compression of stored save data.
+//!
+//! Saves larger than [`COMPRESSION_THRESHOLD`] are compressed with zstd before they are stored,
+//! and the codec is recorded next to the data so reads can decompress it again. Saves written
+//! before compression was added are stored as [`Codec::Raw`] until they are next read or written.
+
+use anyhow::{bail, Error};
+use std::borrow::Cow;
+
+/// Saves smaller than this many bytes are stored as is, since compressing them saves little
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// zstd compression level. Low levels are nearly as small for JSON and much faster on the Pi.
+const ZSTD_LEVEL: i32 = 3;
+
+/// How the data of a save is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Raw,
+    Zstd,
+}
+
+impl Codec {
+    /// Name of the codec stored in the `codec` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Codec::Raw => "raw",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    /// # Errors
+    /// * If the codec name is unknown, such as when the database was written by a newer launcher
+    pub fn parse(name: &str) -> Result<Codec, Error> {
+        match name {
+            "raw" => Ok(Codec::Raw),
+            "zstd" => Ok(Codec::Zstd),
+            _ => bail!("Unknown save data codec {}", name),
+        }
+    }
+}
+
+/// Returns the codec and bytes to store for the given data. Data is only compressed if it's at
+/// least [`COMPRESSION_THRESHOLD`] bytes and compressing makes it smaller.
+pub fn encode(data: &[u8]) -> (Codec, Cow<'_, [u8]>) {
+    if data.len() < COMPRESSION_THRESHOLD {
+        return (Codec::Raw, Cow::Borrowed(data));
+    }
+    match zstd::bulk::compress(data, ZSTD_LEVEL) {
+        Ok(compressed) if compressed.len() < data.len() => (Codec::Zstd, Cow::Owned(compressed)),
+        _ => (Codec::Raw, Cow::Borrowed(data)),
+    }
+}
+
+/// Returns the original data of stored bytes
+///
+/// # Errors
+/// * If the codec is unknown or the data is corrupt
+pub fn decode(codec: Codec, stored: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    match codec {
+        Codec::Raw => Ok(Cow::Borrowed(stored)),
+        Codec::Zstd => Ok(Cow::Owned(zstd::stream::decode_all(stored)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compresses_large_data() {
+        let data = serde_json::to_vec(&vec!["grass"; 1000]).unwrap();
+        let (codec, stored) = encode(&data);
+        assert_eq!(codec, Codec::Zstd);
+        assert!(stored.len() < data.len() / 10);
+        assert_eq!(decode(codec, &stored).unwrap(), data);
+    }
+
+    #[test]
+    fn stores_small_and_incompressible_data_raw() {
+        let small = br#"{"level":3}"#;
+        assert_eq!(encode(small), (Codec::Raw, Cow::Borrowed(&small[..])));
+
+        // pseudo random bytes don't compress
+        let mut state: u32 = 1;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect();
+        assert_eq!(encode(&noise).0, Codec::Raw);
+    }
+
+    #[test]
+    fn parses_codec_names() {
+        for codec in [Codec::Raw, Codec::Zstd] {
+            assert_eq!(Codec::parse(codec.as_str()).unwrap(), codec);
+        }
+        assert!(Codec::parse("lz4").is_err());
+        assert!(decode(Codec::Zstd, b"not zstd").is_err());
+    }
+}
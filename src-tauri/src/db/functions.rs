@@ -0,0 +1,72 @@
+//! SQL functions used to filter rows in SQLite instead of after loading them
+
+use diesel::{
+    define_sql_function,
+    sql_types::{Bool, Text},
+    QueryResult, SqliteConnection,
+};
+use regex::Regex;
+use std::sync::Mutex;
+
+define_sql_function! {
+    /// Whether `value` matches the regular expression `pattern`. Has to be registered on a
+    /// connection with [`register_regexp`] before it's used, which also makes SQLite's
+    /// `value REGEXP pattern` operator work.
+    fn regexp(pattern: Text, value: Text) -> Bool;
+}
+
+define_sql_function! {
+    /// SQLite's built in, case sensitive glob matching. `*` matches any characters and `?` matches
+    /// a single character.
+    fn glob(pattern: Text, value: Text) -> Bool;
+}
+
+/// Registers [`regexp`]. SQLite calls it once per row with the same pattern, so the last compiled
+/// pattern is kept around. Invalid patterns match nothing, so check patterns with [`Regex::new`]
+/// before querying to report the error.
+pub fn register_regexp(connection: &mut SqliteConnection) -> QueryResult<()> {
+    let compiled: Mutex<Option<(String, Option<Regex>)>> = Mutex::new(None);
+    regexp_utils::register_impl(connection, move |pattern: String, value: String| {
+        let mut compiled = compiled.lock().unwrap_or_else(|e| e.into_inner());
+        if compiled
+            .as_ref()
+            .map_or(true, |(cached, _)| *cached != pattern)
+        {
+            let regex = Regex::new(&pattern).ok();
+            *compiled = Some((pattern, regex));
+        }
+        compiled
+            .as_ref()
+            .and_then(|(_, regex)| regex.as_ref())
+            .is_some_and(|regex| regex.is_match(&value))
+    })
+}
+
+/// Returns a glob pattern matching every string starting with `prefix`
+pub fn prefix_glob(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        match c {
+            '*' | '?' | '[' => {
+                pattern.push('[');
+                pattern.push(c);
+                pattern.push(']');
+            }
+            _ => pattern.push(c),
+        }
+    }
+    pattern.push('*');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_glob() {
+        assert_eq!(prefix_glob("slot"), "slot*");
+        assert_eq!(prefix_glob("a*b?[c]"), "a[*]b[?][[]c]*");
+        assert_eq!(prefix_glob(""), "*");
+    }
+}
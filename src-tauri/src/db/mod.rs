@@ -1,9 +1,24 @@
 use anyhow::{Error, Ok};
-use diesel::{expression::is_aggregate::No, insert_into, prelude::*, sql_types::Nullable};
+use codec::Codec;
+use diesel::{
+    connection::SimpleConnection,
+    expression::is_aggregate::No,
+    insert_into,
+    prelude::*,
+    sql_types::{BigInt, Nullable, Text},
+};
+use functions::{glob, prefix_glob, regexp, register_regexp};
 use models::*;
 use regex::Regex;
-use std::option::Option;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    option::Option,
+};
+use tracing::{debug, info, warn};
 
+pub mod codec;
+pub mod functions;
 pub mod models;
 pub mod schema;
 pub mod test_context;
@@ -17,13 +32,20 @@ pub fn setup_db(db_path: &str) {
     connection
         .run_pending_migrations(MIGRATIONS)
         .expect("Failed to run migrations");
-    println!("Pending migrations ran successfully");
+    info!(db_path, "Pending migrations ran successfully");
 }
 
 pub fn establish_connection(db_path: &str) -> SqliteConnection {
-    SqliteConnection::establish(db_path)
-        .expect(format!("Failed to connect to database at {}", db_path).as_str())
+    let mut connection = SqliteConnection::establish(db_path)
+        .expect(format!("Failed to connect to database at {}", db_path).as_str());
     // TODO handle database connection error
+    register_regexp(&mut connection).expect("Failed to register the regexp function");
+    // concurrent writers, such as two sessions incrementing the same global, wait for each other
+    // instead of failing with "database is locked"
+    connection
+        .batch_execute("PRAGMA busy_timeout = 5000;")
+        .expect("Failed to set the busy timeout");
+    connection
 }
 
 pub fn insert_game(id_s: &str, name_s: &str, is_installed: bool, db_path: &str) -> usize {
@@ -38,6 +60,69 @@ pub fn insert_game(id_s: &str, name_s: &str, is_installed: bool, db_path: &str)
         .expect("Failed to insert game")
 }
 
+/// Enables or disables a game. Disabled games stay installed on disk, but are hidden from the
+/// launcher and cannot be played.
+///
+/// Returns the number of games updated, which is 0 if no game has the given id.
+pub fn set_game_enabled(id_s: &str, is_enabled: bool, db_path: &str) -> QueryResult<usize> {
+    use self::schema::games::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    diesel::update(games.filter(id.eq(id_s)))
+        .set(enabled.eq(is_enabled))
+        .execute(connection)
+}
+
+/// Returns whether the given game is enabled. Games that are not in the database yet are
+/// treated as enabled.
+pub fn is_game_enabled(id_s: &str, db_path: &str) -> QueryResult<bool> {
+    use self::schema::games::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    let is_enabled = games
+        .select(enabled)
+        .filter(id.eq(id_s))
+        .first::<bool>(connection)
+        .optional()?;
+
+    QueryResult::Ok(is_enabled.unwrap_or(true))
+}
+
+/// Returns the ids of every disabled game
+pub fn get_disabled_game_ids(db_path: &str) -> QueryResult<Vec<String>> {
+    use self::schema::games::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    games
+        .select(id)
+        .filter(enabled.eq(false))
+        .get_results(connection)
+}
+
+/// Marks a game as no longer installed. If `purge_data` is true, every save, save revision,
+/// leaderboard entry, global and player stat belonging to the game is deleted in the same
+/// transaction.
+pub fn mark_game_uninstalled(id_s: &str, purge_data: bool, db_path: &str) -> QueryResult<()> {
+    use self::schema::{game_globals, games, leaderboard, player_stats, save_revisions, saves};
+    let connection = &mut establish_connection(db_path);
+    connection.transaction(|connection| {
+        diesel::update(games::table.filter(games::id.eq(id_s)))
+            .set(games::installed.eq(false))
+            .execute(connection)?;
+
+        if purge_data {
+            diesel::delete(saves::table.filter(saves::game_id.eq(id_s))).execute(connection)?;
+            diesel::delete(save_revisions::table.filter(save_revisions::game_id.eq(id_s)))
+                .execute(connection)?;
+            diesel::delete(leaderboard::table.filter(leaderboard::game_id.eq(id_s)))
+                .execute(connection)?;
+            diesel::delete(game_globals::table.filter(game_globals::game_id.eq(id_s)))
+                .execute(connection)?;
+            diesel::delete(player_stats::table.filter(player_stats::game_id.eq(id_s)))
+                .execute(connection)?;
+        }
+
+        QueryResult::Ok(())
+    })
+}
+
 /// Ensures a game exists in the data base by inserting the given game into the database
 /// and doing nothing if there is a conflict.
 pub fn make_sure_game_exists(name_s: &str, id_s: &str, db_path: &str) {
@@ -71,6 +156,342 @@ pub fn insert_leaderboard_entry(
         .execute(&mut connection)
 }
 
+/// A leaderboard entry to insert, see [`insert_leaderboard_write`] and
+/// [`insert_leaderboard_entries`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LeaderboardWrite {
+    pub user_id: String,
+    pub value_name: String,
+    pub value_num: f64,
+    /// JSON text stored with the entry
+    pub details: Option<String>,
+    /// Binary data stored with the entry, such as a replay
+    pub attachment: Option<Vec<u8>>,
+    /// Name of the team that set the entry
+    pub team_name: Option<String>,
+    /// User ids of every member of the team, including `user_id`. Only stored with a team name.
+    pub members: Vec<String>,
+    /// Values after `value_num` of a composite metric, at most 3
+    pub tie_breakers: Vec<f64>,
+    /// Day of the daily challenge the entry was set in
+    pub challenge_date: Option<String>,
+}
+
+/// Inserts an entry and its attachment, returning the entry's row id, or `None` if the same
+/// player already has an entry with the same name and value
+fn insert_write(
+    connection: &mut SqliteConnection,
+    game_id_s: &str,
+    entry: &LeaderboardWrite,
+) -> QueryResult<Option<i32>> {
+    use self::schema::{leaderboard, leaderboard_attachments, leaderboard_members};
+    let inserted: Vec<i32> = insert_into(leaderboard::table)
+        .values((
+            leaderboard::user_id.eq(&entry.user_id),
+            leaderboard::game_id.eq(game_id_s),
+            leaderboard::value_name.eq(&entry.value_name),
+            leaderboard::value_num.eq(entry.value_num),
+            leaderboard::details.eq(&entry.details),
+            leaderboard::team_name.eq(&entry.team_name),
+            leaderboard::tie_breaker_1.eq(entry.tie_breakers.first().copied().unwrap_or(0.0)),
+            leaderboard::tie_breaker_2.eq(entry.tie_breakers.get(1).copied().unwrap_or(0.0)),
+            leaderboard::tie_breaker_3.eq(entry.tie_breakers.get(2).copied().unwrap_or(0.0)),
+            leaderboard::challenge_date.eq(&entry.challenge_date),
+        ))
+        .on_conflict_do_nothing()
+        .returning(leaderboard::row_id)
+        .get_results(connection)?;
+    let Some(&entry_id) = inserted.first() else {
+        return QueryResult::Ok(None);
+    };
+    if let Some(attachment) = &entry.attachment {
+        insert_into(leaderboard_attachments::table)
+            .values((
+                leaderboard_attachments::entry_id.eq(entry_id),
+                leaderboard_attachments::data.eq(attachment),
+            ))
+            .execute(connection)?;
+    }
+    if entry.team_name.is_some() {
+        let members: Vec<_> = entry
+            .members
+            .iter()
+            .map(|member| {
+                (
+                    leaderboard_members::entry_id.eq(entry_id),
+                    leaderboard_members::user_id.eq(member),
+                )
+            })
+            .collect();
+        insert_into(leaderboard_members::table)
+            .values(&members)
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+    QueryResult::Ok(Some(entry_id))
+}
+
+/// Inserts a leaderboard entry along with its details and attachment.
+///
+/// Returns the row id of the new entry, or `None` if the same player already has an entry with
+/// the same name and value, in which case nothing is stored.
+pub fn insert_leaderboard_write(
+    game_id_s: &str,
+    entry: &LeaderboardWrite,
+    db_path: &str,
+) -> QueryResult<Option<i32>> {
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| insert_write(connection, game_id_s, entry))
+}
+
+/// What happened to an entry inserted by [`insert_leaderboard_entries`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeaderboardInsert {
+    Stored,
+    /// The same player already has an entry with the same name and value
+    Duplicate,
+    /// The entry couldn't be inserted, for the given reason
+    Rejected(String),
+}
+
+/// Inserts several leaderboard entries of a game in one transaction. Each entry is inserted on
+/// its own, so an entry breaking a constraint of the database doesn't stop the others from being
+/// stored. Any other error rolls back the whole batch.
+pub fn insert_leaderboard_entries(
+    game_id_s: &str,
+    entries: &[LeaderboardWrite],
+    db_path: &str,
+) -> QueryResult<Vec<LeaderboardInsert>> {
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| {
+        let mut inserts = Vec::with_capacity(entries.len());
+        for entry in entries {
+            // a nested transaction is a savepoint, so only this entry is rolled back
+            let inserted =
+                connection.transaction(|connection| insert_write(connection, game_id_s, entry));
+            inserts.push(match inserted {
+                QueryResult::Ok(None) => LeaderboardInsert::Duplicate,
+                QueryResult::Ok(Some(_)) => LeaderboardInsert::Stored,
+                Err(diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+                    _,
+                )) => LeaderboardInsert::Rejected("Player doesn't exist".to_string()),
+                Err(diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation
+                    | diesel::result::DatabaseErrorKind::CheckViolation
+                    | diesel::result::DatabaseErrorKind::NotNullViolation,
+                    info,
+                )) => LeaderboardInsert::Rejected(info.message().to_string()),
+                Err(e) => return Err(e),
+            });
+        }
+        QueryResult::Ok(inserts)
+    })
+}
+
+/// Returns the attachment of a game's leaderboard entry, or `None` if the game has no entry
+/// with that id or the entry has no attachment
+pub fn get_leaderboard_attachment(
+    game_id_s: &str,
+    entry_id_i: i32,
+    db_path: &str,
+) -> QueryResult<Option<Vec<u8>>> {
+    use self::schema::{leaderboard, leaderboard_attachments};
+    let connection = &mut establish_connection(db_path);
+    leaderboard_attachments::table
+        .inner_join(leaderboard::table)
+        .filter(leaderboard::game_id.eq(game_id_s))
+        .filter(leaderboard_attachments::entry_id.eq(entry_id_i))
+        .select(leaderboard_attachments::data)
+        .first(connection)
+        .optional()
+}
+
+/// Returns every value of a game's leaderboard metric, lowest first. Only values of the daily
+/// challenge of `challenge_date_s` are returned if it's given, and only values set outside of
+/// daily challenges otherwise.
+pub fn get_leaderboard_values(
+    game_id_s: &str,
+    value_name_s: &str,
+    challenge_date_s: Option<&str>,
+    db_path: &str,
+) -> QueryResult<Vec<f64>> {
+    use self::schema::leaderboard::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    let mut query = leaderboard
+        .filter(game_id.eq(game_id_s))
+        .filter(value_name.eq(value_name_s))
+        .into_boxed();
+    query = match challenge_date_s {
+        Some(challenge_date_s) => query.filter(challenge_date.eq(challenge_date_s)),
+        None => query.filter(challenge_date.is_null()),
+    };
+    query
+        .order_by(value_num.asc())
+        .select(value_num)
+        .load(connection)
+}
+
+/// Returns which of the given leaderboard entries have an attachment
+pub fn get_leaderboard_attachment_ids(
+    entry_ids: &[i32],
+    db_path: &str,
+) -> QueryResult<HashSet<i32>> {
+    use self::schema::leaderboard_attachments::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    let ids: Vec<i32> = leaderboard_attachments
+        .filter(entry_id.eq_any(entry_ids))
+        .select(entry_id)
+        .load(connection)?;
+    QueryResult::Ok(ids.into_iter().collect())
+}
+
+/// Returns the user ids of the team members of the given leaderboard entries, by entry. Entries
+/// not set by a team aren't included.
+pub fn get_leaderboard_members(
+    entry_ids: &[i32],
+    db_path: &str,
+) -> QueryResult<HashMap<i32, Vec<String>>> {
+    use self::schema::leaderboard_members::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    let rows: Vec<(i32, String)> = leaderboard_members
+        .filter(entry_id.eq_any(entry_ids))
+        .order_by((entry_id, user_id))
+        .select((entry_id, user_id))
+        .load(connection)?;
+    let mut members: HashMap<i32, Vec<String>> = HashMap::new();
+    for (entry, user) in rows {
+        members.entry(entry).or_default().push(user);
+    }
+    QueryResult::Ok(members)
+}
+
+/// Returns the settings of a game's leaderboard values. Values without settings aren't included.
+pub fn get_leaderboard_metrics(
+    game_id_s: &str,
+    db_path: &str,
+) -> QueryResult<Vec<LeaderboardMetric>> {
+    use self::schema::leaderboard_metrics::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    leaderboard_metrics
+        .filter(game_id.eq(game_id_s))
+        .order_by(value_name)
+        .select(LeaderboardMetric::as_select())
+        .load(connection)
+}
+
+/// Sets whether only whole numbers are accepted for one of a game's leaderboard values.
+///
+/// Returns the number of metrics updated, which is 0 if no game has the given id.
+pub fn set_leaderboard_metric(
+    game_id_s: &str,
+    value_name_s: &str,
+    integer_only_b: bool,
+    db_path: &str,
+) -> QueryResult<usize> {
+    use self::schema::{games, leaderboard_metrics::dsl::*};
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| {
+        let game_count: i64 = games::table
+            .filter(games::id.eq(game_id_s))
+            .count()
+            .get_result(connection)?;
+        if game_count == 0 {
+            return QueryResult::Ok(0);
+        }
+        insert_into(leaderboard_metrics)
+            .values((
+                game_id.eq(game_id_s),
+                value_name.eq(value_name_s),
+                integer_only.eq(integer_only_b),
+            ))
+            .on_conflict((game_id, value_name))
+            .do_update()
+            .set(integer_only.eq(integer_only_b))
+            .execute(connection)
+    })
+}
+
+/// Sets the values one of a game's leaderboard metrics is ranked by, in order. The first
+/// component is stored as `value_num` and the others as tie-breakers. An empty list makes the
+/// metric rank by `value_num` alone again.
+///
+/// Returns the number of metrics updated, which is 0 if no game has the given id.
+pub fn set_leaderboard_components(
+    game_id_s: &str,
+    value_name_s: &str,
+    components_l: &[MetricComponent],
+    db_path: &str,
+) -> Result<usize, Error> {
+    use self::schema::{games, leaderboard_metrics::dsl::*};
+    let components_s = match components_l {
+        [] => None,
+        _ => Some(serde_json::to_string(components_l)?),
+    };
+    let connection = &mut establish_connection(db_path);
+    let updated = connection.immediate_transaction(|connection| {
+        let game_count: i64 = games::table
+            .filter(games::id.eq(game_id_s))
+            .count()
+            .get_result(connection)?;
+        if game_count == 0 {
+            return QueryResult::Ok(0);
+        }
+        insert_into(leaderboard_metrics)
+            .values((
+                game_id.eq(game_id_s),
+                value_name.eq(value_name_s),
+                components.eq(&components_s),
+            ))
+            .on_conflict((game_id, value_name))
+            .do_update()
+            .set(components.eq(&components_s))
+            .execute(connection)
+    })?;
+    Ok(updated)
+}
+
+/// Returns the components of a game's composite metric, or an empty list if it isn't composite
+fn metric_components(
+    connection: &mut SqliteConnection,
+    game_id_s: &str,
+    value_name_s: &str,
+) -> QueryResult<Vec<MetricComponent>> {
+    use self::schema::leaderboard_metrics::dsl::*;
+    let metric = leaderboard_metrics
+        .filter(game_id.eq(game_id_s))
+        .filter(value_name.eq(value_name_s))
+        .select(LeaderboardMetric::as_select())
+        .first(connection)
+        .optional()?;
+    QueryResult::Ok(metric.map(|metric| metric.components()).unwrap_or_default())
+}
+
+/// Orders leaderboard entries by each component of a composite metric in turn, and then by age
+/// so the earliest of tied entries ranks first. `reverse` flips the direction of every component.
+fn order_by_components<'a>(
+    mut query: schema::leaderboard::BoxedQuery<'a, diesel::sqlite::Sqlite>,
+    components_l: &[MetricComponent],
+    reverse: bool,
+) -> schema::leaderboard::BoxedQuery<'a, diesel::sqlite::Sqlite> {
+    use self::schema::leaderboard::dsl::*;
+    for (index, component) in components_l.iter().enumerate() {
+        query = match (index, component.ascending != reverse) {
+            (0, true) => query.then_order_by(value_num.asc()),
+            (0, false) => query.then_order_by(value_num.desc()),
+            (1, true) => query.then_order_by(tie_breaker_1.asc()),
+            (1, false) => query.then_order_by(tie_breaker_1.desc()),
+            (2, true) => query.then_order_by(tie_breaker_2.asc()),
+            (2, false) => query.then_order_by(tie_breaker_2.desc()),
+            (3, true) => query.then_order_by(tie_breaker_3.asc()),
+            (3, false) => query.then_order_by(tie_breaker_3.desc()),
+            _ => query,
+        };
+    }
+    query.then_order_by(row_id.asc())
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn get_leaderboard(
     game_id_s: Option<String>,
     user_id_s: Option<String>,
@@ -78,6 +499,7 @@ pub async fn get_leaderboard(
     ascending: Option<bool>,
     value_name_s: Option<String>,
     offset: Option<i64>,
+    challenge_date_s: Option<String>,
     db_path: &str,
 ) -> Vec<LeaderboardEntry> {
     use self::schema::leaderboard::dsl::*;
@@ -85,12 +507,24 @@ pub async fn get_leaderboard(
 
     let mut query = leaderboard.into_boxed(); // Selects all by default
 
+    let metric_components = match (&game_id_s, &value_name_s) {
+        (Some(game_id_s), Some(value_name_s)) => {
+            metric_components(&mut connection, game_id_s, value_name_s)
+                .expect("Error loading leaderboard metric")
+        }
+        _ => Vec::new(),
+    };
+
     if let Some(game_id_s) = game_id_s {
         query = query.filter(game_id.eq(game_id_s));
     }
 
     if let Some(user_id_s) = user_id_s {
-        query = query.filter(user_id.eq(user_id_s));
+        // entries of a team are listed for every member
+        let team_entries = schema::leaderboard_members::table
+            .filter(schema::leaderboard_members::user_id.eq(user_id_s.clone()))
+            .select(schema::leaderboard_members::entry_id);
+        query = query.filter(user_id.eq(user_id_s).or(row_id.eq_any(team_entries)));
     }
 
     if let Some(num_entries) = num_entries {
@@ -101,7 +535,15 @@ pub async fn get_leaderboard(
         query = query.filter(value_name.eq(value_name_s))
     }
 
-    if let Some(ascending) = ascending {
+    // entries of a daily challenge are only listed on its own leaderboard
+    query = match challenge_date_s {
+        Some(challenge_date_s) => query.filter(challenge_date.eq(challenge_date_s)),
+        None => query.filter(challenge_date.is_null()),
+    };
+
+    if !metric_components.is_empty() {
+        query = order_by_components(query, &metric_components, ascending.unwrap_or(false));
+    } else if let Some(ascending) = ascending {
         if ascending {
             query = query.order_by(value_num.asc());
         } else {
@@ -122,12 +564,22 @@ pub async fn get_leaderboard(
     results
 }
 
-fn validate_save_data_params(
+/// Checks the parameters of [`get_save_data`], so invalid requests can be told apart from
+/// errors reading the saves
+///
+/// # Errors
+/// * If both regx and filename_s parameters are passed as Some
+/// * If regex is invalid or greater than the size limit
+pub fn validate_save_data_params(
     file_name: &Option<String>,
     regx: &Option<String>,
 ) -> Result<(), Error> {
     match (file_name, regx) {
         (Some(_), Some(_)) => Err(Error::msg("Save data con only be searched by file name or matching a regular expression, not both")),
+        (_, Some(regx)) => {
+            Regex::new(regx)?;
+            Ok(())
+        }
         _ => Ok(())
     }
 }
@@ -172,24 +624,374 @@ pub async fn get_save_data(
     //     query = query.order(time_stamp.desc());
     // }
 
-    let mut results: Vec<Save> = query
+    if let Some(regx) = regx {
+        query = query.filter(regexp(regx, file_name));
+    }
+
+    let results: Vec<Save> = query
         .get_results(&mut connection)
         .expect("Error loading save data");
+    results
+        .into_iter()
+        .map(|save| decode_save(save, &mut connection))
+        .collect()
+}
 
-    if let Some(regex) = regx {
-        let re = Regex::new(&regex)?;
+/// Decompresses the data of a save read from the database
+fn decode_save(mut save: Save, connection: &mut SqliteConnection) -> Result<Save, Error> {
+    save.data = decode_stored_data(save.row_id, save.data, &save.codec, connection)?;
+    Ok(save)
+}
 
-        results = results
-            .into_iter()
-            .filter(|entry| re.is_match(&entry.file_name))
-            .collect();
+/// Decompresses the stored data of a save. Saves stored uncompressed before compression was
+/// added are compressed in place, so existing databases shrink as saves are used. Compressing is
+/// only an optimisation, so a failed write is logged and the data is still returned.
+///
+/// Only the current data is compressed in place. Revisions stored uncompressed in
+/// `save_revisions` stay that way, since they are rarely read and are dropped as newer, compressed
+/// revisions are written past the game's revision limit.
+fn decode_stored_data(
+    save_row_id: i32,
+    stored: Vec<u8>,
+    stored_codec: &str,
+    connection: &mut SqliteConnection,
+) -> Result<Vec<u8>, Error> {
+    use self::schema::saves;
+    let stored_codec = Codec::parse(stored_codec)?;
+    if stored_codec != Codec::Raw {
+        return Ok(codec::decode(stored_codec, &stored)?.into_owned());
+    }
+
+    if let (Codec::Zstd, compressed) = codec::encode(&stored) {
+        let compressed_in_place = diesel::update(
+            saves::table
+                .filter(saves::row_id.eq(save_row_id))
+                .filter(saves::codec.eq(Codec::Raw.as_str())),
+        )
+        .set((
+            saves::data.eq(&*compressed),
+            saves::codec.eq(Codec::Zstd.as_str()),
+        ))
+        .execute(connection);
+        if let Err(e) = compressed_in_place {
+            warn!(row_id = save_row_id, error = %e, "Failed to compress save data");
+        } else {
+            debug!(
+                row_id = save_row_id,
+                bytes = stored.len(),
+                compressed_bytes = compressed.len(),
+                "Compressed save data"
+            );
+        }
+    }
+    Ok(stored)
+}
+
+/// Position of the last entry of a leaderboard page. Entries are ordered by value, then by the
+/// tie-breakers of a composite metric and then by row id, so the position stays valid when new
+/// entries are added.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LeaderboardPosition {
+    pub value_num: f64,
+    /// Values after `value_num` of a composite metric, empty for other metrics
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tie_breakers: Vec<f64>,
+    pub row_id: i32,
+}
+
+type LeaderboardPredicate = Box<
+    dyn BoxableExpression<
+        schema::leaderboard::table,
+        diesel::sqlite::Sqlite,
+        SqlType = diesel::sql_types::Bool,
+    >,
+>;
+
+/// The column of the value at `index` of an entry, where 0 is `value_num` and the rest are the
+/// tie-breakers of a composite metric
+fn ranked_column(
+    index: usize,
+) -> Box<
+    dyn BoxableExpression<
+        schema::leaderboard::table,
+        diesel::sqlite::Sqlite,
+        SqlType = diesel::sql_types::Double,
+    >,
+> {
+    use self::schema::leaderboard::dsl::*;
+    match index {
+        0 => Box::new(value_num),
+        1 => Box::new(tie_breaker_1),
+        2 => Box::new(tie_breaker_2),
+        _ => Box::new(tie_breaker_3),
+    }
+}
+
+/// Matches the entries ranked after `after`. `ascending` has the direction of each value entries
+/// are ranked by, and entries with equal values are ranked by row id.
+fn ranked_after(after: &LeaderboardPosition, ascending: &[bool]) -> LeaderboardPredicate {
+    use self::schema::leaderboard::dsl::*;
+    let mut predicate: LeaderboardPredicate = Box::new(row_id.gt(after.row_id));
+    for (index, &ascending) in ascending.iter().enumerate().rev() {
+        let after_value = match index {
+            0 => after.value_num,
+            _ => after.tie_breakers.get(index - 1).copied().unwrap_or(0.0),
+        };
+        let later: LeaderboardPredicate = if ascending {
+            Box::new(ranked_column(index).gt(after_value))
+        } else {
+            Box::new(ranked_column(index).lt(after_value))
+        };
+        predicate = Box::new(later.or(ranked_column(index).eq(after_value).and(predicate)));
+    }
+    predicate
+}
+
+/// Filters of a leaderboard page
+#[derive(Debug, Clone, Default)]
+pub struct LeaderboardFilter {
+    pub game_id: String,
+    pub user_id: Option<String>,
+    pub value_name: Option<String>,
+    pub ascending: bool,
+    /// Day of the daily challenge to list, entries set outside of daily challenges are listed
+    /// if there is none
+    pub challenge_date: Option<String>,
+}
+
+fn filtered_leaderboard(
+    filter: &LeaderboardFilter,
+) -> schema::leaderboard::BoxedQuery<'_, diesel::sqlite::Sqlite> {
+    use self::schema::leaderboard::dsl::*;
+    let mut query = leaderboard.filter(game_id.eq(&filter.game_id)).into_boxed();
+    if let Some(user_id_s) = &filter.user_id {
+        let team_entries = schema::leaderboard_members::table
+            .filter(schema::leaderboard_members::user_id.eq(user_id_s))
+            .select(schema::leaderboard_members::entry_id);
+        query = query.filter(user_id.eq(user_id_s).or(row_id.eq_any(team_entries)));
+    }
+    if let Some(value_name_s) = &filter.value_name {
+        query = query.filter(value_name.eq(value_name_s));
+    }
+    match &filter.challenge_date {
+        Some(challenge_date_s) => query.filter(challenge_date.eq(challenge_date_s)),
+        None => query.filter(challenge_date.is_null()),
+    }
+}
 
-        Ok(results)
+/// Returns up to `limit` leaderboard entries after the given position, along with the total
+/// number of entries matching the filter.
+pub fn get_leaderboard_page(
+    filter: &LeaderboardFilter,
+    after: Option<&LeaderboardPosition>,
+    limit: i64,
+    db_path: &str,
+) -> QueryResult<(Vec<LeaderboardEntry>, i64)> {
+    use self::schema::leaderboard::dsl::*;
+    let mut connection = establish_connection(db_path);
+
+    let total = filtered_leaderboard(filter)
+        .count()
+        .get_result::<i64>(&mut connection)?;
+    let metric_components = match &filter.value_name {
+        Some(value_name_s) => metric_components(&mut connection, &filter.game_id, value_name_s)?,
+        None => Vec::new(),
+    };
+
+    let mut query = filtered_leaderboard(filter);
+    let ranked_ascending = if !metric_components.is_empty() {
+        query = order_by_components(query, &metric_components, filter.ascending);
+        metric_components
+            .iter()
+            .map(|component| component.ascending != filter.ascending)
+            .collect()
+    } else if filter.ascending {
+        query = query.order_by((value_num.asc(), row_id.asc()));
+        vec![true]
     } else {
-        Ok(results)
+        query = query.order_by((value_num.desc(), row_id.asc()));
+        vec![false]
+    };
+    if let Some(after) = after {
+        query = query.filter(ranked_after(after, &ranked_ascending));
+    }
+
+    let entries = query
+        .limit(limit)
+        .select(LeaderboardEntry::as_select())
+        .get_results(&mut connection)?;
+    QueryResult::Ok((entries, total))
+}
+
+/// Position of the last save of a page. Saves are ordered by file name and then by row id.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavePosition {
+    pub file_name: String,
+    pub row_id: i32,
+}
+
+/// Filters of a page of saves. At most one of `file_name`, `regex`, `glob` and `prefix` can be
+/// used. `glob` and `prefix` are cheaper than `regex` for simple patterns.
+#[derive(Debug, Clone, Default)]
+pub struct SaveFilter {
+    pub game_id: String,
+    pub user_id: Option<String>,
+    pub file_name: Option<String>,
+    pub regex: Option<String>,
+    /// A case sensitive glob pattern, see [`glob`]
+    pub glob: Option<String>,
+    /// Only match file names starting with this
+    pub prefix: Option<String>,
+}
+
+impl SaveFilter {
+    /// # Errors
+    /// * If more than one of `file_name`, `regex`, `glob` and `prefix` are given
+    /// * If the regex is invalid or greater than the size limit
+    pub fn validate(&self) -> Result<(), Error> {
+        let file_name_filters = [&self.file_name, &self.regex, &self.glob, &self.prefix];
+        if file_name_filters.iter().filter(|f| f.is_some()).count() > 1 {
+            return Err(Error::msg(
+                "Save data can only be searched by one of file name, regular expression, glob or prefix",
+            ));
+        }
+        if let Some(regex) = &self.regex {
+            Regex::new(regex)?;
+        }
+        Ok(())
     }
 }
 
+/// Returns up to `limit` saves after the given position, along with the total number of saves
+/// matching the filter. Filtering, counting and paging all happen in SQLite. The data of a save is
+/// only loaded if `include_data` is true, and only for the saves on the page.
+///
+/// # Errors
+/// * If more than one of `file_name`, `regex`, `glob` and `prefix` are given
+/// * If the regex is invalid or greater than the size limit
+pub fn get_save_data_page(
+    filter: &SaveFilter,
+    after: Option<&SavePosition>,
+    limit: usize,
+    include_data: bool,
+    db_path: &str,
+) -> Result<(Vec<(SaveMetadata, Option<Vec<u8>>)>, i64), Error> {
+    use self::schema::saves::dsl::*;
+    filter.validate()?;
+    let mut connection = establish_connection(db_path);
+
+    let filtered = || {
+        let mut query = saves.filter(game_id.eq(&filter.game_id)).into_boxed();
+        if let Some(user_id_s) = &filter.user_id {
+            query = query.filter(user_id.eq(user_id_s));
+        }
+        if let Some(file_name_s) = &filter.file_name {
+            query = query.filter(file_name.eq(file_name_s));
+        }
+        if let Some(regex) = &filter.regex {
+            query = query.filter(regexp(regex, file_name));
+        }
+        if let Some(pattern) = &filter.glob {
+            query = query.filter(glob(pattern, file_name));
+        }
+        if let Some(prefix) = &filter.prefix {
+            query = query.filter(glob(prefix_glob(prefix), file_name));
+        }
+        query
+    };
+
+    let total: i64 = filtered().count().get_result(&mut connection)?;
+
+    let mut query = filtered()
+        .select(SaveMetadata::as_select())
+        .order_by((file_name.asc(), row_id.asc()))
+        .limit(limit as i64);
+    if let Some(after) = after {
+        query = query.filter(
+            file_name
+                .gt(&after.file_name)
+                .or(file_name.eq(&after.file_name).and(row_id.gt(after.row_id))),
+        );
+    }
+    let page: Vec<SaveMetadata> = query.get_results(&mut connection)?;
+
+    if !include_data {
+        let page = page.into_iter().map(|save| (save, None)).collect();
+        return Ok((page, total));
+    }
+
+    let mut blobs: HashMap<i32, Vec<u8>> = saves
+        .filter(row_id.eq_any(page.iter().map(|save| save.row_id)))
+        .select((row_id, data, schema::saves::codec))
+        .load::<(i32, Vec<u8>, String)>(&mut connection)?
+        .into_iter()
+        .map(|(save_row_id, stored, stored_codec)| {
+            let blob = decode_stored_data(save_row_id, stored, &stored_codec, &mut connection)?;
+            Ok((save_row_id, blob))
+        })
+        .collect::<Result<_, Error>>()?;
+    let page = page
+        .into_iter()
+        .map(|save| {
+            let blob = blobs.remove(&save.row_id);
+            (save, blob)
+        })
+        .collect();
+    Ok((page, total))
+}
+
+/// Returns the quotas of a game, along with the number of saves and the total size of their
+/// data. Only the saves of `user_id_s` are counted if it's given.
+pub fn get_storage_usage(
+    game_id_s: &str,
+    user_id_s: Option<&str>,
+    db_path: &str,
+) -> QueryResult<(StorageQuotas, i64, i64)> {
+    use self::schema::saves::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    let quotas = game_quotas(game_id_s, connection)?;
+    let used_bytes = game_save_bytes(game_id_s, connection)?;
+
+    let mut query = saves.filter(game_id.eq(game_id_s)).into_boxed();
+    if let Some(user_id_s) = user_id_s {
+        query = query.filter(user_id.eq(user_id_s));
+    }
+    let save_count: i64 = query.count().get_result(connection)?;
+    QueryResult::Ok((quotas, save_count, used_bytes))
+}
+
+/// Returns how much space each game's saves take up, largest first
+pub fn get_games_storage_usage(db_path: &str) -> QueryResult<Vec<GameStorageUsage>> {
+    let connection = &mut establish_connection(db_path);
+    diesel::sql_query(
+        "SELECT games.id AS game_id, games.name AS name,
+            (SELECT COUNT(*) FROM saves WHERE saves.game_id = games.id) AS save_count,
+            (SELECT COALESCE(SUM(LENGTH(data)), 0) FROM saves
+                WHERE saves.game_id = games.id) AS save_bytes,
+            (SELECT COALESCE(SUM(LENGTH(data)), 0) FROM save_revisions
+                WHERE save_revisions.game_id = games.id) AS revision_bytes
+        FROM games
+        ORDER BY save_bytes + revision_bytes DESC, games.name",
+    )
+    .load(connection)
+}
+
+/// Sets the storage quotas of a game. Saves already over a lowered quota are kept, but can't
+/// grow until the game is back under it.
+///
+/// Returns the number of games updated, which is 0 if no game has the given id.
+pub fn set_storage_quotas(id_s: &str, quotas: &StorageQuotas, db_path: &str) -> QueryResult<usize> {
+    use self::schema::games::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    diesel::update(games.filter(id.eq(id_s)))
+        .set((
+            max_save_bytes.eq(quotas.max_save_bytes),
+            max_saves_per_user.eq(quotas.max_saves_per_user),
+            max_game_bytes.eq(quotas.max_game_bytes),
+        ))
+        .execute(connection)
+}
+
 pub fn create_default_guest(db_path: &str) -> Vec<User> {
     use self::schema::users::dsl::*;
     const ID_S: &str = "1";
@@ -213,6 +1015,19 @@ pub fn create_user(id_s: &str, name_s: &str, db_path: &str) -> User {
         .expect("Could not create User")
 }
 
+/// Creates a user, or renames the user if one with the given id already exists
+pub fn upsert_user(id_s: &str, name_s: &str, db_path: &str) -> User {
+    use self::schema::users::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    insert_into(users)
+        .values((id.eq(id_s), name.eq(name_s)))
+        .on_conflict(id)
+        .do_update()
+        .set(name.eq(name_s))
+        .get_result::<User>(connection)
+        .expect("Could not upsert User")
+}
+
 pub async fn get_user(name_s: &str, user_id_s: &str, db_path: &str) -> User {
     use self::schema::users::dsl::*;
     let connection = &mut establish_connection(db_path);
@@ -225,70 +1040,856 @@ pub async fn get_user(name_s: &str, user_id_s: &str, db_path: &str) -> User {
         .expect("Error loading user data")
 }
 
+/// Creates or replaces a save. Every write is kept as a new revision, see [`get_save_revisions`].
+///
+/// # Errors
+/// * [`QuotaExceeded`] if the save would go over one of the game's [`StorageQuotas`]
+/// * If the database can't be written
 pub async fn set_save(
     user_id_s: &str,
     game_id_s: &str,
     file_name_s: &str,
-    data_b: &Vec<u8>,
+    data_b: &[u8],
     db_path: &str,
-) -> Save {
+) -> Result<Save, Error> {
+    set_save_if(user_id_s, game_id_s, file_name_s, data_b, None, db_path).await
+}
+
+/// Same as [`set_save`], but only writes the save if its current revision matches `if_revision`
+///
+/// # Errors
+/// * [`RevisionMismatch`] if the save was changed since the caller read it
+/// * [`QuotaExceeded`] if the save would go over one of the game's quotas
+pub async fn set_save_if(
+    user_id_s: &str,
+    game_id_s: &str,
+    file_name_s: &str,
+    data_b: &[u8],
+    if_revision: Option<&IfRevision>,
+    db_path: &str,
+) -> Result<Save, Error> {
     use self::schema::saves::dsl::*;
     let connection = &mut establish_connection(db_path);
-    insert_into(saves)
-        .values((
-            user_id.eq(user_id_s),
-            game_id.eq(game_id_s),
-            file_name.eq(file_name_s),
-            data.eq(data_b),
-        ))
-        .on_conflict((user_id, file_name))
-        .do_update()
-        .set(data.eq(data_b))
-        .execute(connection)
-        .expect("Error inserting save");
+    connection.immediate_transaction(|connection| {
+        write_save(
+            user_id_s,
+            game_id_s,
+            file_name_s,
+            data_b,
+            if_revision,
+            connection,
+        )
+    })?;
 
-    saves
+    let save = saves
         .select(Save::as_select())
         .filter(user_id.eq(user_id_s))
         .filter(game_id.eq(game_id_s))
         .filter(file_name.eq(file_name_s))
-        .first(connection)
-        .expect("Could not return inserted save")
+        .first(connection)?;
+    decode_save(save, connection)
 }
 
-pub async fn get_save(user_id_s: &str, game_id_s: &str, file_name_s: &str, db_path: &str) -> Save {
-    use self::schema::saves::dsl::*;
-    let connection = &mut establish_connection(db_path);
-    saves
-        .select(Save::as_select())
-        .filter(user_id.eq(user_id_s))
-        .filter(game_id.eq(game_id_s))
-        .filter(file_name.eq(file_name_s))
-        .first(connection)
-        .expect("Could not get save")
+/// A condition on the current revision of a save or global, checked before it is written
+#[derive(Debug, Clone, PartialEq)]
+pub enum IfRevision {
+    /// The save has to exist, whatever its revision
+    Any,
+    /// The save has to be at one of these revisions
+    OneOf(Vec<i32>),
+    /// The save can't exist yet
+    Absent,
 }
 
-/// Returns all leadboard data for a given game title.
-/// In cases other than testing, db_path should be "local"
-pub fn get_leaderboard_game_data(
-    game_title: &str,
-    db_path: &str,
-) -> Result<Vec<LeaderboardEntry>, Error> {
-    use self::schema::games::dsl::{games, name};
-    use self::schema::leaderboard::dsl::{game_id, leaderboard};
-    let connection = &mut establish_connection(db_path);
+impl IfRevision {
+    /// Whether a save at `current` revision, or `None` if there is no save yet, matches
+    pub fn matches(&self, current: Option<i32>) -> bool {
+        match (self, current) {
+            (IfRevision::Any, current) => current.is_some(),
+            (IfRevision::OneOf(revisions), Some(current)) => revisions.contains(&current),
+            (IfRevision::OneOf(_), None) => false,
+            (IfRevision::Absent, current) => current.is_none(),
+        }
+    }
+}
 
-    let game = games
-        .select(Game::as_select())
-        .filter(name.eq(game_title))
-        .first(connection)?;
-    println!("Found game with title: {}", game.name);
+/// The error returned when a save was changed since the writer read it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevisionMismatch {
+    /// Revision the save is at, or `None` if it doesn't exist
+    pub current: Option<i32>,
+}
 
-    let data = leaderboard
-        .select(LeaderboardEntry::as_select())
-        .filter(game_id.eq(game.id))
-        .get_results(connection)?;
-    println!("Found {} entries for {}", data.len(), game.name);
+impl std::fmt::Display for RevisionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.current {
+            Some(current) => write!(f, "Save was changed and is now at revision {}", current),
+            None => write!(f, "Save doesn't exist"),
+        }
+    }
+}
+
+impl std::error::Error for RevisionMismatch {}
+
+/// A partial update of a save holding a JSON document
+#[derive(Debug, Clone, PartialEq)]
+pub enum SavePatch {
+    /// An RFC 6902 JSON Patch, a list of operations such as `add` and `remove`
+    Json(json_patch::Patch),
+    /// An RFC 7396 JSON Merge Patch, a document of fields to replace, or remove with `null`
+    Merge(serde_json::Value),
+}
+
+/// The error returned when a patch can't be applied to a save
+#[derive(Debug, Clone, PartialEq)]
+pub enum SavePatchError {
+    NotFound,
+    /// The save's data isn't a JSON document, such as when it was written by the frontend
+    NotJson,
+    /// An operation of a JSON Patch failed, such as a `test` or a path that doesn't exist
+    Failed(String),
+}
+
+impl std::fmt::Display for SavePatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SavePatchError::NotFound => write!(f, "Save file not found"),
+            SavePatchError::NotJson => {
+                write!(f, "Save data isn't a JSON document, so it can't be patched")
+            }
+            SavePatchError::Failed(reason) => write!(f, "Patch couldn't be applied: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SavePatchError {}
+
+/// Which of a game's [`StorageQuotas`] a save would go over
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quota {
+    SaveBytes,
+    SavesPerUser,
+    GameBytes,
+}
+
+/// The error returned when a save would go over one of the game's [`StorageQuotas`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaExceeded {
+    pub quota: Quota,
+    pub limit: i64,
+    /// What the quota would have been used up to with the save
+    pub requested: i64,
+}
+
+impl QuotaExceeded {
+    /// Machine readable name of the exceeded quota
+    pub fn code(&self) -> &'static str {
+        match self.quota {
+            Quota::SaveBytes => "save_too_large",
+            Quota::SavesPerUser => "too_many_saves",
+            Quota::GameBytes => "storage_full",
+        }
+    }
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.quota {
+            Quota::SaveBytes => write!(
+                f,
+                "Save is {} bytes, but saves can be at most {} bytes",
+                self.requested, self.limit
+            ),
+            Quota::SavesPerUser => write!(
+                f,
+                "Each player can have at most {} saves for this game",
+                self.limit
+            ),
+            Quota::GameBytes => write!(
+                f,
+                "Saves of this game would take up {} bytes, but at most {} bytes are available",
+                self.requested, self.limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Returns the quotas of a game, or the default quotas if the game isn't in the database
+fn game_quotas(game_id_s: &str, connection: &mut SqliteConnection) -> QueryResult<StorageQuotas> {
+    use self::schema::games::dsl::*;
+    QueryResult::Ok(
+        games
+            .filter(id.eq(game_id_s))
+            .select(StorageQuotas::as_select())
+            .first(connection)
+            .optional()?
+            .unwrap_or_default(),
+    )
+}
+
+/// Total size in bytes of the current data of a game's saves
+fn game_save_bytes(game_id_s: &str, connection: &mut SqliteConnection) -> QueryResult<i64> {
+    use self::schema::saves::dsl::*;
+    saves
+        .filter(game_id.eq(game_id_s))
+        .select(diesel::dsl::sql::<BigInt>("COALESCE(SUM(LENGTH(data)), 0)"))
+        .first(connection)
+}
+
+/// Checks that replacing a save, or creating it if `existing_bytes` is `None`, stays within the
+/// game's quotas. The size of a single save is limited before compression, and the total size of
+/// a game's saves after compression, since that's what takes up space.
+fn check_quotas(
+    user_id_s: &str,
+    game_id_s: &str,
+    data_bytes: i64,
+    new_bytes: i64,
+    existing_bytes: Option<i64>,
+    connection: &mut SqliteConnection,
+) -> Result<(), Error> {
+    use self::schema::saves::dsl::*;
+    let quotas = game_quotas(game_id_s, connection)?;
+
+    if data_bytes > quotas.max_save_bytes {
+        return Err(QuotaExceeded {
+            quota: Quota::SaveBytes,
+            limit: quotas.max_save_bytes,
+            requested: data_bytes,
+        }
+        .into());
+    }
+    if existing_bytes.is_none() {
+        let user_saves: i64 = saves
+            .filter(user_id.eq(user_id_s))
+            .filter(game_id.eq(game_id_s))
+            .count()
+            .get_result(connection)?;
+        if user_saves + 1 > quotas.max_saves_per_user {
+            return Err(QuotaExceeded {
+                quota: Quota::SavesPerUser,
+                limit: quotas.max_saves_per_user,
+                requested: user_saves + 1,
+            }
+            .into());
+        }
+    }
+    let game_bytes =
+        game_save_bytes(game_id_s, connection)? - existing_bytes.unwrap_or(0) + new_bytes;
+    if game_bytes > quotas.max_game_bytes {
+        return Err(QuotaExceeded {
+            quota: Quota::GameBytes,
+            limit: quotas.max_game_bytes,
+            requested: game_bytes,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Number of revisions kept of each save when a game doesn't set its own limit
+pub const DEFAULT_SAVE_REVISIONS: i32 = 5;
+
+/// Writes a save as its next revision and deletes revisions past the game's limit. Has to be
+/// called in a transaction so two writes can't get the same revision or both fit in a quota.
+fn write_save(
+    user_id_s: &str,
+    game_id_s: &str,
+    file_name_s: &str,
+    data_b: &[u8],
+    if_revision: Option<&IfRevision>,
+    connection: &mut SqliteConnection,
+) -> Result<i32, Error> {
+    use self::schema::{games, save_revisions, saves};
+
+    let existing = saves::table
+        .filter(saves::user_id.eq(user_id_s))
+        .filter(saves::game_id.eq(game_id_s))
+        .filter(saves::file_name.eq(file_name_s))
+        .select((saves::revision, diesel::dsl::sql::<BigInt>("LENGTH(data)")))
+        .first::<(i32, i64)>(connection)
+        .optional()?;
+    let current = existing.map(|(revision, _)| revision);
+    if if_revision.is_some_and(|condition| !condition.matches(current)) {
+        return Err(RevisionMismatch { current }.into());
+    }
+    let (stored_codec, stored) = codec::encode(data_b);
+    check_quotas(
+        user_id_s,
+        game_id_s,
+        data_b.len() as i64,
+        stored.len() as i64,
+        existing.map(|(_, bytes)| bytes),
+        connection,
+    )?;
+    let next_revision = existing.map_or(1, |(current, _)| current + 1);
+
+    insert_into(saves::table)
+        .values((
+            saves::user_id.eq(user_id_s),
+            saves::game_id.eq(game_id_s),
+            saves::file_name.eq(file_name_s),
+            saves::data.eq(&*stored),
+            saves::revision.eq(next_revision),
+            saves::codec.eq(stored_codec.as_str()),
+        ))
+        .on_conflict((saves::user_id, saves::game_id, saves::file_name))
+        .do_update()
+        .set((
+            saves::data.eq(&*stored),
+            saves::revision.eq(next_revision),
+            saves::codec.eq(stored_codec.as_str()),
+        ))
+        .execute(connection)?;
+
+    let kept = games::table
+        .filter(games::id.eq(game_id_s))
+        .select(games::save_revisions)
+        .first::<i32>(connection)
+        .optional()?
+        .unwrap_or(DEFAULT_SAVE_REVISIONS);
+    if kept > 0 {
+        insert_into(save_revisions::table)
+            .values((
+                save_revisions::user_id.eq(user_id_s),
+                save_revisions::game_id.eq(game_id_s),
+                save_revisions::file_name.eq(file_name_s),
+                save_revisions::revision.eq(next_revision),
+                save_revisions::data.eq(&*stored),
+                save_revisions::codec.eq(stored_codec.as_str()),
+            ))
+            .execute(connection)?;
+    }
+    diesel::delete(
+        save_revisions::table
+            .filter(save_revisions::user_id.eq(user_id_s))
+            .filter(save_revisions::game_id.eq(game_id_s))
+            .filter(save_revisions::file_name.eq(file_name_s))
+            .filter(save_revisions::revision.le(next_revision - kept)),
+    )
+    .execute(connection)?;
+
+    Ok(next_revision)
+}
+
+/// Returns the kept revisions of a save, newest first. The newest revision is the current data.
+pub fn get_save_revisions(
+    user_id_s: &str,
+    game_id_s: &str,
+    file_name_s: &str,
+    db_path: &str,
+) -> QueryResult<Vec<SaveRevision>> {
+    use self::schema::save_revisions::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    save_revisions
+        .filter(user_id.eq(user_id_s))
+        .filter(game_id.eq(game_id_s))
+        .filter(file_name.eq(file_name_s))
+        .order_by(revision.desc())
+        .select(SaveRevision::as_select())
+        .get_results(connection)
+}
+
+/// Writes the data of an earlier revision as the newest revision of a save, so restoring can be
+/// undone by restoring the revision before it.
+///
+/// Returns the save after restoring, or `None` if the revision isn't kept.
+///
+/// # Errors
+/// * [`QuotaExceeded`] if the restored data would go over one of the game's [`StorageQuotas`]
+/// * If the database can't be written
+pub fn restore_save_revision(
+    user_id_s: &str,
+    game_id_s: &str,
+    file_name_s: &str,
+    revision_n: i32,
+    db_path: &str,
+) -> Result<Option<Save>, Error> {
+    use self::schema::{save_revisions, saves};
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| {
+        let Some((stored, stored_codec)) = save_revisions::table
+            .filter(save_revisions::user_id.eq(user_id_s))
+            .filter(save_revisions::game_id.eq(game_id_s))
+            .filter(save_revisions::file_name.eq(file_name_s))
+            .filter(save_revisions::revision.eq(revision_n))
+            .select((save_revisions::data, save_revisions::codec))
+            .first::<(Vec<u8>, String)>(connection)
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let data_b = codec::decode(Codec::parse(&stored_codec)?, &stored)?;
+        write_save(user_id_s, game_id_s, file_name_s, &data_b, None, connection)?;
+
+        let save = saves::table
+            .select(Save::as_select())
+            .filter(saves::user_id.eq(user_id_s))
+            .filter(saves::game_id.eq(game_id_s))
+            .filter(saves::file_name.eq(file_name_s))
+            .first(connection)?;
+        decode_save(save, connection).map(Some)
+    })
+}
+
+/// Applies a patch to the JSON document of a save and writes the result as its next revision.
+/// The save is read and written in one transaction, so concurrent writes can't be lost.
+///
+/// # Errors
+/// * [`SavePatchError`] if the save doesn't exist, isn't JSON, or the patch fails
+/// * [`RevisionMismatch`] if the save doesn't match `if_revision`
+/// * [`QuotaExceeded`] if the patched save would go over one of the game's quotas
+pub fn patch_save(
+    user_id_s: &str,
+    game_id_s: &str,
+    file_name_s: &str,
+    save_patch: &SavePatch,
+    if_revision: Option<&IfRevision>,
+    db_path: &str,
+) -> Result<Save, Error> {
+    use self::schema::saves;
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| {
+        apply_patch(
+            user_id_s,
+            game_id_s,
+            file_name_s,
+            save_patch,
+            if_revision,
+            connection,
+        )?;
+        let save = saves::table
+            .select(Save::as_select())
+            .filter(saves::user_id.eq(user_id_s))
+            .filter(saves::game_id.eq(game_id_s))
+            .filter(saves::file_name.eq(file_name_s))
+            .first(connection)?;
+        decode_save(save, connection)
+    })
+}
+
+/// Writes a patched save as its next revision and returns the revision. Has to be called in a
+/// transaction.
+fn apply_patch(
+    user_id_s: &str,
+    game_id_s: &str,
+    file_name_s: &str,
+    save_patch: &SavePatch,
+    if_revision: Option<&IfRevision>,
+    connection: &mut SqliteConnection,
+) -> Result<i32, Error> {
+    use self::schema::saves;
+    let save = saves::table
+        .select(Save::as_select())
+        .filter(saves::user_id.eq(user_id_s))
+        .filter(saves::game_id.eq(game_id_s))
+        .filter(saves::file_name.eq(file_name_s))
+        .first(connection)
+        .optional()?
+        .ok_or(SavePatchError::NotFound)?;
+    if if_revision.is_some_and(|condition| !condition.matches(Some(save.revision))) {
+        return Err(RevisionMismatch {
+            current: Some(save.revision),
+        }
+        .into());
+    }
+
+    let save = decode_save(save, connection)?;
+    let mut document = serde_json::from_slice::<serde_json::Value>(&save.data)
+        .map_err(|_| SavePatchError::NotJson)?;
+    match save_patch {
+        SavePatch::Json(operations) => json_patch::patch(&mut document, operations)
+            .map_err(|e| SavePatchError::Failed(e.to_string()))?,
+        SavePatch::Merge(fields) => json_patch::merge(&mut document, fields),
+    }
+    let data_b = serde_json::to_vec(&document)?;
+    write_save(user_id_s, game_id_s, file_name_s, &data_b, None, connection)
+}
+
+/// Deletes a save along with its revisions. Deleting a save that doesn't exist does nothing,
+/// unless `if_revision` requires it to exist. Has to be called in a transaction.
+fn delete_save(
+    user_id_s: &str,
+    game_id_s: &str,
+    file_name_s: &str,
+    if_revision: Option<&IfRevision>,
+    connection: &mut SqliteConnection,
+) -> Result<(), Error> {
+    use self::schema::{save_revisions, saves};
+    let current = saves::table
+        .filter(saves::user_id.eq(user_id_s))
+        .filter(saves::game_id.eq(game_id_s))
+        .filter(saves::file_name.eq(file_name_s))
+        .select(saves::revision)
+        .first::<i32>(connection)
+        .optional()?;
+    if if_revision.is_some_and(|condition| !condition.matches(current)) {
+        return Err(RevisionMismatch { current }.into());
+    }
+
+    diesel::delete(
+        saves::table
+            .filter(saves::user_id.eq(user_id_s))
+            .filter(saves::game_id.eq(game_id_s))
+            .filter(saves::file_name.eq(file_name_s)),
+    )
+    .execute(connection)?;
+    // a save written again under the same name starts over at revision 1
+    diesel::delete(
+        save_revisions::table
+            .filter(save_revisions::user_id.eq(user_id_s))
+            .filter(save_revisions::game_id.eq(game_id_s))
+            .filter(save_revisions::file_name.eq(file_name_s)),
+    )
+    .execute(connection)?;
+    Ok(())
+}
+
+/// What a [`SaveWrite`] does to its save
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveAction {
+    /// Creates or replaces the save with this data
+    Set(Vec<u8>),
+    /// Patches the JSON document of an existing save
+    Patch(SavePatch),
+    Delete,
+}
+
+/// One write of a batch, see [`write_saves`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveWrite {
+    pub user_id: String,
+    pub file_name: String,
+    pub action: SaveAction,
+    pub if_revision: Option<IfRevision>,
+}
+
+/// Applies several writes to a game's saves in one transaction, in order, so either every write
+/// is applied or none are. Later writes see the saves as left by earlier ones.
+///
+/// Returns the revision of each save after its write, or `None` for deleted saves.
+///
+/// # Errors
+/// * The error of the first write that failed, such as [`RevisionMismatch`], [`SavePatchError`]
+///   or [`QuotaExceeded`], with the position of the write as context
+pub fn write_saves(
+    game_id_s: &str,
+    writes: &[SaveWrite],
+    db_path: &str,
+) -> Result<Vec<Option<i32>>, Error> {
+    use anyhow::Context;
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| {
+        let mut revisions = Vec::with_capacity(writes.len());
+        for (index, write) in writes.iter().enumerate() {
+            let user_id_s = write.user_id.as_str();
+            let file_name_s = write.file_name.as_str();
+            let if_revision = write.if_revision.as_ref();
+            let revision_n = match &write.action {
+                SaveAction::Set(data_b) => write_save(
+                    user_id_s,
+                    game_id_s,
+                    file_name_s,
+                    data_b,
+                    if_revision,
+                    connection,
+                )
+                .map(Some),
+                SaveAction::Patch(save_patch) => apply_patch(
+                    user_id_s,
+                    game_id_s,
+                    file_name_s,
+                    save_patch,
+                    if_revision,
+                    connection,
+                )
+                .map(Some),
+                SaveAction::Delete => {
+                    delete_save(user_id_s, game_id_s, file_name_s, if_revision, connection)
+                        .map(|_| None)
+                }
+            }
+            .with_context(|| format!("Operation {} on {} failed", index, file_name_s))?;
+            revisions.push(revision_n);
+        }
+        Ok(revisions)
+    })
+}
+
+/// Sets how many revisions are kept of each of a game's saves. Revisions past the new limit are
+/// deleted the next time a save is written.
+///
+/// Returns the number of games updated, which is 0 if no game has the given id.
+pub fn set_save_revision_limit(id_s: &str, revisions: i32, db_path: &str) -> QueryResult<usize> {
+    use self::schema::games::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    diesel::update(games.filter(id.eq(id_s)))
+        .set(save_revisions.eq(revisions))
+        .execute(connection)
+}
+
+pub async fn get_save(user_id_s: &str, game_id_s: &str, file_name_s: &str, db_path: &str) -> Save {
+    use self::schema::saves::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    let save = saves
+        .select(Save::as_select())
+        .filter(user_id.eq(user_id_s))
+        .filter(game_id.eq(game_id_s))
+        .filter(file_name.eq(file_name_s))
+        .first(connection)
+        .expect("Could not get save");
+    decode_save(save, connection).expect("Could not decompress save")
+}
+
+/// Returns a global of a game, or `None` if it was never set
+pub fn get_global(game_id_s: &str, name_s: &str, db_path: &str) -> QueryResult<Option<GameGlobal>> {
+    let connection = &mut establish_connection(db_path);
+    find_global(game_id_s, name_s, connection)
+}
+
+/// Sets a global of a game to a JSON document, if its current revision matches `if_revision`
+///
+/// # Errors
+/// * [`RevisionMismatch`] if the global was changed since the caller read it
+pub fn set_global(
+    game_id_s: &str,
+    name_s: &str,
+    value_s: &str,
+    if_revision: Option<&IfRevision>,
+    db_path: &str,
+) -> Result<GameGlobal, Error> {
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| {
+        let current = find_global(game_id_s, name_s, connection)?.map(|global| global.revision);
+        if if_revision.is_some_and(|condition| !condition.matches(current)) {
+            return Err(RevisionMismatch { current }.into());
+        }
+        Ok(write_global(
+            game_id_s, name_s, value_s, current, connection,
+        )?)
+    })
+}
+
+/// Adds `by` to a global holding a number, starting from 0 if the global was never set. Whole
+/// numbers stay integers as long as they fit in an `i64`.
+///
+/// # Errors
+/// * [`GlobalIncrementError`] if the global isn't a number or the sum is too large
+pub fn increment_global(
+    game_id_s: &str,
+    name_s: &str,
+    by: f64,
+    db_path: &str,
+) -> Result<GameGlobal, Error> {
+    let connection = &mut establish_connection(db_path);
+    connection.immediate_transaction(|connection| {
+        let existing = find_global(game_id_s, name_s, connection)?;
+        let current = match &existing {
+            Some(global) => match serde_json::from_str(&global.value) {
+                Result::Ok(serde_json::Value::Number(number)) => number,
+                _ => return Err(GlobalIncrementError::NotNumber.into()),
+            },
+            None => serde_json::Number::from(0),
+        };
+        let sum = add_number(&current, by).ok_or(GlobalIncrementError::Overflow)?;
+        Ok(write_global(
+            game_id_s,
+            name_s,
+            &sum.to_string(),
+            existing.map(|global| global.revision),
+            connection,
+        )?)
+    })
+}
+
+/// The error returned when a global can't be incremented
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlobalIncrementError {
+    /// The global holds something other than a number
+    NotNumber,
+    /// The sum isn't a finite number
+    Overflow,
+}
+
+impl std::fmt::Display for GlobalIncrementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlobalIncrementError::NotNumber => {
+                write!(f, "Global isn't a number, so it can't be incremented")
+            }
+            GlobalIncrementError::Overflow => write!(f, "Global would become too large"),
+        }
+    }
+}
+
+impl std::error::Error for GlobalIncrementError {}
+
+fn find_global(
+    game_id_s: &str,
+    name_s: &str,
+    connection: &mut SqliteConnection,
+) -> QueryResult<Option<GameGlobal>> {
+    use self::schema::game_globals::dsl::*;
+    game_globals
+        .filter(game_id.eq(game_id_s))
+        .filter(name.eq(name_s))
+        .select(GameGlobal::as_select())
+        .first(connection)
+        .optional()
+}
+
+/// Writes a global as the revision after `current`. Has to be called in a transaction so two
+/// writes can't get the same revision.
+fn write_global(
+    game_id_s: &str,
+    name_s: &str,
+    value_s: &str,
+    current: Option<i32>,
+    connection: &mut SqliteConnection,
+) -> QueryResult<GameGlobal> {
+    use self::schema::game_globals::dsl::*;
+    let next_revision = current.map_or(1, |current| current + 1);
+    insert_into(game_globals)
+        .values((
+            game_id.eq(game_id_s),
+            name.eq(name_s),
+            value.eq(value_s),
+            revision.eq(next_revision),
+        ))
+        .on_conflict((game_id, name))
+        .do_update()
+        .set((
+            value.eq(value_s),
+            revision.eq(next_revision),
+            time_stamp.eq(diesel::dsl::sql::<Text>("CURRENT_TIMESTAMP")),
+        ))
+        .returning(GameGlobal::as_returning())
+        .get_result(connection)
+}
+
+/// Adds `by` to a JSON number, keeping whole numbers as integers while they fit
+fn add_number(current: &serde_json::Number, by: f64) -> Option<serde_json::Number> {
+    let whole = (by.fract() == 0.0 && by.abs() < i64::MAX as f64).then_some(by as i64);
+    if let Some(sum) = whole.and_then(|by| current.as_i64()?.checked_add(by)) {
+        return Some(sum.into());
+    }
+    serde_json::Number::from_f64(current.as_f64()? + by)
+}
+
+/// Adds `by` to a player's stat in a single upsert, starting from 0 if the stat was never set,
+/// and returns the stat
+pub fn increment_player_stat(
+    user_id_s: &str,
+    game_id_s: &str,
+    name_s: &str,
+    by: i64,
+    db_path: &str,
+) -> QueryResult<PlayerStat> {
+    use self::schema::player_stats::dsl::*;
+    use diesel::upsert::excluded;
+    let connection = &mut establish_connection(db_path);
+    insert_into(player_stats)
+        .values((
+            user_id.eq(user_id_s),
+            game_id.eq(game_id_s),
+            name.eq(name_s),
+            value.eq(by),
+        ))
+        .on_conflict((user_id, game_id, name))
+        .do_update()
+        .set((
+            value.eq(value + excluded(value)),
+            time_stamp.eq(diesel::dsl::sql::<Text>("CURRENT_TIMESTAMP")),
+        ))
+        .returning(PlayerStat::as_returning())
+        .get_result(connection)
+}
+
+/// Returns the stats of a game, by player and then by name. Only returns the stats of the given
+/// player and with the given name if there are any.
+pub fn get_player_stats(
+    game_id_s: &str,
+    user_id_s: Option<&str>,
+    name_s: Option<&str>,
+    db_path: &str,
+) -> QueryResult<Vec<PlayerStat>> {
+    use self::schema::player_stats::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    let mut query = player_stats
+        .filter(game_id.eq(game_id_s))
+        .select(PlayerStat::as_select())
+        .order_by((user_id, name))
+        .into_boxed();
+    if let Some(user_id_s) = user_id_s {
+        query = query.filter(user_id.eq(user_id_s));
+    }
+    if let Some(name_s) = name_s {
+        query = query.filter(name.eq(name_s));
+    }
+    query.load(connection)
+}
+
+/// Returns every stat of a user with the name of its game, by game name and then by stat name
+pub fn get_user_stats(user_id_s: &str, db_path: &str) -> QueryResult<Vec<(String, PlayerStat)>> {
+    use self::schema::{games, player_stats};
+    let connection = &mut establish_connection(db_path);
+    player_stats::table
+        .inner_join(games::table)
+        .filter(player_stats::user_id.eq(user_id_s))
+        .order_by((games::name, games::id, player_stats::name))
+        .select((games::name, PlayerStat::as_select()))
+        .load(connection)
+}
+
+/// Returns every entry of the daily challenges of a day with the name of its game, by game name
+pub fn get_daily_leaderboard_entries(
+    challenge_date_s: &str,
+    db_path: &str,
+) -> QueryResult<Vec<(String, LeaderboardEntry)>> {
+    use self::schema::{games, leaderboard};
+    let connection = &mut establish_connection(db_path);
+    leaderboard::table
+        .inner_join(games::table)
+        .filter(leaderboard::challenge_date.eq(challenge_date_s))
+        .order_by((games::name, games::id, leaderboard::row_id))
+        .select((games::name, LeaderboardEntry::as_select()))
+        .load(connection)
+}
+
+/// Returns the secret of the console with the given name, which is generated when the database
+/// is created
+pub fn get_console_secret(name_s: &str, db_path: &str) -> QueryResult<Vec<u8>> {
+    use self::schema::console_secrets::dsl::*;
+    let connection = &mut establish_connection(db_path);
+    console_secrets
+        .filter(name.eq(name_s))
+        .select(value)
+        .first(connection)
+}
+
+/// Returns all leadboard data for a given game title.
+/// In cases other than testing, db_path should be "local"
+pub fn get_leaderboard_game_data(
+    game_title: &str,
+    db_path: &str,
+) -> Result<Vec<LeaderboardEntry>, Error> {
+    use self::schema::games::dsl::{games, name};
+    use self::schema::leaderboard::dsl::{challenge_date, game_id, leaderboard};
+    let connection = &mut establish_connection(db_path);
+
+    let game = games
+        .select(Game::as_select())
+        .filter(name.eq(game_title))
+        .first(connection)?;
+    debug!(game_id = %game.id, "Found game with title: {}", game.name);
+
+    let data = leaderboard
+        .select(LeaderboardEntry::as_select())
+        .filter(game_id.eq(&game.id))
+        .filter(challenge_date.is_null())
+        .get_results(connection)?;
+    debug!(game_id = %game.id, "Found {} entries for {}", data.len(), game.name);
 
     Ok(data)
 }
@@ -346,7 +1947,8 @@ mod tests {
             &data_b,
             test_context.get_db_path(),
         )
-        .await;
+        .await
+        .expect("Failed to set save");
     }
 
     #[tokio::test]
@@ -359,6 +1961,384 @@ mod tests {
         assert_eq!(username, "user1".to_string())
     }
 
+    #[tokio::test]
+    pub async fn test_insert_leaderboard_entries() {
+        let context = TestContext::new("insert_leaderboard_entries").await;
+        setup_initial_data(context.get_db_path()).await;
+        let entry = |user: &str, value: f64| LeaderboardWrite {
+            user_id: user.to_string(),
+            value_name: "score".to_string(),
+            value_num: value,
+            ..Default::default()
+        };
+
+        let inserts = insert_leaderboard_entries(
+            "0",
+            &[
+                entry("1", 100.0),
+                entry("2", 50.0),
+                entry("1", 100.0),
+                // SQLite stores NaN as NULL, which the column doesn't allow
+                entry("2", f64::NAN),
+                entry("9", 10.0),
+            ],
+            context.get_db_path(),
+        )
+        .expect("Failed to insert entries");
+        assert_eq!(inserts[0], LeaderboardInsert::Stored);
+        assert_eq!(inserts[1], LeaderboardInsert::Stored);
+        assert_eq!(inserts[2], LeaderboardInsert::Duplicate);
+        assert!(matches!(inserts[3], LeaderboardInsert::Rejected(_)));
+        assert_eq!(
+            inserts[4],
+            LeaderboardInsert::Rejected("Player doesn't exist".to_string())
+        );
+
+        let entries = get_leaderboard(
+            Some("0".to_string()),
+            None,
+            None,
+            None,
+            Some("score".to_string()),
+            None,
+            None,
+            context.get_db_path(),
+        )
+        .await;
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[tokio::test]
+    pub async fn test_leaderboard_attachments() {
+        let context = TestContext::new("leaderboard_attachments").await;
+        setup_initial_data(context.get_db_path()).await;
+        let write = LeaderboardWrite {
+            user_id: "1".to_string(),
+            value_name: "time".to_string(),
+            value_num: 61.5,
+            details: Some(r#"{"track":"lake"}"#.to_string()),
+            attachment: Some(vec![0, 1, 2, 3]),
+            ..Default::default()
+        };
+
+        let id = insert_leaderboard_write("0", &write, context.get_db_path())
+            .expect("Failed to insert entry")
+            .expect("The entry should be new");
+        assert_eq!(
+            insert_leaderboard_write("0", &write, context.get_db_path()),
+            QueryResult::Ok(None)
+        );
+        assert_eq!(
+            get_leaderboard_attachment("0", id, context.get_db_path()),
+            QueryResult::Ok(Some(vec![0, 1, 2, 3]))
+        );
+        // entries of other games can't be read
+        assert_eq!(
+            get_leaderboard_attachment("1", id, context.get_db_path()),
+            QueryResult::Ok(None)
+        );
+        assert_eq!(
+            get_leaderboard_attachment_ids(&[id, id + 1], context.get_db_path()),
+            QueryResult::Ok(HashSet::from([id]))
+        );
+
+        mark_game_uninstalled("0", true, context.get_db_path()).expect("Failed to purge game");
+        assert_eq!(
+            get_leaderboard_attachment_ids(&[id], context.get_db_path()),
+            QueryResult::Ok(HashSet::new())
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_composite_leaderboard() {
+        let context = TestContext::new("composite_leaderboard").await;
+        setup_initial_data(context.get_db_path()).await;
+        let components_l = vec![
+            MetricComponent {
+                name: "score".to_string(),
+                ascending: false,
+            },
+            MetricComponent {
+                name: "time".to_string(),
+                ascending: true,
+            },
+        ];
+        assert_eq!(
+            set_leaderboard_components("0", "run", &components_l, context.get_db_path()).unwrap(),
+            1
+        );
+        assert_eq!(
+            set_leaderboard_components("404", "run", &components_l, context.get_db_path()).unwrap(),
+            0
+        );
+
+        let run = |user: &str, score: f64, time: f64| LeaderboardWrite {
+            user_id: user.to_string(),
+            value_name: "run".to_string(),
+            value_num: score,
+            tie_breakers: vec![time],
+            ..Default::default()
+        };
+        // the same score with a different time isn't a duplicate
+        let inserts = insert_leaderboard_entries(
+            "0",
+            &[
+                run("1", 100.0, 90.0),
+                run("1", 100.0, 80.0),
+                run("2", 120.0, 99.0),
+            ],
+            context.get_db_path(),
+        )
+        .expect("Failed to insert entries");
+        assert!(inserts
+            .iter()
+            .all(|insert| insert == &LeaderboardInsert::Stored));
+
+        let ranked = |ascending: Option<bool>| {
+            get_leaderboard(
+                Some("0".to_string()),
+                None,
+                None,
+                ascending,
+                Some("run".to_string()),
+                None,
+                None,
+                context.get_db_path(),
+            )
+        };
+        let entries = ranked(None).await;
+        let tuples: Vec<(f64, f64)> = entries
+            .iter()
+            .map(|entry| (entry.value_num, entry.tie_breaker_1))
+            .collect();
+        assert_eq!(tuples, vec![(120.0, 99.0), (100.0, 80.0), (100.0, 90.0)]);
+        let reversed = ranked(Some(true)).await;
+        assert_eq!(reversed[0].tie_breaker_1, 90.0);
+
+        // rebuilding the table keeps every entry
+        let connection = &mut establish_connection(context.get_db_path());
+        loop {
+            let reverted = connection
+                .revert_last_migration(MIGRATIONS)
+                .expect("Failed to revert migration");
+            if reverted.to_string() == "20261019000008" {
+                break;
+            }
+        }
+        connection
+            .run_pending_migrations(MIGRATIONS)
+            .expect("Failed to run migrations");
+        // only one of the entries differing by their time survives the old unique constraint
+        assert_eq!(ranked(None).await.len(), 2);
+    }
+
+    #[tokio::test]
+    pub async fn test_game_globals() {
+        let context = TestContext::new("game_globals").await;
+        setup_initial_data(context.get_db_path()).await;
+        assert_eq!(
+            get_global("0", "seed", context.get_db_path()),
+            QueryResult::Ok(None)
+        );
+
+        let created = set_global(
+            "0",
+            "seed",
+            "1234",
+            Some(&IfRevision::Absent),
+            context.get_db_path(),
+        )
+        .expect("Failed to create global");
+        assert_eq!((created.value.as_str(), created.revision), ("1234", 1));
+        let error = set_global(
+            "0",
+            "seed",
+            "5678",
+            Some(&IfRevision::Absent),
+            context.get_db_path(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<RevisionMismatch>(),
+            Some(&RevisionMismatch { current: Some(1) })
+        );
+        let swapped = set_global(
+            "0",
+            "seed",
+            "5678",
+            Some(&IfRevision::OneOf(vec![1])),
+            context.get_db_path(),
+        )
+        .expect("Failed to swap global");
+        assert_eq!(swapped.revision, 2);
+        // globals of other games are separate
+        assert_eq!(
+            get_global("1", "seed", context.get_db_path()),
+            QueryResult::Ok(None)
+        );
+
+        let global = increment_global("0", "ducks", 2.0, context.get_db_path()).unwrap();
+        assert_eq!((global.value.as_str(), global.revision), ("2", 1));
+        let global = increment_global("0", "ducks", 0.5, context.get_db_path()).unwrap();
+        assert_eq!(global.value, "2.5");
+        set_global("0", "name", "\"pond\"", None, context.get_db_path()).unwrap();
+        let error = increment_global("0", "name", 1.0, context.get_db_path()).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<GlobalIncrementError>(),
+            Some(&GlobalIncrementError::NotNumber)
+        );
+
+        // concurrent increments are never lost
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let db_path = context.get_db_path().to_string();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        increment_global("0", "rescued", 1.0, &db_path)
+                            .expect("Failed to increment global");
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let rescued = get_global("0", "rescued", context.get_db_path())
+            .unwrap()
+            .unwrap();
+        assert_eq!((rescued.value.as_str(), rescued.revision), ("100", 100));
+
+        mark_game_uninstalled("0", true, context.get_db_path()).expect("Failed to purge game");
+        assert_eq!(
+            get_global("0", "seed", context.get_db_path()),
+            QueryResult::Ok(None)
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_player_stats() {
+        let context = TestContext::new("player_stats").await;
+        setup_initial_data(context.get_db_path()).await;
+        for (user, name_s, by) in [("1", "jumps", 10), ("1", "jumps", -3), ("2", "jumps", 4)] {
+            increment_player_stat(user, "0", name_s, by, context.get_db_path())
+                .expect("Failed to increment stat");
+        }
+        let deaths = increment_player_stat("1", "0", "deaths", 1, context.get_db_path())
+            .expect("Failed to increment stat");
+        assert_eq!(deaths.value, 1);
+
+        let stats = get_player_stats("0", None, Some("jumps"), context.get_db_path()).unwrap();
+        let values: Vec<(&str, i64)> = stats
+            .iter()
+            .map(|stat| (stat.user_id.as_str(), stat.value))
+            .collect();
+        assert_eq!(values, vec![("1", 7), ("2", 4)]);
+        assert_eq!(
+            get_player_stats("0", Some("1"), None, context.get_db_path())
+                .unwrap()
+                .len(),
+            2
+        );
+
+        mark_game_uninstalled("0", true, context.get_db_path()).expect("Failed to purge game");
+        assert_eq!(
+            get_player_stats("0", None, None, context.get_db_path()),
+            QueryResult::Ok(vec![])
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_daily_challenge_entries() {
+        let context = TestContext::new("daily_challenge_entries").await;
+        setup_initial_data(context.get_db_path()).await;
+        let daily = |date: &str| LeaderboardWrite {
+            user_id: "1".to_string(),
+            value_name: "Score".to_string(),
+            value_num: 100.0,
+            challenge_date: Some(date.to_string()),
+            ..Default::default()
+        };
+        // the same score as an entry outside of the challenge is kept, but only once a day
+        for date in ["2026-10-18", "2026-10-19", "2026-10-19"] {
+            insert_leaderboard_write("0", &daily(date), context.get_db_path())
+                .expect("Failed to insert entry");
+        }
+
+        let scores = |date: Option<&str>| {
+            get_leaderboard(
+                Some("0".to_string()),
+                None,
+                None,
+                None,
+                Some("Score".to_string()),
+                None,
+                date.map(str::to_string),
+                context.get_db_path(),
+            )
+        };
+        assert_eq!(scores(None).await.len(), 2);
+        let entries = scores(Some("2026-10-19")).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].challenge_date.as_deref(), Some("2026-10-19"));
+        assert_eq!(
+            get_leaderboard_values("0", "Score", Some("2026-10-18"), context.get_db_path()),
+            QueryResult::Ok(vec![100.0])
+        );
+
+        let daily_entries =
+            get_daily_leaderboard_entries("2026-10-19", context.get_db_path()).unwrap();
+        assert_eq!(daily_entries.len(), 1);
+        assert_eq!(daily_entries[0].0, "game0");
+
+        let secret = get_console_secret("daily_challenge", context.get_db_path())
+            .expect("Failed to get the console secret");
+        assert_eq!(secret.len(), 32);
+    }
+
+    #[tokio::test]
+    pub async fn test_leaderboard_members() {
+        let context = TestContext::new("leaderboard_members").await;
+        setup_initial_data(context.get_db_path()).await;
+        let write = LeaderboardWrite {
+            user_id: "1".to_string(),
+            value_name: "coop time".to_string(),
+            value_num: 300.0,
+            team_name: Some("Ducklings".to_string()),
+            members: vec!["1".to_string(), "2".to_string()],
+            ..Default::default()
+        };
+        let id = insert_leaderboard_write("0", &write, context.get_db_path())
+            .expect("Failed to insert entry")
+            .expect("The entry should be new");
+
+        let members =
+            get_leaderboard_members(&[id], context.get_db_path()).expect("Failed to get members");
+        assert_eq!(members[&id], vec!["1".to_string(), "2".to_string()]);
+
+        // the entry is listed for every member, not only the one who posted it
+        let entries = get_leaderboard(
+            Some("0".to_string()),
+            Some("2".to_string()),
+            None,
+            None,
+            Some("coop time".to_string()),
+            None,
+            None,
+            context.get_db_path(),
+        )
+        .await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].team_name.as_deref(), Some("Ducklings"));
+
+        // every member has to be a player
+        let unknown_member = LeaderboardWrite {
+            value_num: 280.0,
+            members: vec!["1".to_string(), "9".to_string()],
+            ..write
+        };
+        assert!(insert_leaderboard_write("0", &unknown_member, context.get_db_path()).is_err());
+    }
+
     #[tokio::test]
     pub async fn test_get_leaderboard_game_data() {
         let context = TestContext::new("get_leaderboard_game_data").await;
@@ -394,4 +2374,779 @@ mod tests {
         // shouldn't error out if the default guest already exists
         create_default_guest(context.get_db_path());
     }
+
+    #[tokio::test]
+    pub async fn test_upsert_user() {
+        let context = TestContext::new("upsert_user").await;
+        setup_initial_data(context.get_db_path()).await;
+
+        let user = upsert_user("1", "renamed", context.get_db_path());
+        assert_eq!(user.name, "renamed");
+        assert_eq!(get_username("1", context.get_db_path()).unwrap(), "renamed");
+
+        let user = upsert_user("3", "user3", context.get_db_path());
+        assert_eq!(user.id, "3");
+        assert_eq!(get_username("3", context.get_db_path()).unwrap(), "user3");
+    }
+
+    #[tokio::test]
+    pub async fn test_set_game_enabled() {
+        let context = TestContext::new("set_game_enabled").await;
+        setup_initial_data(context.get_db_path()).await;
+
+        assert!(is_game_enabled("0", context.get_db_path()).unwrap());
+        // games missing from the database are treated as enabled
+        assert!(is_game_enabled("missing", context.get_db_path()).unwrap());
+
+        let updated =
+            set_game_enabled("0", false, context.get_db_path()).expect("Failed to disable game");
+        assert_eq!(updated, 1);
+        assert!(!is_game_enabled("0", context.get_db_path()).unwrap());
+        assert_eq!(
+            get_disabled_game_ids(context.get_db_path()).unwrap(),
+            vec!["0".to_string()]
+        );
+
+        // reinstalling a game shouldn't re-enable it
+        insert_game("0", "game0", true, context.get_db_path());
+        assert!(!is_game_enabled("0", context.get_db_path()).unwrap());
+
+        set_game_enabled("0", true, context.get_db_path()).expect("Failed to enable game");
+        assert!(get_disabled_game_ids(context.get_db_path())
+            .unwrap()
+            .is_empty());
+
+        let updated = set_game_enabled("missing", false, context.get_db_path())
+            .expect("Failed to run update");
+        assert_eq!(updated, 0);
+    }
+
+    #[tokio::test]
+    pub async fn test_mark_game_uninstalled() {
+        use self::schema::games::dsl::{games, id};
+        let context = TestContext::new("mark_game_uninstalled").await;
+        setup_initial_data(context.get_db_path()).await;
+        set_save("1", "0", "save", b"{}", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+
+        mark_game_uninstalled("0", false, context.get_db_path()).expect("Failed to uninstall");
+        let game = games
+            .select(Game::as_select())
+            .filter(id.eq("0"))
+            .first(&mut establish_connection(context.get_db_path()))
+            .unwrap();
+        assert!(!game.installed);
+        assert_eq!(
+            get_leaderboard_game_data("game0", context.get_db_path())
+                .unwrap()
+                .len(),
+            3
+        );
+
+        mark_game_uninstalled("0", true, context.get_db_path()).expect("Failed to uninstall");
+        assert!(get_leaderboard_game_data("game0", context.get_db_path())
+            .unwrap()
+            .is_empty());
+        let saves = get_save_data(
+            &Some("0".to_string()),
+            &None,
+            &None,
+            &None,
+            context.get_db_path(),
+        )
+        .await
+        .unwrap();
+        assert!(saves.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_get_leaderboard_page() {
+        let context = TestContext::new("get_leaderboard_page").await;
+        setup_initial_data(context.get_db_path()).await;
+        insert_leaderboard_entry("2", "0", "Score", 100.0, context.get_db_path()).unwrap();
+
+        let filter = LeaderboardFilter {
+            game_id: "0".to_string(),
+            value_name: Some("Score".to_string()),
+            ..LeaderboardFilter::default()
+        };
+        let (first_page, total) = get_leaderboard_page(&filter, None, 2, context.get_db_path())
+            .expect("Failed to get leaderboard page");
+        assert_eq!(total, 3);
+        let values: Vec<f64> = first_page.iter().map(|e| e.value_num).collect();
+        assert_eq!(values, vec![125.0, 100.0]);
+
+        // a new entry with a higher value shouldn't shift the next page
+        insert_leaderboard_entry("1", "0", "Score", 500.0, context.get_db_path()).unwrap();
+
+        let last = first_page.last().unwrap();
+        let after = LeaderboardPosition {
+            value_num: last.value_num,
+            tie_breakers: Vec::new(),
+            row_id: last.row_id,
+        };
+        let (second_page, total) =
+            get_leaderboard_page(&filter, Some(&after), 2, context.get_db_path())
+                .expect("Failed to get leaderboard page");
+        assert_eq!(total, 4);
+        assert_eq!(second_page.len(), 1);
+        assert_eq!(second_page[0].value_num, 100.0);
+        assert_ne!(second_page[0].row_id, last.row_id);
+
+        let ascending = LeaderboardFilter {
+            ascending: true,
+            ..filter
+        };
+        let (page, _) = get_leaderboard_page(&ascending, None, 10, context.get_db_path())
+            .expect("Failed to get leaderboard page");
+        let values: Vec<f64> = page.iter().map(|e| e.value_num).collect();
+        assert_eq!(values, vec![100.0, 100.0, 125.0, 500.0]);
+    }
+
+    #[tokio::test]
+    pub async fn test_get_save_data_page() {
+        let context = TestContext::new("get_save_data_page").await;
+        setup_initial_data(context.get_db_path()).await;
+        for (user, file) in [
+            ("1", "slot2"),
+            ("1", "slot1"),
+            ("2", "slot1"),
+            ("1", "config"),
+        ] {
+            set_save(user, "0", file, b"{}", context.get_db_path())
+                .await
+                .expect("Failed to set save");
+        }
+
+        let filter = SaveFilter {
+            game_id: "0".to_string(),
+            regex: Some("^slot".to_string()),
+            ..SaveFilter::default()
+        };
+        let (page, total) = get_save_data_page(&filter, None, 2, false, context.get_db_path())
+            .expect("Failed to get save data page");
+        assert_eq!(total, 3);
+        assert_eq!(page.len(), 2);
+        assert!(page
+            .iter()
+            .all(|(save, data)| save.file_name == "slot1" && data.is_none()));
+
+        let (last, _) = page.last().unwrap();
+        let after = SavePosition {
+            file_name: last.file_name.clone(),
+            row_id: last.row_id,
+        };
+        let (page, _) = get_save_data_page(&filter, Some(&after), 2, true, context.get_db_path())
+            .expect("Failed to get save data page");
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].0.file_name, "slot2");
+        assert_eq!(page[0].1.as_deref(), Some(&b"{}"[..]));
+
+        let invalid = SaveFilter {
+            file_name: Some("slot1".to_string()),
+            ..filter
+        };
+        assert!(get_save_data_page(&invalid, None, 2, false, context.get_db_path()).is_err());
+    }
+
+    #[tokio::test]
+    pub async fn test_save_file_name_filters() {
+        let context = TestContext::new("save_file_name_filters").await;
+        setup_initial_data(context.get_db_path()).await;
+        for file in ["slot1", "slot2", "Slot3", "slot*", "autosave"] {
+            set_save("1", "0", file, b"{}", context.get_db_path())
+                .await
+                .expect("Failed to set save");
+        }
+
+        let file_names = |filter: SaveFilter| {
+            let filter = SaveFilter {
+                game_id: "0".to_string(),
+                ..filter
+            };
+            let (page, total) = get_save_data_page(&filter, None, 10, false, context.get_db_path())
+                .expect("Failed to get save data page");
+            assert_eq!(total, page.len() as i64);
+            page.into_iter()
+                .map(|(save, _)| save.file_name)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            file_names(SaveFilter {
+                regex: Some("^[sS]lot\\d$".to_string()),
+                ..SaveFilter::default()
+            }),
+            vec!["Slot3", "slot1", "slot2"]
+        );
+        assert_eq!(
+            file_names(SaveFilter {
+                glob: Some("slot?".to_string()),
+                ..SaveFilter::default()
+            }),
+            vec!["slot*", "slot1", "slot2"]
+        );
+        assert_eq!(
+            file_names(SaveFilter {
+                prefix: Some("slot*".to_string()),
+                ..SaveFilter::default()
+            }),
+            vec!["slot*"]
+        );
+
+        // the v1 listing filters in SQLite too
+        let saves = get_save_data(
+            &Some("0".to_string()),
+            &None,
+            &None,
+            &Some("save$".to_string()),
+            context.get_db_path(),
+        )
+        .await
+        .expect("Failed to get save data");
+        assert_eq!(saves.len(), 1);
+        assert_eq!(saves[0].file_name, "autosave");
+
+        let invalid = SaveFilter {
+            game_id: "0".to_string(),
+            regex: Some("(".to_string()),
+            ..SaveFilter::default()
+        };
+        assert!(get_save_data_page(&invalid, None, 10, false, context.get_db_path()).is_err());
+        let invalid = SaveFilter {
+            game_id: "0".to_string(),
+            glob: Some("slot*".to_string()),
+            prefix: Some("slot".to_string()),
+            ..SaveFilter::default()
+        };
+        assert!(get_save_data_page(&invalid, None, 10, false, context.get_db_path()).is_err());
+    }
+
+    #[tokio::test]
+    pub async fn test_save_revisions() {
+        let context = TestContext::new("save_revisions").await;
+        setup_initial_data(context.get_db_path()).await;
+        set_save_revision_limit("0", 3, context.get_db_path()).expect("Failed to set limit");
+
+        for i in 1..=4 {
+            let save = set_save(
+                "1",
+                "0",
+                "save1",
+                &format!("{i}").into_bytes(),
+                context.get_db_path(),
+            )
+            .await
+            .expect("Failed to set save");
+            assert_eq!(save.revision, i);
+        }
+        let revisions = get_save_revisions("1", "0", "save1", context.get_db_path())
+            .expect("Failed to get revisions");
+        assert_eq!(
+            revisions.iter().map(|r| r.revision).collect::<Vec<i32>>(),
+            vec![4, 3, 2]
+        );
+
+        let restored = restore_save_revision("1", "0", "save1", 2, context.get_db_path())
+            .expect("Failed to restore revision")
+            .expect("Revision 2 should be kept");
+        assert_eq!(restored.revision, 5);
+        assert_eq!(restored.data, b"2");
+        assert_eq!(
+            get_save("1", "0", "save1", context.get_db_path())
+                .await
+                .data,
+            b"2"
+        );
+        assert!(
+            restore_save_revision("1", "0", "save1", 1, context.get_db_path())
+                .expect("Failed to restore revision")
+                .is_none()
+        );
+
+        set_save_revision_limit("0", 0, context.get_db_path()).expect("Failed to set limit");
+        set_save("1", "0", "save1", b"6", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        assert!(get_save_revisions("1", "0", "save1", context.get_db_path())
+            .unwrap()
+            .is_empty());
+
+        set_save_revision_limit("0", 3, context.get_db_path()).expect("Failed to set limit");
+        set_save("1", "0", "save2", b"1", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        assert_eq!(
+            get_save_revisions("1", "0", "save2", context.get_db_path())
+                .unwrap()
+                .len(),
+            1
+        );
+        mark_game_uninstalled("0", true, context.get_db_path()).expect("Failed to purge game");
+        assert!(get_save_revisions("1", "0", "save2", context.get_db_path())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_saves_are_per_game() {
+        let context = TestContext::new("saves_are_per_game").await;
+        setup_initial_data(context.get_db_path()).await;
+
+        set_save("1", "0", "save1", b"game 0", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        let other = set_save("1", "1", "save1", b"game 1", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        assert_eq!(other.revision, 1);
+        assert_eq!(
+            get_save("1", "0", "save1", context.get_db_path())
+                .await
+                .data,
+            b"game 0"
+        );
+        assert_eq!(
+            get_save("1", "1", "save1", context.get_db_path())
+                .await
+                .data,
+            b"game 1"
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_conditional_save() {
+        let context = TestContext::new("conditional_save").await;
+        setup_initial_data(context.get_db_path()).await;
+        let mismatch = |result: Result<Save, Error>| {
+            result
+                .expect_err("Save should be refused")
+                .downcast::<RevisionMismatch>()
+                .expect("Error should be a revision mismatch")
+        };
+
+        let exists = IfRevision::Any;
+        let current = mismatch(
+            set_save_if(
+                "1",
+                "0",
+                "save1",
+                b"1",
+                Some(&exists),
+                context.get_db_path(),
+            )
+            .await,
+        )
+        .current;
+        assert_eq!(current, None);
+
+        let save = set_save("1", "0", "save1", b"1", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        let read = IfRevision::OneOf(vec![save.revision]);
+        let save = set_save_if("1", "0", "save1", b"2", Some(&read), context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        assert_eq!(save.revision, 2);
+
+        // the second writer read revision 1 too and has to read again
+        let current = mismatch(
+            set_save_if("1", "0", "save1", b"3", Some(&read), context.get_db_path()).await,
+        )
+        .current;
+        assert_eq!(current, Some(2));
+        assert_eq!(
+            get_save("1", "0", "save1", context.get_db_path())
+                .await
+                .data,
+            b"2"
+        );
+        set_save_if(
+            "1",
+            "0",
+            "save1",
+            b"3",
+            Some(&exists),
+            context.get_db_path(),
+        )
+        .await
+        .expect("Failed to set save");
+    }
+
+    #[tokio::test]
+    pub async fn test_patch_save() {
+        let context = TestContext::new("patch_save").await;
+        setup_initial_data(context.get_db_path()).await;
+        let patch_error = |result: Result<Save, Error>| {
+            result
+                .expect_err("Patch should fail")
+                .downcast::<SavePatchError>()
+                .expect("Error should be a patch error")
+        };
+        let merge = SavePatch::Merge(serde_json::json!({ "level": 2 }));
+
+        assert_eq!(
+            patch_error(patch_save(
+                "1",
+                "0",
+                "save1",
+                &merge,
+                None,
+                context.get_db_path()
+            )),
+            SavePatchError::NotFound
+        );
+
+        set_save(
+            "1",
+            "0",
+            "save1",
+            br#"{"level":1,"items":["sword"],"gold":10}"#,
+            context.get_db_path(),
+        )
+        .await
+        .expect("Failed to set save");
+        let save = patch_save("1", "0", "save1", &merge, None, context.get_db_path())
+            .expect("Failed to merge patch");
+        assert_eq!(save.revision, 2);
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&save.data).unwrap(),
+            serde_json::json!({ "level": 2, "items": ["sword"], "gold": 10 })
+        );
+
+        let operations = serde_json::from_value(serde_json::json!([
+            { "op": "add", "path": "/items/-", "value": "shield" },
+            { "op": "remove", "path": "/gold" }
+        ]))
+        .unwrap();
+        let save = patch_save(
+            "1",
+            "0",
+            "save1",
+            &SavePatch::Json(operations),
+            Some(&IfRevision::OneOf(vec![2])),
+            context.get_db_path(),
+        )
+        .expect("Failed to apply JSON patch");
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&save.data).unwrap(),
+            serde_json::json!({ "level": 2, "items": ["sword", "shield"] })
+        );
+
+        // a failing operation leaves the save as it was
+        let operations = serde_json::from_value(serde_json::json!([
+            { "op": "replace", "path": "/level", "value": 5 },
+            { "op": "test", "path": "/level", "value": 1 }
+        ]))
+        .unwrap();
+        assert!(matches!(
+            patch_error(patch_save(
+                "1",
+                "0",
+                "save1",
+                &SavePatch::Json(operations),
+                None,
+                context.get_db_path()
+            )),
+            SavePatchError::Failed(_)
+        ));
+        assert_eq!(
+            get_save("1", "0", "save1", context.get_db_path())
+                .await
+                .revision,
+            3
+        );
+
+        set_save("1", "0", "notes", b"not json", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        assert_eq!(
+            patch_error(patch_save(
+                "1",
+                "0",
+                "notes",
+                &merge,
+                None,
+                context.get_db_path()
+            )),
+            SavePatchError::NotJson
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_write_saves() {
+        let context = TestContext::new("write_saves").await;
+        setup_initial_data(context.get_db_path()).await;
+        let write = |user: &str, file: &str, action: SaveAction| SaveWrite {
+            user_id: user.to_string(),
+            file_name: file.to_string(),
+            action,
+            if_revision: None,
+        };
+
+        set_save("1", "0", "old", b"{}", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        let revisions = write_saves(
+            "0",
+            &[
+                write(
+                    "1",
+                    "profile",
+                    SaveAction::Set(br#"{"name":"duck"}"#.to_vec()),
+                ),
+                write(
+                    "2",
+                    "profile",
+                    SaveAction::Set(br#"{"name":"goose"}"#.to_vec()),
+                ),
+                write(
+                    "1",
+                    "profile",
+                    SaveAction::Patch(SavePatch::Merge(serde_json::json!({ "level": 2 }))),
+                ),
+                write("1", "old", SaveAction::Delete),
+            ],
+            context.get_db_path(),
+        )
+        .expect("Failed to write saves");
+        assert_eq!(revisions, vec![Some(1), Some(1), Some(2), None]);
+        assert_eq!(
+            get_save("1", "0", "profile", context.get_db_path())
+                .await
+                .data,
+            br#"{"level":2,"name":"duck"}"#
+        );
+        assert!(get_save_revisions("1", "0", "old", context.get_db_path())
+            .unwrap()
+            .is_empty());
+
+        // the stale write fails, so the writes before it are rolled back
+        let mut stale = write("2", "profile", SaveAction::Delete);
+        stale.if_revision = Some(IfRevision::OneOf(vec![5]));
+        let error = write_saves(
+            "0",
+            &[
+                write("1", "world", SaveAction::Set(b"{}".to_vec())),
+                write("1", "profile", SaveAction::Delete),
+                stale,
+            ],
+            context.get_db_path(),
+        )
+        .expect_err("Batch should fail");
+        assert_eq!(
+            error.downcast_ref::<RevisionMismatch>(),
+            Some(&RevisionMismatch { current: Some(1) })
+        );
+        assert!(error.to_string().contains("Operation 2"));
+        let saves = get_save_data(
+            &Some("0".to_string()),
+            &Some("1".to_string()),
+            &None,
+            &None,
+            context.get_db_path(),
+        )
+        .await
+        .expect("Failed to get saves");
+        assert_eq!(
+            saves
+                .iter()
+                .map(|s| s.file_name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["profile"]
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_storage_quotas() {
+        let context = TestContext::new("storage_quotas").await;
+        setup_initial_data(context.get_db_path()).await;
+        let quotas = StorageQuotas {
+            max_save_bytes: 10,
+            max_saves_per_user: 2,
+            max_game_bytes: 25,
+        };
+        set_storage_quotas("0", &quotas, context.get_db_path()).expect("Failed to set quotas");
+
+        let quota_error = |result: Result<Save, Error>| {
+            result
+                .expect_err("Save should go over a quota")
+                .downcast::<QuotaExceeded>()
+                .expect("Error should be a quota error")
+        };
+
+        let exceeded = quota_error(set_save("1", "0", "a", &[0; 11], context.get_db_path()).await);
+        assert_eq!(exceeded.quota, Quota::SaveBytes);
+        assert_eq!(exceeded.requested, 11);
+
+        set_save("1", "0", "a", &[0; 10], context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        set_save("1", "0", "b", &[0; 10], context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        let exceeded = quota_error(set_save("1", "0", "c", &[0; 1], context.get_db_path()).await);
+        assert_eq!(exceeded.quota, Quota::SavesPerUser);
+
+        let exceeded = quota_error(set_save("2", "0", "a2", &[0; 6], context.get_db_path()).await);
+        assert_eq!(exceeded.quota, Quota::GameBytes);
+        assert_eq!(exceeded.requested, 26);
+        set_save("2", "0", "a2", &[0; 5], context.get_db_path())
+            .await
+            .expect("Failed to set save");
+
+        // a refused save leaves nothing behind
+        let (quotas_read, save_count, used_bytes) =
+            get_storage_usage("0", None, context.get_db_path()).expect("Failed to get usage");
+        assert_eq!(quotas_read, quotas);
+        assert_eq!(save_count, 3);
+        assert_eq!(used_bytes, 25);
+        let (_, user_save_count, _) =
+            get_storage_usage("0", Some("2"), context.get_db_path()).expect("Failed to get usage");
+        assert_eq!(user_save_count, 1);
+
+        let usage = get_games_storage_usage(context.get_db_path()).expect("Failed to get usage");
+        assert_eq!(usage[0].game_id, "0");
+        assert_eq!(usage[0].save_count, 3);
+        assert_eq!(usage[0].save_bytes, 25);
+        assert_eq!(usage[0].revision_bytes, 25);
+        assert_eq!(usage[1].save_bytes, 0);
+    }
+
+    #[tokio::test]
+    pub async fn test_save_compression() {
+        let context = TestContext::new("save_compression").await;
+        setup_initial_data(context.get_db_path()).await;
+        let world = serde_json::to_vec(&vec!["grass"; 2000]).unwrap();
+
+        let save = set_save("1", "0", "world", &world, context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        assert_eq!(save.data, world);
+        assert_eq!(save.codec, "zstd");
+        let (_, _, used_bytes) =
+            get_storage_usage("0", None, context.get_db_path()).expect("Failed to get usage");
+        assert!(used_bytes < world.len() as i64 / 10);
+
+        // saves stored before compression was added are compressed once they're read
+        {
+            use self::schema::saves::dsl::*;
+            insert_into(saves)
+                .values((
+                    user_id.eq("2"),
+                    game_id.eq("0"),
+                    file_name.eq("old"),
+                    data.eq(&world),
+                ))
+                .execute(&mut establish_connection(context.get_db_path()))
+                .expect("Failed to insert save");
+        }
+        let old_saves = get_save_data(
+            &Some("0".to_string()),
+            &Some("2".to_string()),
+            &None,
+            &None,
+            context.get_db_path(),
+        )
+        .await
+        .expect("Failed to get save data");
+        assert_eq!(old_saves[0].codec, "raw");
+        assert_eq!(old_saves[0].data, world);
+        let old_save = get_save("2", "0", "old", context.get_db_path()).await;
+        assert_eq!(old_save.codec, "zstd");
+        assert_eq!(old_save.data, world);
+
+        set_save("1", "0", "world", b"{}", context.get_db_path())
+            .await
+            .expect("Failed to set save");
+        let restored = restore_save_revision("1", "0", "world", 1, context.get_db_path())
+            .expect("Failed to restore revision")
+            .expect("Revision 1 should be kept");
+        assert_eq!(restored.data, world);
+    }
+
+    /// Compares listing saves with filtering in SQLite against loading every save and filtering
+    /// them afterwards, which is how saves used to be listed. Run it with
+    /// `cargo test --release bench_save_data_filtering -- --ignored --nocapture`
+    #[tokio::test]
+    #[ignore]
+    pub async fn bench_save_data_filtering() {
+        use self::schema::saves::dsl::*;
+        use std::time::{Duration, Instant};
+        const SAVES: usize = 5000;
+        const RUNS: u32 = 10;
+
+        let context = TestContext::new("bench_save_data_filtering").await;
+        setup_initial_data(context.get_db_path()).await;
+        let blob = vec![b'0'; 16 * 1024];
+        let rows: Vec<_> = (0..SAVES)
+            .map(|i| {
+                let name = match i % 50 {
+                    0 => format!("slot{:05}", i),
+                    _ => format!("autosave{:05}", i),
+                };
+                (
+                    user_id.eq("1"),
+                    game_id.eq("0"),
+                    file_name.eq(name),
+                    data.eq(blob.clone()),
+                )
+            })
+            .collect();
+        let mut connection = establish_connection(context.get_db_path());
+        insert_into(saves)
+            .values(&rows)
+            .execute(&mut connection)
+            .expect("Failed to insert saves");
+
+        let time = |f: &dyn Fn() -> usize| {
+            let start = Instant::now();
+            let mut count = 0;
+            for _ in 0..RUNS {
+                count = f();
+            }
+            (start.elapsed() / RUNS, count)
+        };
+
+        let (in_rust, in_rust_count) = time(&|| {
+            let re = Regex::new("^slot").unwrap();
+            saves
+                .filter(game_id.eq("0"))
+                .load::<Save>(&mut establish_connection(context.get_db_path()))
+                .expect("Failed to load saves")
+                .into_iter()
+                .filter(|save| re.is_match(&save.file_name))
+                .count()
+        });
+        let db_path = context.get_db_path();
+        let page = |filter: SaveFilter| {
+            move || {
+                get_save_data_page(&filter, None, 100, true, db_path)
+                    .expect("Failed to get save data page")
+                    .0
+                    .len()
+            }
+        };
+        let (with_regex, regex_count) = time(&page(SaveFilter {
+            game_id: "0".to_string(),
+            regex: Some("^slot".to_string()),
+            ..SaveFilter::default()
+        }));
+        let (with_prefix, prefix_count) = time(&page(SaveFilter {
+            game_id: "0".to_string(),
+            prefix: Some("slot".to_string()),
+            ..SaveFilter::default()
+        }));
+
+        println!("filtering {} saves in Rust:  {:?}", SAVES, in_rust);
+        println!("filtering {} saves by regex: {:?}", SAVES, with_regex);
+        println!("filtering {} saves by prefix: {:?}", SAVES, with_prefix);
+        assert_eq!(in_rust_count, SAVES / 50);
+        assert_eq!(regex_count, SAVES / 50);
+        assert_eq!(prefix_count, SAVES / 50);
+        assert!(with_regex < in_rust);
+        assert!(with_prefix < in_rust);
+        assert!(with_prefix > Duration::ZERO);
+    }
 }
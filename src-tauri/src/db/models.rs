@@ -1,5 +1,5 @@
 use diesel::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Queryable, Selectable, Debug, Serialize)]
 #[diesel(table_name = crate::db::schema::leaderboard)]
@@ -11,6 +11,28 @@ pub struct LeaderboardEntry {
     pub value_name: String,
     pub value_num: f64,
     pub time_stamp: String, // add time stamp
+    /// JSON text the game attached to the entry, such as the character or level used
+    pub details: Option<String>,
+    /// Name of the team that set the entry, whose members are in `leaderboard_members`
+    pub team_name: Option<String>,
+    /// Values after `value_num` of an entry for a composite metric, see [`MetricComponent`]
+    pub tie_breaker_1: f64,
+    pub tie_breaker_2: f64,
+    pub tie_breaker_3: f64,
+    /// Day of the daily challenge the entry was set in, such as `2026-10-19`
+    pub challenge_date: Option<String>,
+}
+
+impl LeaderboardEntry {
+    /// Every value of the entry in ranking order, starting with `value_num`
+    pub fn components(&self) -> [f64; 4] {
+        [
+            self.value_num,
+            self.tie_breaker_1,
+            self.tie_breaker_2,
+            self.tie_breaker_3,
+        ]
+    }
 }
 
 #[derive(Queryable, Selectable, Debug)]
@@ -29,6 +51,68 @@ pub struct Game {
     pub id: String,
     pub name: String,
     pub installed: bool,
+    pub enabled: bool,
+    /// Number of revisions kept of each save
+    pub save_revisions: i32,
+    #[diesel(embed)]
+    pub quotas: StorageQuotas,
+}
+
+/// Limits on how much a game can store, checked whenever a save is written
+#[derive(Queryable, Selectable, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[diesel(table_name = crate::db::schema::games)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct StorageQuotas {
+    /// Largest size of a single save in bytes
+    pub max_save_bytes: i64,
+    /// Largest number of saves each user can have
+    pub max_saves_per_user: i64,
+    /// Largest total size of the game's saves in bytes, not counting kept revisions
+    pub max_game_bytes: i64,
+}
+
+impl Default for StorageQuotas {
+    fn default() -> Self {
+        StorageQuotas {
+            max_save_bytes: 1024 * 1024,
+            max_saves_per_user: 100,
+            max_game_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Settings of one of a game's leaderboard values, such as `score`
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[diesel(table_name = crate::db::schema::leaderboard_metrics)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LeaderboardMetric {
+    pub game_id: String,
+    pub value_name: String,
+    /// Only whole numbers are accepted for the value
+    pub integer_only: bool,
+    /// JSON list of [`MetricComponent`]s if the value is ranked by several values
+    pub components: Option<String>,
+}
+
+impl LeaderboardMetric {
+    /// Values the metric is ranked by, in order, or an empty list for a metric ranked only by
+    /// `value_num`
+    pub fn components(&self) -> Vec<MetricComponent> {
+        self.components
+            .as_deref()
+            .and_then(|components| serde_json::from_str(components).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// One of the values a composite leaderboard metric is ranked by, such as the time breaking a
+/// tie between equal scores
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricComponent {
+    pub name: String,
+    /// Rank lower values first, such as for times. Defaults to ranking higher values first.
+    #[serde(default)]
+    pub ascending: bool,
 }
 
 #[derive(Queryable, Selectable, Debug)]
@@ -41,4 +125,79 @@ pub struct Save {
     pub file_name: String,
     pub data: Vec<u8>,
     pub time_stamp: String,
+    /// Increases by one every time the save is written
+    pub revision: i32,
+    /// How `data` is stored in the database. The db functions returning saves decompress
+    /// `data`, so this only tells how much space the save takes up.
+    pub codec: String,
+}
+
+/// A save without its data, for listing saves without loading every blob
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::db::schema::saves)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SaveMetadata {
+    pub row_id: i32,
+    pub user_id: String,
+    pub game_id: String,
+    pub file_name: String,
+    pub time_stamp: String,
+    pub revision: i32,
+}
+
+/// An earlier version of a save, without its data
+#[derive(Queryable, Selectable, Debug, Serialize)]
+#[diesel(table_name = crate::db::schema::save_revisions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SaveRevision {
+    pub user_id: String,
+    pub game_id: String,
+    pub file_name: String,
+    pub revision: i32,
+    /// When this revision was written
+    pub time_stamp: String,
+}
+
+/// How much space a game's saves take up
+#[derive(QueryableByName, Debug, Clone, PartialEq, Serialize)]
+pub struct GameStorageUsage {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub game_id: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub save_count: i64,
+    /// Total size of the current data of every save
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub save_bytes: i64,
+    /// Total size of every kept revision
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub revision_bytes: i64,
+}
+
+/// A value of a game shared by every player, such as a community goal
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = crate::db::schema::game_globals)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct GameGlobal {
+    pub game_id: String,
+    pub name: String,
+    /// A JSON document
+    pub value: String,
+    /// Increases by one every time the global is written
+    pub revision: i32,
+    pub time_stamp: String,
+}
+
+/// A lifetime stat of a player in a game, such as total jumps
+#[derive(Queryable, Selectable, Debug, Clone, PartialEq, Serialize)]
+#[diesel(table_name = crate::db::schema::player_stats)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PlayerStat {
+    pub user_id: String,
+    pub game_id: String,
+    pub name: String,
+    pub value: i64,
+    /// When the stat was last changed
+    pub time_stamp: String,
 }
@@ -1,10 +1,32 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    console_secrets (name) {
+        name -> Text,
+        value -> Binary,
+    }
+}
+
 diesel::table! {
     games (id) {
         id -> Text,
         name -> Text,
         installed -> Bool,
+        enabled -> Bool,
+        save_revisions -> Integer,
+        max_save_bytes -> BigInt,
+        max_saves_per_user -> BigInt,
+        max_game_bytes -> BigInt,
+    }
+}
+
+diesel::table! {
+    game_globals (game_id, name) {
+        game_id -> Text,
+        name -> Text,
+        value -> Text,
+        revision -> Integer,
+        time_stamp -> Text,
     }
 }
 
@@ -16,6 +38,45 @@ diesel::table! {
         value_name -> Text,
         value_num -> Double,
         time_stamp -> Text,
+        details -> Nullable<Text>,
+        team_name -> Nullable<Text>,
+        tie_breaker_1 -> Double,
+        tie_breaker_2 -> Double,
+        tie_breaker_3 -> Double,
+        challenge_date -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    leaderboard_attachments (entry_id) {
+        entry_id -> Integer,
+        data -> Binary,
+    }
+}
+
+diesel::table! {
+    leaderboard_members (entry_id, user_id) {
+        entry_id -> Integer,
+        user_id -> Text,
+    }
+}
+
+diesel::table! {
+    leaderboard_metrics (game_id, value_name) {
+        game_id -> Text,
+        value_name -> Text,
+        integer_only -> Bool,
+        components -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    player_stats (user_id, game_id, name) {
+        user_id -> Text,
+        game_id -> Text,
+        name -> Text,
+        value -> BigInt,
+        time_stamp -> Text,
     }
 }
 
@@ -27,6 +88,21 @@ diesel::table! {
         file_name -> Text,
         data -> Binary,
         time_stamp -> Text,
+        revision -> Integer,
+        codec -> Text,
+    }
+}
+
+diesel::table! {
+    save_revisions (row_id) {
+        row_id -> Integer,
+        user_id -> Text,
+        game_id -> Text,
+        file_name -> Text,
+        revision -> Integer,
+        data -> Binary,
+        time_stamp -> Text,
+        codec -> Text,
     }
 }
 
@@ -38,9 +114,30 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(game_globals -> games (game_id));
 diesel::joinable!(leaderboard -> games (game_id));
 diesel::joinable!(leaderboard -> users (user_id));
+diesel::joinable!(leaderboard_attachments -> leaderboard (entry_id));
+diesel::joinable!(leaderboard_members -> leaderboard (entry_id));
+diesel::joinable!(leaderboard_members -> users (user_id));
+diesel::joinable!(leaderboard_metrics -> games (game_id));
+diesel::joinable!(player_stats -> games (game_id));
+diesel::joinable!(player_stats -> users (user_id));
+diesel::joinable!(save_revisions -> games (game_id));
+diesel::joinable!(save_revisions -> users (user_id));
 diesel::joinable!(saves -> games (game_id));
 diesel::joinable!(saves -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(games, leaderboard, saves, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    console_secrets,
+    game_globals,
+    games,
+    leaderboard,
+    leaderboard_attachments,
+    leaderboard_members,
+    leaderboard_metrics,
+    player_stats,
+    save_revisions,
+    saves,
+    users,
+);
@@ -8,7 +8,7 @@ use crate::{
     },
 };
 use axum::Router;
-use axum_test::TestServer;
+use axum_test::{TestServer, TestServerBuilder};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use tempfile::NamedTempFile;
 use tokio::sync::{
@@ -18,7 +18,7 @@ use tokio::sync::{
 
 use super::{
     create_user, insert_game, insert_leaderboard_entry,
-    models::{Game, LeaderboardEntry, User},
+    models::{Game, LeaderboardEntry, StorageQuotas, User},
 };
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/");
@@ -54,6 +54,16 @@ pub struct TestContext {
 
 impl TestContext {
     pub async fn new(db_name: &str) -> Self {
+        Self::with_server(db_name, TestServer::builder()).await
+    }
+
+    /// Same as [`TestContext::new`], but the test server listens on a random local port so
+    /// real http clients can send requests to it. Get the url with `server.server_address()`.
+    pub async fn new_with_http(db_name: &str) -> Self {
+        Self::with_server(db_name, TestServer::builder().http_transport()).await
+    }
+
+    async fn with_server(db_name: &str, server_builder: TestServerBuilder) -> Self {
         let db_file = tempfile::Builder::new()
             .prefix(db_name)
             .suffix(".db")
@@ -77,7 +87,9 @@ impl TestContext {
             db_file,
             current_game_tx,
             notifier,
-            server: TestServer::new(app).expect("Failed to set up test server"),
+            server: server_builder
+                .build(app)
+                .expect("Failed to set up test server"),
         }
     }
 
@@ -111,11 +123,17 @@ pub fn setup_initial_game_data(db_path: &str) {
             id: String::from("1"),
             name: String::from("game1"),
             installed: true,
+            enabled: true,
+            save_revisions: super::DEFAULT_SAVE_REVISIONS,
+            quotas: StorageQuotas::default(),
         },
         Game {
             id: String::from("0"),
             name: String::from("game0"),
             installed: true,
+            enabled: true,
+            save_revisions: super::DEFAULT_SAVE_REVISIONS,
+            quotas: StorageQuotas::default(),
         },
     ];
 
@@ -133,6 +151,12 @@ pub fn setup_initial_leaderboard_data(db_path: &str) {
             time_stamp: "timestamp".to_string(),
             value_num: 100.0,
             row_id: 0, // placeholder
+            details: None,
+            team_name: None,
+            tie_breaker_1: 0.0,
+            tie_breaker_2: 0.0,
+            tie_breaker_3: 0.0,
+            challenge_date: None,
         },
         LeaderboardEntry {
             user_id: "2".to_string(),
@@ -141,6 +165,12 @@ pub fn setup_initial_leaderboard_data(db_path: &str) {
             time_stamp: "timestamp".to_string(),
             value_num: 125.0,
             row_id: 0, // placeholder
+            details: None,
+            team_name: None,
+            tie_breaker_1: 0.0,
+            tie_breaker_2: 0.0,
+            tie_breaker_3: 0.0,
+            challenge_date: None,
         },
         LeaderboardEntry {
             user_id: "1".to_string(),
@@ -149,6 +179,12 @@ pub fn setup_initial_leaderboard_data(db_path: &str) {
             time_stamp: "timestamp".to_string(),
             value_num: 423.50,
             row_id: 0, // placeholder
+            details: None,
+            team_name: None,
+            tie_breaker_1: 0.0,
+            tie_breaker_2: 0.0,
+            tie_breaker_3: 0.0,
+            challenge_date: None,
         },
     ];
 
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env};
+
+/// The `exec` field of a game's metadata.
+///
+/// Either a single url or path that is used on every platform, or a map from platform keys to
+/// launch entries. Platform keys are either an OS and architecture joined by a dash, such as
+/// `linux-aarch64` for the Raspberry Pi, or just an OS such as `windows`. OS and architecture
+/// names follow [`std::env::consts::OS`] and [`std::env::consts::ARCH`].
+///
+/// # Example (game-metadata.json)
+///
+/// ```json
+/// "exec": {
+///     "linux-aarch64": { "path": "bin/game.arm64", "args": ["--fullscreen"] },
+///     "linux-x86_64": { "path": "bin/game.x86_64", "env": { "SDL_VIDEODRIVER": "x11" } },
+///     "windows": { "path": "bin/game.exe" }
+/// }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Exec {
+    Single(String),
+    PerPlatform(HashMap<String, ExecEntry>),
+}
+
+/// A url or path to launch, along with the arguments and environment variables to launch it with.
+/// Arguments and environment variables are ignored for urls.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ExecEntry {
+    pub path: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
+impl Exec {
+    /// Returns the launch entry for the given OS and architecture. An entry for the exact
+    /// OS and architecture takes priority over an entry for just the OS.
+    pub fn resolve(&self, os: &str, arch: &str) -> Option<ExecEntry> {
+        match self {
+            Exec::Single(path) => Some(ExecEntry {
+                path: path.clone(),
+                args: Vec::new(),
+                env: HashMap::new(),
+            }),
+            Exec::PerPlatform(entries) => entries
+                .get(&format!("{os}-{arch}"))
+                .or_else(|| entries.get(os))
+                .cloned(),
+        }
+    }
+
+    /// Returns the launch entry for the platform the launcher is running on
+    pub fn resolve_current(&self) -> Option<ExecEntry> {
+        self.resolve(env::consts::OS, env::consts::ARCH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_exec_runs_everywhere() {
+        let exec: Exec = serde_json::from_str(r#""main.py""#).unwrap();
+        assert_eq!(exec, Exec::Single("main.py".to_string()));
+
+        let entry = exec
+            .resolve("windows", "x86_64")
+            .expect("No entry for platform");
+        assert_eq!(entry.path, "main.py");
+        assert!(entry.args.is_empty());
+        assert!(entry.env.is_empty());
+    }
+
+    #[test]
+    fn per_platform_exec() {
+        let exec: Exec = serde_json::from_str(
+            r#"{
+                "linux-aarch64": { "path": "game.arm64", "args": ["--fullscreen"] },
+                "linux": { "path": "game.x86_64", "env": { "SDL_VIDEODRIVER": "x11" } },
+                "windows-x86_64": { "path": "game.exe" }
+            }"#,
+        )
+        .unwrap();
+
+        let entry = exec
+            .resolve("linux", "aarch64")
+            .expect("No entry for platform");
+        assert_eq!(entry.path, "game.arm64");
+        assert_eq!(entry.args, vec!["--fullscreen".to_string()]);
+
+        // falls back to the entry for just the OS
+        let entry = exec
+            .resolve("linux", "x86_64")
+            .expect("No entry for platform");
+        assert_eq!(entry.path, "game.x86_64");
+        assert_eq!(entry.env.get("SDL_VIDEODRIVER").unwrap(), "x11");
+
+        assert!(exec.resolve("windows", "aarch64").is_none());
+        assert!(exec.resolve("macos", "aarch64").is_none());
+    }
+
+    #[test]
+    fn per_platform_exec_requires_path() {
+        let exec = serde_json::from_str::<Exec>(r#"{ "linux": { "args": [] } }"#);
+        assert!(exec.is_err());
+    }
+}
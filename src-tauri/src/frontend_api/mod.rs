@@ -2,22 +2,32 @@ use crate::db::get_username;
 use crate::db::{get_leaderboard, get_leaderboard_game_data, insert_game};
 use anyhow::Error;
 use chrono::{serde::ts_seconds_option, DateTime, Utc};
+use quackbox_client::types::LeaderboardComponent;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
     env,
     fs::{self, File},
     hash::{DefaultHasher, Hash, Hasher},
-    io::BufReader,
+    io::{self, BufReader},
     path::PathBuf,
     process::Command,
     sync::Arc,
 };
 use tauri::{AppHandle, Listener, Manager, State};
 use tokio::sync::{oneshot, watch::Sender, Mutex, Notify};
+use tracing::{debug, info, instrument, warn};
 use url::Url;
 
 use crate::db;
+use crate::game_dev_api::daily;
+use crate::game_dev_api::validation::{validate_value_name, MAX_METRIC_COMPONENTS};
+use crate::logging::{read_recent_logs, LogEntry, LogState};
+use crate::settings::{Settings, SettingsState};
+use exec::Exec;
+
+pub mod exec;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(try_from = "GameInfoJS")]
@@ -34,7 +44,9 @@ pub struct GameInfo {
     cover_image: Option<PathBuf>,
     times_played: u128,
     last_played: Option<DateTime<Utc>>,
-    exec: String,
+    exec: Exec,
+    enabled: bool,
+    available: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -53,13 +65,21 @@ struct GameInfoJS {
     times_played: u128,
     #[serde(with = "ts_seconds_option")]
     last_played: Option<DateTime<Utc>>,
-    exec: String,
+    exec: Exec,
+    #[serde(default = "enabled_default")]
+    enabled: bool,
+    #[serde(default)]
+    available: bool,
 }
 
 fn id_default() -> String {
     "0".to_string()
 }
 
+fn enabled_default() -> bool {
+    true
+}
+
 impl From<GameInfo> for GameInfoJS {
     fn from(game_info: GameInfo) -> Self {
         GameInfoJS {
@@ -75,6 +95,8 @@ impl From<GameInfo> for GameInfoJS {
             times_played: game_info.times_played,
             last_played: game_info.last_played,
             exec: game_info.exec,
+            enabled: game_info.enabled,
+            available: game_info.available,
         }
     }
 }
@@ -94,6 +116,8 @@ impl TryFrom<GameInfoJS> for GameInfo {
             times_played: game_info_js.times_played,
             last_played: game_info_js.last_played,
             exec: game_info_js.exec,
+            enabled: game_info_js.enabled,
+            available: game_info_js.available,
         })
     }
 
@@ -185,7 +209,7 @@ async fn get_game_info_list(
     // generating app data directory and games folder if it doesn't exist
     let app_data_dir = app_handle.path().app_data_dir()?.join("games");
 
-    println!("{:?}", app_data_dir);
+    debug!(games_dir = ?app_data_dir, "Scanning games folder");
 
     fs::create_dir_all(app_data_dir.clone())?;
 
@@ -207,17 +231,13 @@ async fn get_game_info_list(
             serde_json::from_reader(BufReader::new(game_metadata_file));
 
         if let Err(err) = game_metadata {
-            println!("Failed at {:#?}", game_metadata_path);
-            match err.classify() {
-                serde_json::error::Category::Io => println!("Failed to read json"),
-                serde_json::error::Category::Syntax => println!("JSON is not syntactically valid"),
-                serde_json::error::Category::Data => {
-                    println!("JSON data is not semantically correct")
-                }
-                serde_json::error::Category::Eof => {
-                    println!("Prematurely reached end of JSON file")
-                }
-            }
+            let reason = match err.classify() {
+                serde_json::error::Category::Io => "Failed to read json",
+                serde_json::error::Category::Syntax => "JSON is not syntactically valid",
+                serde_json::error::Category::Data => "JSON data is not semantically correct",
+                serde_json::error::Category::Eof => "Prematurely reached end of JSON file",
+            };
+            warn!(path = ?game_metadata_path, error = %err, "Skipping game metadata: {}", reason);
             continue;
         }
 
@@ -228,6 +248,9 @@ async fn get_game_info_list(
         folder_path.pop();
         game_metadata.file_path = folder_path;
 
+        // check if the game has something to run on this platform
+        game_metadata.available = game_metadata.exec.resolve_current().is_some();
+
         // create and set hash id
         let mut hasher = DefaultHasher::new();
         game_metadata.file_path.hash(&mut hasher);
@@ -249,16 +272,13 @@ async fn get_game_info_list(
         // set cover image to the canonicalized path if it exists
         game_metadata.cover_image = game_metadata
             .cover_image
-            .map(|cover_image: PathBuf| {
-                println!("{:?}", &cover_image);
-                fs::canonicalize(&cover_image)
-            })
+            .map(|cover_image: PathBuf| fs::canonicalize(&cover_image))
             .transpose()?;
 
         games_list.push(game_metadata);
     }
 
-    println!("{}", serde_json::to_string_pretty(games_list).unwrap());
+    debug!(games = games_list.len(), "Loaded game metadata");
 
     Ok(state.games_list.clone())
 }
@@ -275,7 +295,7 @@ struct GameData {
 }
 
 /// Make sure every game listed in the games\all-games.json file is in the local database
-fn check_all_games(app_handle: &AppHandle) {
+fn check_all_games(app_handle: &AppHandle, db_path: &str) {
     // getting the app data directory
     let app_data_dir = app_handle
         .path()
@@ -298,61 +318,632 @@ fn check_all_games(app_handle: &AppHandle) {
         serde_json::from_reader(reader).expect("Failed to read all-games.json");
 
     for game in games_list.games {
-        db::make_sure_game_exists(
-            &game.title,
-            &game.id,
-            app_handle
-                .path()
-                .app_data_dir()
-                .unwrap()
-                .join("local")
-                .with_extension("db")
-                .into_os_string()
-                .to_str()
-                .unwrap(),
-        );
+        db::make_sure_game_exists(&game.title, &game.id, db_path);
     }
 }
 
 // Given a list of games, set them to be installed in the database
-fn set_games_installed(games: &Vec<GameInfo>, app_handle: &AppHandle) {
+fn set_games_installed(games: &Vec<GameInfo>, db_path: &str) {
     for game in games {
-        db::insert_game(
-            &game.id.to_string(),
-            &game.title,
-            true,
-            app_handle
-                .path()
-                .app_data_dir()
-                .unwrap()
-                .join("local")
-                .with_extension("db")
-                .into_os_string()
-                .to_str()
-                .unwrap(),
-        );
+        db::insert_game(&game.id.to_string(), &game.title, true, db_path);
     }
 }
 
+/// Sets the `enabled` flag of every game from the database and removes disabled games from
+/// the list unless `include_disabled` is true.
+fn filter_disabled_games(
+    games: Vec<GameInfo>,
+    include_disabled: bool,
+    db_path: &str,
+) -> Result<Vec<GameInfo>, ErrorType> {
+    let disabled_ids = db::get_disabled_game_ids(db_path)?;
+
+    Ok(games
+        .into_iter()
+        .map(|mut game| {
+            game.enabled = !disabled_ids.contains(&game.id.to_string());
+            game
+        })
+        .filter(|game| include_disabled || game.enabled)
+        .collect())
+}
+
+/// Returns the installed games. Disabled games are left out unless `include_disabled` is true,
+/// which allows an admin screen to list them so they can be enabled again.
 #[tauri::command]
 pub async fn get_game_info(
     state: State<'_, Mutex<AppState>>,
     app_handle: AppHandle,
+    include_disabled: Option<bool>,
 ) -> Result<Vec<GameInfo>, ErrorType> {
     let games = get_game_info_list(&state, &app_handle).await?;
-    set_games_installed(&games, &app_handle);
+    let db_path = state.lock().await.db_path.clone();
+    set_games_installed(&games, &db_path);
     // Only populate the database with all games if code is running on the quackbox
     if cfg!(feature = "quackbox-raspi") {
-        check_all_games(&app_handle);
+        check_all_games(&app_handle, &db_path);
+    }
+    filter_disabled_games(games, include_disabled.unwrap_or(false), &db_path)
+}
+
+/// Enables or disables a game. A disabled game stays on disk, but is left out of
+/// `get_game_info` and refused by `play_game`.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('set_game_enabled', { id: '12345', enabled: false });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn set_game_enabled(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    enabled: bool,
+) -> Result<(), ErrorType> {
+    let id = id.parse::<u64>()?;
+    let updated = db::set_game_enabled(&id.to_string(), enabled, &state.lock().await.db_path)?;
+    if updated == 0 {
+        return Err("Game ID not found")?;
+    }
+    info!("Set game enabled");
+    Ok(())
+}
+
+/// Returns the kept revisions of a player's save for a game, newest first. The newest revision
+/// is the current data.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// const revisions = await invoke('get_save_revisions', { gameId: '12345', userId: '1', fileName: 'save1' });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn get_save_revisions(
+    state: State<'_, Mutex<AppState>>,
+    game_id: String,
+    user_id: String,
+    file_name: String,
+) -> Result<Vec<db::models::SaveRevision>, ErrorType> {
+    let game_id = game_id.parse::<u64>()?;
+    Ok(db::get_save_revisions(
+        &user_id,
+        &game_id.to_string(),
+        &file_name,
+        &state.lock().await.db_path,
+    )?)
+}
+
+/// Rolls a player's save back to an earlier revision, such as after a game bug corrupted it.
+/// The restored data becomes the newest revision, so the rollback can be undone.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('restore_save_revision', { gameId: '12345', userId: '1', fileName: 'save1', revision: 3 });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn restore_save_revision(
+    state: State<'_, Mutex<AppState>>,
+    game_id: String,
+    user_id: String,
+    file_name: String,
+    revision: i32,
+) -> Result<(), ErrorType> {
+    let game_id = game_id.parse::<u64>()?;
+    db::restore_save_revision(
+        &user_id,
+        &game_id.to_string(),
+        &file_name,
+        revision,
+        &state.lock().await.db_path,
+    )?
+    .ok_or("Save revision not found")?;
+    info!("Restored save revision");
+    Ok(())
+}
+
+/// Sets how many revisions are kept of each of a game's saves. 0 stops keeping revisions.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('set_save_revision_limit', { gameId: '12345', revisions: 10 });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn set_save_revision_limit(
+    state: State<'_, Mutex<AppState>>,
+    game_id: String,
+    revisions: u16,
+) -> Result<(), ErrorType> {
+    let game_id = game_id.parse::<u64>()?;
+    let updated = db::set_save_revision_limit(
+        &game_id.to_string(),
+        revisions.into(),
+        &state.lock().await.db_path,
+    )?;
+    if updated == 0 {
+        return Err("Game ID not found")?;
+    }
+    info!("Set save revision limit");
+    Ok(())
+}
+
+/// Returns how much space each game's saves take up, largest first, so an admin can find the
+/// games filling up the console's storage.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// const usage = await invoke('get_storage_usage');
+/// usage.forEach(({ name, save_bytes, revision_bytes }) => console.log(name, save_bytes + revision_bytes));
+/// ```
+#[tauri::command]
+pub async fn get_storage_usage(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<Vec<db::models::GameStorageUsage>, ErrorType> {
+    Ok(db::get_games_storage_usage(&state.lock().await.db_path)?)
+}
+
+/// Lifetime stats of a player in one game
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GameStats {
+    pub game_id: String,
+    pub game_name: String,
+    /// Every stat by name, such as `{ "jumps": 1200 }`
+    pub stats: BTreeMap<String, i64>,
+}
+
+/// Returns a player's lifetime stats, such as total jumps, for every game they have stats in,
+/// in order of the game's name.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// const games = await invoke('get_player_stats', { userId: '1' });
+/// games.forEach(({ game_name, stats }) => console.log(game_name, stats.jumps));
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn get_player_stats(
+    state: State<'_, Mutex<AppState>>,
+    user_id: String,
+) -> Result<Vec<GameStats>, ErrorType> {
+    player_stats_by_game(&user_id, &state.lock().await.db_path)
+}
+
+fn player_stats_by_game(user_id: &str, db_path: &str) -> Result<Vec<GameStats>, ErrorType> {
+    let mut games: Vec<GameStats> = Vec::new();
+    for (game_name, stat) in db::get_user_stats(user_id, db_path)? {
+        match games.last_mut() {
+            Some(game) if game.game_id == stat.game_id => {
+                game.stats.insert(stat.name, stat.value);
+            }
+            _ => games.push(GameStats {
+                game_id: stat.game_id,
+                game_name,
+                stats: BTreeMap::from([(stat.name, stat.value)]),
+            }),
+        }
     }
     Ok(games)
 }
 
+/// Sets the storage quotas of a game
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('set_storage_quotas', {
+///   gameId: '12345',
+///   quotas: { max_save_bytes: 65536, max_saves_per_user: 10, max_game_bytes: 1048576 },
+/// });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn set_storage_quotas(
+    state: State<'_, Mutex<AppState>>,
+    game_id: String,
+    quotas: db::models::StorageQuotas,
+) -> Result<(), ErrorType> {
+    let game_id = game_id.parse::<u64>()?;
+    if quotas.max_save_bytes < 0 || quotas.max_saves_per_user < 0 || quotas.max_game_bytes < 0 {
+        return Err("Storage quotas can't be negative")?;
+    }
+    let updated =
+        db::set_storage_quotas(&game_id.to_string(), &quotas, &state.lock().await.db_path)?;
+    if updated == 0 {
+        return Err("Game ID not found")?;
+    }
+    info!("Set storage quotas");
+    Ok(())
+}
+
+/// Sets whether a leaderboard value of a game only accepts whole numbers
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('set_leaderboard_metric', { gameId: '12345', valueName: 'coins', integerOnly: true });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn set_leaderboard_metric(
+    state: State<'_, Mutex<AppState>>,
+    game_id: String,
+    value_name: String,
+    integer_only: bool,
+) -> Result<(), ErrorType> {
+    let game_id = game_id.parse::<u64>()?;
+    validate_value_name(&value_name)?;
+    let updated = db::set_leaderboard_metric(
+        &game_id.to_string(),
+        &value_name,
+        integer_only,
+        &state.lock().await.db_path,
+    )?;
+    if updated == 0 {
+        return Err("Game ID not found")?;
+    }
+    info!("Set leaderboard metric");
+    Ok(())
+}
+
+/// Makes a leaderboard value of a game rank by several values in turn, such as by score, then by
+/// time. Games then post every value in `values`. An empty list ranks by `value_num` alone again.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('set_leaderboard_components', {
+///   gameId: '12345',
+///   valueName: 'run',
+///   components: [{ name: 'score' }, { name: 'time', ascending: true }],
+/// });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn set_leaderboard_components(
+    state: State<'_, Mutex<AppState>>,
+    game_id: String,
+    value_name: String,
+    components: Vec<db::models::MetricComponent>,
+) -> Result<(), ErrorType> {
+    let game_id = game_id.parse::<u64>()?;
+    validate_value_name(&value_name)?;
+    if components.len() == 1 || components.len() > MAX_METRIC_COMPONENTS {
+        return Err(format!(
+            "A value can be ranked by 2 to {} values",
+            MAX_METRIC_COMPONENTS
+        ))?;
+    }
+    for (index, component) in components.iter().enumerate() {
+        validate_value_name(&component.name)?;
+        if components[..index]
+            .iter()
+            .any(|other| other.name == component.name)
+        {
+            return Err(format!("{} is listed more than once", component.name))?;
+        }
+    }
+    let updated = db::set_leaderboard_components(
+        &game_id.to_string(),
+        &value_name,
+        &components,
+        &state.lock().await.db_path,
+    )?;
+    if updated == 0 {
+        return Err("Game ID not found")?;
+    }
+    info!("Set leaderboard components");
+    Ok(())
+}
+
+/// Deletes a game's folder and marks it as not installed. If `purge_data` is true, the game's
+/// saves and leaderboard entries are deleted as well.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('uninstall_game', { id: '12345', purgeData: true });
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn uninstall_game(
+    state: State<'_, Mutex<AppState>>,
+    id: String,
+    purge_data: Option<bool>,
+) -> Result<(), ErrorType> {
+    let mut state = state.lock().await;
+    let id = id.parse::<u64>()?;
+    let index = state
+        .games_list
+        .iter()
+        .position(|g| g.id == id)
+        .ok_or("Game ID not found")?;
+
+    uninstall_game_helper(
+        &state.games_list[index],
+        purge_data.unwrap_or(false),
+        &state.db_path,
+    )?;
+    state.games_list.remove(index);
+    info!("Uninstalled game");
+    Ok(())
+}
+
+fn uninstall_game_helper(
+    game_info: &GameInfo,
+    purge_data: bool,
+    db_path: &str,
+) -> Result<(), ErrorType> {
+    let game_id = game_info.id.to_string();
+    // the game is marked uninstalled before its files are removed, so it's never listed as
+    // installed without them. If only some of its files could be removed it stays uninstalled,
+    // and its data is only purged once they are all gone.
+    db::mark_game_uninstalled(&game_id, false, db_path)?;
+    match fs::remove_dir_all(&game_info.file_path) {
+        // the folder was deleted by hand
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            debug!("The game's files were already removed")
+        }
+        Err(e) => return Err(e.into()),
+        Ok(()) => {}
+    }
+    if purge_data {
+        db::mark_game_uninstalled(&game_id, true, db_path)?;
+    }
+    Ok(())
+}
+
+/// Returns the current console settings.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// const settings = await invoke('get_settings');
+/// console.log(settings.volume, settings.dev_api.port);
+/// ```
+#[tauri::command]
+pub async fn get_settings(settings_state: State<'_, SettingsState>) -> Result<Settings, ErrorType> {
+    Ok(settings_state.settings.lock().await.clone())
+}
+
+/// Updates the console settings and saves them to the settings file. Only the fields present
+/// in `changes` are updated. Returns the updated settings.
+///
+/// Changes to the dev API address, database path and autostart take effect the next time the
+/// launcher starts.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// * The updated settings are invalid, for example a volume above 100.
+/// * The settings file cannot be written.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// await invoke('update_settings', { changes: { volume: 50, dev_api: { port: 7000 } } });
+/// ```
+#[tauri::command]
+pub async fn update_settings(
+    settings_state: State<'_, SettingsState>,
+    changes: serde_json::Value,
+) -> Result<Settings, ErrorType> {
+    let mut settings = settings_state.settings.lock().await;
+    let updated = settings.merged_with(changes)?;
+    updated.save(&settings_state.dir)?;
+    *settings = updated.clone();
+    Ok(updated)
+}
+
+/// Returns the most recent backend log entries, newest first, so that errors can be seen
+/// without SSH access to the console.
+///
+/// # Arguments
+///
+/// * `limit` - The maximum number of entries to return. Defaults to 200.
+/// * `level` - The minimum level of entries to return, such as `"warn"` for warnings and
+///   errors. Defaults to every level.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// const errors = await invoke('get_recent_logs', { limit: 50, level: 'error' });
+/// errors.forEach(({ timestamp, message, fields }) => console.log(timestamp, message, fields));
+/// ```
+#[tauri::command]
+pub async fn get_recent_logs(
+    log_state: State<'_, LogState>,
+    limit: Option<usize>,
+    level: Option<String>,
+) -> Result<Vec<LogEntry>, ErrorType> {
+    Ok(read_recent_logs(
+        &log_state.dir,
+        limit.unwrap_or(200),
+        level.as_deref(),
+    )?)
+}
+
 #[derive(Serialize, Debug)]
 struct FrontendLeaderboardEntry {
     value_num: f64,
     username: String,
     time_stamp: String,
+    /// Name of the team that set the entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    team_name: Option<String>,
+    /// Usernames of every team member, or only `username` for an entry set by one player
+    usernames: Vec<String>,
+    /// Every value of an entry for a composite metric, in ranking order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<Vec<LeaderboardComponent>>,
+}
+
+/// Compares two entries of a composite metric by each of its components in turn, and then by
+/// age so the earliest of tied entries ranks first
+fn compare_components(
+    a: &db::models::LeaderboardEntry,
+    b: &db::models::LeaderboardEntry,
+    components: &[db::models::MetricComponent],
+) -> Ordering {
+    components
+        .iter()
+        .zip(a.components().iter().zip(b.components()))
+        .map(|(component, (a_value, b_value))| {
+            let ordering = a_value.total_cmp(&b_value);
+            if component.ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+        .then(a.row_id.cmp(&b.row_id))
+}
+
+/// Returns the components of every composite metric of a game by value name
+fn composite_metrics(
+    game_id: &str,
+    db_name: &str,
+) -> Result<HashMap<String, Vec<db::models::MetricComponent>>, ErrorType> {
+    Ok(db::get_leaderboard_metrics(game_id, db_name)?
+        .into_iter()
+        .map(|metric| (metric.value_name.clone(), metric.components()))
+        .filter(|(_, components)| !components.is_empty())
+        .collect())
+}
+
+/// Builds the entry shown by the frontend, with the usernames of the player or of every team
+/// member. `members` are the team members by entry, see [`db::get_leaderboard_members`].
+fn frontend_entry(
+    entry: db::models::LeaderboardEntry,
+    components: Option<&Vec<db::models::MetricComponent>>,
+    members: &mut HashMap<i32, Vec<String>>,
+    db_name: &str,
+) -> Result<FrontendLeaderboardEntry, Error> {
+    let username = get_username(&entry.user_id, db_name)?;
+    let usernames = match members.remove(&entry.row_id) {
+        Some(user_ids) => user_ids
+            .iter()
+            .map(|user_id| get_username(user_id, db_name))
+            .collect::<Result<Vec<String>, Error>>()?,
+        None => vec![username.clone()],
+    };
+    let components = components.map(|components| {
+        components
+            .iter()
+            .zip(entry.components())
+            .map(|(component, value)| LeaderboardComponent {
+                name: component.name.clone(),
+                value,
+            })
+            .collect()
+    });
+    Ok(FrontendLeaderboardEntry {
+        value_num: entry.value_num,
+        username,
+        time_stamp: entry.time_stamp,
+        team_name: entry.team_name,
+        usernames,
+        components,
+    })
+}
+
+/// Most entries of a daily leaderboard shown on the home screen
+const DAILY_LEADERBOARD_SIZE: usize = 10;
+
+/// The best entries of one value of a game's daily challenge
+#[derive(Serialize, Debug)]
+pub struct DailyLeaderboard {
+    game_id: String,
+    game_name: String,
+    value_name: String,
+    /// At most [`DAILY_LEADERBOARD_SIZE`] entries, best first
+    entries: Vec<FrontendLeaderboardEntry>,
+}
+
+/// Returns the leaderboards of today's daily challenges with their best entries, in order of
+/// the game's name and then the value's name. Games nobody played today's challenge of are left
+/// out.
+///
+/// # Example (Frontend)
+///
+/// ```javascript
+/// const leaderboards = await invoke('get_daily_leaderboards');
+/// leaderboards.forEach(({ game_name, value_name, entries }) =>
+///   console.log(game_name, value_name, entries[0].username, entries[0].value_num));
+/// ```
+#[tauri::command]
+#[instrument(skip(state))]
+pub async fn get_daily_leaderboards(
+    state: State<'_, Mutex<AppState>>,
+) -> Result<Vec<DailyLeaderboard>, ErrorType> {
+    let challenge_date = daily::format_date(daily::today());
+    daily_leaderboards(&challenge_date, &state.lock().await.db_path)
+}
+
+fn daily_leaderboards(
+    challenge_date: &str,
+    db_name: &str,
+) -> Result<Vec<DailyLeaderboard>, ErrorType> {
+    // entries come ordered by game, and are grouped by value within a game
+    let mut games: Vec<(
+        String,
+        String,
+        BTreeMap<String, Vec<db::models::LeaderboardEntry>>,
+    )> = Vec::new();
+    for (game_name, entry) in db::get_daily_leaderboard_entries(challenge_date, db_name)? {
+        match games.last_mut() {
+            Some((game_id, _, values)) if *game_id == entry.game_id => {
+                values
+                    .entry(entry.value_name.clone())
+                    .or_default()
+                    .push(entry);
+            }
+            _ => games.push((
+                entry.game_id.clone(),
+                game_name,
+                BTreeMap::from([(entry.value_name.clone(), vec![entry])]),
+            )),
+        }
+    }
+
+    let mut leaderboards = Vec::new();
+    for (game_id, game_name, values) in games {
+        let composites = composite_metrics(&game_id, db_name)?;
+        for (value_name, mut entries) in values {
+            match composites.get(&value_name) {
+                Some(components) => entries.sort_by(|a, b| compare_components(a, b, components)),
+                None => entries.sort_by(|a, b| {
+                    b.value_num
+                        .total_cmp(&a.value_num)
+                        .then(a.row_id.cmp(&b.row_id))
+                }),
+            }
+            entries.truncate(DAILY_LEADERBOARD_SIZE);
+            let entry_ids: Vec<i32> = entries.iter().map(|entry| entry.row_id).collect();
+            let mut members = db::get_leaderboard_members(&entry_ids, db_name)?;
+            let entries = entries
+                .into_iter()
+                .map(|entry| {
+                    frontend_entry(entry, composites.get(&value_name), &mut members, db_name)
+                })
+                .collect::<Result<Vec<FrontendLeaderboardEntry>, Error>>()?;
+            leaderboards.push(DailyLeaderboard {
+                game_id: game_id.clone(),
+                game_name: game_name.clone(),
+                value_name,
+                entries,
+            });
+        }
+    }
+    Ok(leaderboards)
 }
 
 /// Retrieves a json object of all leaderboard data for a given game.
@@ -378,25 +969,32 @@ fn get_leaderboard_data_helper(
     game_title: String,
     db_name: &str,
 ) -> Result<serde_json::Value, ErrorType> {
-    let data = get_leaderboard_game_data(&game_title, db_name)?;
+    let mut data = get_leaderboard_game_data(&game_title, db_name)?;
+    let composites = match data.first() {
+        Some(entry) => composite_metrics(&entry.game_id, db_name)?,
+        None => HashMap::new(),
+    };
+    // composite metrics are ranked here, the others are sorted by value_num by the frontend
+    data.sort_by(|a, b| {
+        a.value_name
+            .cmp(&b.value_name)
+            .then_with(|| match composites.get(&a.value_name) {
+                Some(components) => compare_components(a, b, components),
+                None => Ordering::Equal,
+            })
+    });
+    let entry_ids: Vec<i32> = data.iter().map(|entry| entry.row_id).collect();
+    let mut members = db::get_leaderboard_members(&entry_ids, db_name)?;
 
     let mut sorted_data: HashMap<String, Vec<FrontendLeaderboardEntry>> = HashMap::new();
     for entry in data {
-        match sorted_data.get_mut(&entry.value_name) {
-            Some(entries) => entries.push(FrontendLeaderboardEntry {
-                value_num: entry.value_num,
-                username: get_username(&entry.user_id, db_name)?,
-                time_stamp: entry.time_stamp,
-            }),
+        let value_name = entry.value_name.clone();
+        let frontend_entry =
+            frontend_entry(entry, composites.get(&value_name), &mut members, db_name)?;
+        match sorted_data.get_mut(&value_name) {
+            Some(entries) => entries.push(frontend_entry),
             None => {
-                sorted_data.insert(
-                    entry.value_name,
-                    vec![FrontendLeaderboardEntry {
-                        value_num: entry.value_num,
-                        username: get_username(&entry.user_id, db_name)?,
-                        time_stamp: entry.time_stamp,
-                    }],
-                );
+                sorted_data.insert(value_name, vec![frontend_entry]);
             }
         }
     }
@@ -411,6 +1009,8 @@ fn get_leaderboard_data_helper(
 ///
 /// This command finds the game with the specified ID in the `games_list`, minimizes the current window,
 /// and either opens a new window with the game's URL or runs the game's executable file.
+/// Games with per-platform exec entries are launched with the entry for the current platform,
+/// including its arguments and environment variables.
 ///
 /// # Arguments
 ///
@@ -427,6 +1027,8 @@ fn get_leaderboard_data_helper(
 ///
 /// This function will return an error if:
 /// * The game ID is not found in the `games_list`.
+/// * The game has been disabled.
+/// * The game has no exec entry for the current platform.
 /// * The current directory cannot be accessed.
 /// * The game executable path does not exist.
 /// * The game process cannot be started.
@@ -448,30 +1050,38 @@ fn get_leaderboard_data_helper(
 /// startGame('12345');
 /// ```
 #[tauri::command]
+#[instrument(skip(state, game_sender_state, settings_state, window, app_handle))]
 pub async fn play_game(
     state: State<'_, Mutex<AppState>>,
     game_sender_state: State<'_, GameSenderState>,
+    settings_state: State<'_, SettingsState>,
     window: tauri::Window,
     app_handle: AppHandle,
     id: String,
 ) -> Result<(), ErrorType> {
-    let games_list = &state.lock().await.games_list;
+    let api_url = settings_state.settings.lock().await.dev_api.api_url();
+    let state = state.lock().await;
+    let games_list = &state.games_list;
     let path = env::current_dir()?;
     let id = id.parse::<u64>()?;
     let game_info = games_list
         .iter()
         .find(|g| g.id == id)
         .ok_or("Game ID not found")?;
+    if !db::is_game_enabled(&id.to_string(), &state.db_path)? {
+        return Err("Game is disabled")?;
+    }
+    let exec_entry = game_info
+        .exec
+        .resolve_current()
+        .ok_or("Game is not available on this platform")?;
     game_sender_state.game_watch_tx.send(Some(id))?;
-    println!("sending id: {}", id);
     game_sender_state.notifier.notified().await;
-    println!("Recieved notification, starting game");
+    info!(title = %game_info.title, "Starting game");
 
     window.minimize()?;
 
-    let exec_url = Url::parse(&game_info.exec);
-
-    println!("{:#?}", exec_url);
+    let exec_url = Url::parse(&exec_entry.path);
 
     // check if exec_url is using http or https protocols and is valid
     match exec_url
@@ -493,21 +1103,29 @@ pub async fn play_game(
             wait_for_window_close(game_window).await;
         }
         None => {
-            let path = path.join(&game_info.file_path).join(&game_info.exec);
+            let path = path.join(&game_info.file_path).join(&exec_entry.path);
 
             // check if exec path exists
             if path.try_exists()? == false {
                 return Err("Exec path does not exist")?;
             }
-            println!("{:#?}", path);
+            debug!(?path, args = ?exec_entry.args, "Running game executable");
 
+            // games find the game dev API through this variable, see the quackbox-client crate
             let game_process = Command::new(path)
+                .args(&exec_entry.args)
+                .env(quackbox_client::API_URL_ENV, api_url)
+                .envs(&exec_entry.env)
                 .current_dir(&game_info.file_path)
                 .output()?;
 
-            println!("{}", String::from_utf8(game_process.stdout)?);
-            println!("{}", String::from_utf8(game_process.stderr)?);
-            println!("exit code status: {}", game_process.status);
+            let stdout = String::from_utf8_lossy(&game_process.stdout);
+            let stderr = String::from_utf8_lossy(&game_process.stderr);
+            if game_process.status.success() {
+                info!(status = %game_process.status, %stdout, %stderr, "Game exited");
+            } else {
+                warn!(status = %game_process.status, %stdout, %stderr, "Game exited with an error");
+            }
         }
     }
 
@@ -516,7 +1134,7 @@ pub async fn play_game(
     window.set_focus()?;
     window.set_fullscreen(true)?;
     game_sender_state.notifier.notified().await;
-    println!("Recieved notification, game closed");
+    info!("Game closed");
     Ok(())
 }
 
@@ -532,6 +1150,7 @@ async fn wait_for_window_close(window: tauri::WebviewWindow) {
     let _ = rx.await;
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use crate::db::test_context::{setup_initial_data, TestContext};
@@ -546,4 +1165,238 @@ mod tests {
 
         println!("{:?}", data);
     }
+
+    #[tokio::test]
+    async fn test_get_team_leaderboard_data() {
+        let context = TestContext::new("test_get_team_leaderboard_data").await;
+        setup_initial_data(context.get_db_path()).await;
+        let write = db::LeaderboardWrite {
+            user_id: "2".to_string(),
+            value_name: "Coop".to_string(),
+            value_num: 10.0,
+            team_name: Some("Ducklings".to_string()),
+            members: vec!["1".to_string(), "2".to_string()],
+            ..Default::default()
+        };
+        db::insert_leaderboard_write("0", &write, context.get_db_path())
+            .expect("Failed to insert team entry");
+
+        let data = get_leaderboard_data_helper("game0".to_string(), context.get_db_path())
+            .expect("Failed to get leaderboard data");
+
+        let coop = &data["data"]["Coop"][0];
+        assert_eq!(coop["team_name"], "Ducklings");
+        assert_eq!(coop["usernames"], serde_json::json!(["user1", "user2"]));
+        let score = &data["data"]["Score"][0];
+        assert!(score.get("team_name").is_none());
+        assert_eq!(score["usernames"], serde_json::json!([score["username"]]));
+    }
+
+    #[tokio::test]
+    async fn test_player_stats_by_game() {
+        let context = TestContext::new("test_player_stats_by_game").await;
+        setup_initial_data(context.get_db_path()).await;
+        for (game_id, name, by) in [("1", "jumps", 3), ("0", "deaths", 2), ("0", "coins", 5)] {
+            db::increment_player_stat("1", game_id, name, by, context.get_db_path())
+                .expect("Failed to increment stat");
+        }
+        db::increment_player_stat("2", "0", "coins", 7, context.get_db_path())
+            .expect("Failed to increment stat");
+
+        let games =
+            player_stats_by_game("1", context.get_db_path()).expect("Failed to get player stats");
+        let stats: Vec<(&str, &BTreeMap<String, i64>)> = games
+            .iter()
+            .map(|game| (game.game_name.as_str(), &game.stats))
+            .collect();
+        assert_eq!(
+            stats,
+            vec![
+                (
+                    "game0",
+                    &BTreeMap::from([("coins".to_string(), 5), ("deaths".to_string(), 2)])
+                ),
+                ("game1", &BTreeMap::from([("jumps".to_string(), 3)])),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_daily_leaderboards() {
+        let context = TestContext::new("test_daily_leaderboards").await;
+        setup_initial_data(context.get_db_path()).await;
+        db::set_leaderboard_components(
+            "1",
+            "run",
+            &[
+                db::models::MetricComponent {
+                    name: "score".to_string(),
+                    ascending: false,
+                },
+                db::models::MetricComponent {
+                    name: "time".to_string(),
+                    ascending: true,
+                },
+            ],
+            context.get_db_path(),
+        )
+        .expect("Failed to set components");
+        let entries = [
+            ("0", "2", "Score", 50.0, vec![], "2026-10-19"),
+            ("0", "1", "Score", 80.0, vec![], "2026-10-19"),
+            ("1", "1", "run", 10.0, vec![30.0], "2026-10-19"),
+            ("1", "2", "run", 10.0, vec![20.0], "2026-10-19"),
+            ("1", "2", "run", 99.0, vec![1.0], "2026-10-18"),
+        ];
+        for (game_id, user_id, value_name, value_num, tie_breakers, date) in entries {
+            let write = db::LeaderboardWrite {
+                user_id: user_id.to_string(),
+                value_name: value_name.to_string(),
+                value_num,
+                tie_breakers,
+                challenge_date: Some(date.to_string()),
+                ..Default::default()
+            };
+            db::insert_leaderboard_write(game_id, &write, context.get_db_path())
+                .expect("Failed to insert entry");
+        }
+
+        let leaderboards = daily_leaderboards("2026-10-19", context.get_db_path())
+            .expect("Failed to get daily leaderboards");
+        let ranking: Vec<(&str, &str, Vec<&str>)> = leaderboards
+            .iter()
+            .map(|leaderboard| {
+                (
+                    leaderboard.game_name.as_str(),
+                    leaderboard.value_name.as_str(),
+                    leaderboard
+                        .entries
+                        .iter()
+                        .map(|entry| entry.username.as_str())
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranking,
+            vec![
+                ("game0", "Score", vec!["user1", "user2"]),
+                ("game1", "run", vec!["user2", "user1"]),
+            ]
+        );
+        assert!(leaderboards[1].entries[0].components.is_some());
+    }
+
+    fn test_game_info(id: u64, file_path: PathBuf) -> GameInfo {
+        GameInfo {
+            id,
+            title: format!("game{id}"),
+            file_path,
+            author: "author".to_string(),
+            summary: "summary".to_string(),
+            release_date: "2025-01-01".to_string(),
+            multiplayer: false,
+            genres: Vec::new(),
+            cover_image: None,
+            times_played: 0,
+            last_played: None,
+            exec: Exec::Single("main.py".to_string()),
+            enabled: true,
+            available: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filter_disabled_games() {
+        let context = TestContext::new("test_filter_disabled_games").await;
+        setup_initial_data(context.get_db_path()).await;
+        db::set_game_enabled("1", false, context.get_db_path()).expect("Failed to disable game");
+
+        let games = vec![
+            test_game_info(0, PathBuf::new()),
+            test_game_info(1, PathBuf::new()),
+        ];
+
+        let enabled_games = filter_disabled_games(games.clone(), false, context.get_db_path())
+            .expect("Failed to filter games");
+        assert_eq!(enabled_games.len(), 1);
+        assert_eq!(enabled_games[0].id, 0);
+
+        let all_games = filter_disabled_games(games, true, context.get_db_path())
+            .expect("Failed to filter games");
+        assert_eq!(all_games.len(), 2);
+        assert!(all_games[0].enabled);
+        assert!(!all_games[1].enabled);
+    }
+
+    #[tokio::test]
+    async fn test_uninstall_game() {
+        let context = TestContext::new("test_uninstall_game").await;
+        setup_initial_data(context.get_db_path()).await;
+
+        let game_dir = tempfile::tempdir().expect("Failed to create game directory");
+        let game_path = game_dir.path().join("game0");
+        fs::create_dir_all(&game_path).unwrap();
+        fs::write(game_path.join("game-metadata.json"), "{}").unwrap();
+
+        uninstall_game_helper(
+            &test_game_info(0, game_path.clone()),
+            true,
+            context.get_db_path(),
+        )
+        .expect("Failed to uninstall game");
+
+        assert!(!game_path.exists());
+        assert!(get_leaderboard_game_data("game0", context.get_db_path())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_uninstall_game_without_files() {
+        use db::schema::games::dsl::{games, id, installed};
+        use diesel::prelude::*;
+        let context = TestContext::new("test_uninstall_game_without_files").await;
+        setup_initial_data(context.get_db_path()).await;
+
+        let is_installed = |game_id: &str| -> bool {
+            games
+                .select(installed)
+                .filter(id.eq(game_id))
+                .first(&mut db::establish_connection(context.get_db_path()))
+                .unwrap()
+        };
+
+        // a folder deleted by hand doesn't stop the game from being uninstalled
+        let game_dir = tempfile::tempdir().expect("Failed to create game directory");
+        let missing_path = game_dir.path().join("missing");
+        uninstall_game_helper(
+            &test_game_info(0, missing_path),
+            true,
+            context.get_db_path(),
+        )
+        .expect("Failed to uninstall game");
+        assert!(!is_installed("0"));
+        assert!(get_leaderboard_game_data("game0", context.get_db_path())
+            .unwrap()
+            .is_empty());
+
+        // a game whose files can't be removed stays uninstalled, but keeps its data
+        let not_a_folder = game_dir.path().join("game1");
+        fs::write(&not_a_folder, "{}").unwrap();
+        db::insert_leaderboard_entry("1", "1", "Score", 10.0, context.get_db_path()).unwrap();
+        assert!(uninstall_game_helper(
+            &test_game_info(1, not_a_folder),
+            true,
+            context.get_db_path()
+        )
+        .is_err());
+        assert!(!is_installed("1"));
+        assert_eq!(
+            get_leaderboard_game_data("game1", context.get_db_path())
+                .unwrap()
+                .len(),
+            1
+        );
+    }
 }
@@ -0,0 +1,92 @@
+//! Daily challenges, where every player plays the same generated level for a day.
+//!
+//! The seed of a day is derived from a secret the console generates when its database is
+//! created, so players can't work out the levels of days to come.
+
+use super::validation::LeaderboardViolation;
+use chrono::{Days, Local, NaiveDate};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Format of a challenge date, such as `2026-10-19`
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Name of the console secret seeds are derived from
+pub const SECRET_NAME: &str = "daily_challenge";
+
+/// The console's current day. Every player of a console is in the same place, so days follow
+/// the console's local time.
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+/// Returns the seed of a game's daily challenge on the given day
+pub fn challenge_seed(secret: &[u8], game_id: &str, date: NaiveDate) -> u32 {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(game_id.as_bytes());
+    mac.update(b"/");
+    mac.update(format_date(date).as_bytes());
+    let digest = mac.finalize().into_bytes();
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+/// Checks the challenge date of a leaderboard entry. Entries can be set in today's challenge,
+/// or in yesterday's so a run started before midnight still counts.
+pub fn check_challenge_date(date: &str, today: NaiveDate) -> Result<(), LeaderboardViolation> {
+    let parsed = NaiveDate::parse_from_str(date, DATE_FORMAT)
+        .ok()
+        // entries are looked up by the exact text, so `2026-10-9` isn't accepted either
+        .filter(|parsed| format_date(*parsed) == date)
+        .ok_or_else(|| LeaderboardViolation::InvalidChallengeDate(date.to_string()))?;
+    if parsed == today || today.checked_sub_days(Days::new(1)) == Some(parsed) {
+        Ok(())
+    } else {
+        Err(LeaderboardViolation::ChallengeClosed(date.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_seed() {
+        let day = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let next_day = NaiveDate::from_ymd_opt(2026, 10, 20).unwrap();
+        let seed = challenge_seed(b"secret", "1", day);
+        assert_eq!(seed, challenge_seed(b"secret", "1", day));
+        assert_ne!(seed, challenge_seed(b"secret", "1", next_day));
+        assert_ne!(seed, challenge_seed(b"secret", "2", day));
+        assert_ne!(seed, challenge_seed(b"other secret", "1", day));
+    }
+
+    #[test]
+    fn test_check_challenge_date() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        assert_eq!(check_challenge_date("2026-03-01", today), Ok(()));
+        assert_eq!(check_challenge_date("2026-02-28", today), Ok(()));
+        assert_eq!(
+            check_challenge_date("2026-02-27", today),
+            Err(LeaderboardViolation::ChallengeClosed(
+                "2026-02-27".to_string()
+            ))
+        );
+        assert_eq!(
+            check_challenge_date("2026-03-02", today),
+            Err(LeaderboardViolation::ChallengeClosed(
+                "2026-03-02".to_string()
+            ))
+        );
+        assert_eq!(
+            check_challenge_date("2026-3-1", today),
+            Err(LeaderboardViolation::InvalidChallengeDate(
+                "2026-3-1".to_string()
+            ))
+        );
+        assert!(check_challenge_date("yesterday", today).is_err());
+    }
+}
@@ -1,12 +1,27 @@
-use crate::db::{self};
+use super::daily::{challenge_seed, check_challenge_date, format_date, today, SECRET_NAME};
+use super::stats::{check_buckets, leaderboard_stats, parse_percentiles};
+use super::validation::{
+    team_slots, validate_team, validate_value_name, LeaderboardRules, LeaderboardViolation,
+    MAX_VALUE_NAME_LEN,
+};
+use crate::db::{
+    self,
+    models::{GameGlobal, PlayerStat},
+    GlobalIncrementError, IfRevision, LeaderboardInsert, LeaderboardWrite, Quota, QuotaExceeded,
+    RevisionMismatch, SaveAction, SavePatch, SavePatchError, SaveWrite,
+};
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{
+        header::{CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
     Json,
 };
 use axum_macros::FromRef;
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use serde_json::{from_str, Value};
 use std::sync::Arc;
 use std::{option::Option, path::PathBuf};
@@ -14,6 +29,33 @@ use tokio::{
     sync::watch::Receiver,
     sync::{Notify, RwLock},
 };
+use tracing::{debug, error, instrument, Span};
+
+// The request and response bodies are shared with the Rust client SDK so they can't drift apart
+pub use quackbox_client::types::{
+    DailyChallenge, GlobalEntry, GlobalIncrement, GlobalPost, HistogramBucket, LeaderboardBatch,
+    LeaderboardBatchResult, LeaderboardComponent, LeaderboardEntry, LeaderboardEntryStatus,
+    LeaderboardGetParams, LeaderboardPercentile, LeaderboardPost, LeaderboardStats,
+    LeaderboardStatsParams, LeaderboardTeam, QuotaError, SaveDataBatch, SaveDataBatchResult,
+    SaveDataEntry, SaveDataGetParams, SaveDataOperation, SaveDataPatchParams, SaveDataPost,
+    StatEntry, StatIncrement, StatsGetParams, StorageGetParams, StorageUsage, ValidationError,
+    JSON_PATCH_CONTENT_TYPE, MERGE_PATCH_CONTENT_TYPE,
+};
+
+/// Largest number of operations in a save data batch or entries in a leaderboard batch
+pub const MAX_BATCH_OPERATIONS: usize = 100;
+
+/// Largest attachment of a leaderboard entry in bytes, once decoded
+pub const MAX_ATTACHMENT_BYTES: usize = 256 * 1024;
+
+/// Longest key of a global, in characters
+pub const MAX_GLOBAL_KEY_LEN: usize = 64;
+
+/// Largest value of a global in bytes, as JSON
+pub const MAX_GLOBAL_BYTES: usize = 64 * 1024;
+
+/// Largest amount a stat can be changed by at once, either way, so a stat can't overflow
+pub const MAX_STAT_DELTA: i64 = 1_000_000_000;
 
 // TODO: rename to not be confused with the managed tauri app state
 #[derive(Clone, FromRef)]
@@ -36,70 +78,312 @@ pub struct GameState {
 
 pub type GameStateShared = Arc<GameState>;
 
-#[derive(Deserialize, Serialize)]
-pub struct LeaderboardPost {
-    pub value_name: String,
-    pub value_num: f64,
-    pub player_slot: i16,
+/// Returns the id of the running game, if there is one
+pub(crate) async fn running_game_id(game_state: &GameStateShared) -> Option<String> {
+    game_state
+        .id
+        .read()
+        .await
+        .map(|game_id| game_id.to_string())
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct SaveDataPost {
-    pub file_name: String,
-    pub data: serde_json::Value, // This data should be stored in the database as BSON data, is this the correct type?
-    pub player_slot: i16,
+/// Returns the id of the running game, or a 409 response if no game is running
+async fn current_game_id(game_state: &GameStateShared) -> Result<String, Response> {
+    match running_game_id(game_state).await {
+        Some(game_id) => Ok(game_id),
+        None => {
+            debug!("Rejected request because no game is running");
+            Err((StatusCode::CONFLICT, "No game is currently running").into_response())
+        }
+    }
 }
 
 /// Handles HTTP post requests for the axum webserver by inserting the given entry in the
 /// SQLite database.
+#[utoipa::path(
+    post,
+    path = "/api/v1/leaderboard",
+    summary = "Add a leaderboard entry for a player",
+    tag = "leaderboard",
+    request_body = LeaderboardPost,
+    responses(
+        (status = 200, description = "The entry was added", body = LeaderboardPost),
+        (status = 400, description = "The attachment isn't valid base64", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 413, description = "The attachment is larger than 256 KiB", body = String),
+        (status = 422, description = "The entry breaks a validation rule, such as a value that isn't finite, a name longer than 64 characters or a daily challenge that is closed", body = ValidationError),
+        (status = 500, description = "The entry couldn't be saved")
+    )
+)]
+#[instrument(
+    skip_all,
+    fields(game_id, player_slot = payload.player_slot, value_name = %payload.value_name)
+)]
 pub async fn set_leaderboard(
     State(state): State<ApiState>,
     State(game_state): State<GameStateShared>,
-    Json(payload): Json<LeaderboardPost>,
+    Json(mut payload): Json<LeaderboardPost>,
 ) -> impl IntoResponse {
     // TODO: Get game_id and user_id
-    println!("Setting Laaderboard data");
     // let game_id = "1";
-    let game_id = game_state.id.read().await.unwrap().to_string();
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
     drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
     let user_id = payload.player_slot.to_string();
 
+    let rules = match LeaderboardRules::load(&game_id, &state.database_path) {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!(error = %e, "Failed to load leaderboard rules");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let (value_num, tie_breakers) = match check_entry(&rules, &mut payload) {
+        Ok(resolved) => resolved,
+        Err(violation) => return validation_error_response(&violation),
+    };
+    let write = match leaderboard_write(user_id, &payload, value_num, tie_breakers) {
+        Ok(write) => write,
+        Err(response) => return response.into_response(),
+    };
+
     // Save entry to database
-    // TODO: Can I return the query response from this function?
-    db::insert_leaderboard_entry(
-        &user_id,
-        &game_id,
-        payload.value_name.as_str(),
+    if let Err(e) = db::insert_leaderboard_write(&game_id, &write, &state.database_path) {
+        error!(error = %e, "Failed to insert leaderboard entry");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    debug!(value_num, "Set leaderboard entry");
+
+    Json(payload).into_response()
+}
+
+/// Builds the database write of a leaderboard post, decoding its attachment
+fn leaderboard_write(
+    user_id: String,
+    payload: &LeaderboardPost,
+    value_num: f64,
+    tie_breakers: Vec<f64>,
+) -> Result<LeaderboardWrite, (StatusCode, String)> {
+    let attachment = match &payload.attachment {
+        Some(attachment) => {
+            let attachment = STANDARD.decode(attachment).map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("The attachment isn't valid base64: {}", e),
+                )
+            })?;
+            if attachment.len() > MAX_ATTACHMENT_BYTES {
+                return Err((
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!(
+                        "The attachment is {} bytes, but can be at most {}",
+                        attachment.len(),
+                        MAX_ATTACHMENT_BYTES
+                    ),
+                ));
+            }
+            Some(attachment)
+        }
+        None => None,
+    };
+    Ok(LeaderboardWrite {
+        user_id,
+        value_name: payload.value_name.clone(),
+        value_num,
+        details: payload.details.as_ref().map(Value::to_string),
+        attachment,
+        team_name: payload.team.as_ref().map(|team| team.name.clone()),
+        members: payload
+            .team
+            .as_ref()
+            .map(|team| {
+                team_slots(team, payload.player_slot)
+                    .iter()
+                    .map(i16::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        tie_breakers,
+        challenge_date: payload.challenge_date.clone(),
+    })
+}
+
+/// Checks a leaderboard post against the game's rules and checks its team. For a composite
+/// metric, `value_num` is set to its first component. Returns the value and tie-breakers of the
+/// entry.
+fn check_entry(
+    rules: &LeaderboardRules,
+    payload: &mut LeaderboardPost,
+) -> Result<(f64, Vec<f64>), LeaderboardViolation> {
+    let (value_num, tie_breakers) = rules.resolve(
+        &payload.value_name,
         payload.value_num,
-        &state.database_path,
-    )
-    .expect("Faied to enter leaderboard entry");
+        payload.values.as_ref(),
+    )?;
+    payload.value_num = Some(value_num);
+    rules.check(&payload.value_name, value_num)?;
+    if let Some(team) = &payload.team {
+        validate_team(team, payload.player_slot)?;
+    }
+    if let Some(challenge_date) = &payload.challenge_date {
+        check_challenge_date(challenge_date, today())?;
+    }
+    Ok((value_num, tie_breakers))
+}
 
-    Json(serde_json::json!({
-        "value_name":payload.value_name,
-        "value_num":payload.value_num,
-        "player_slot":payload.player_slot,
-    }))
+fn validation_error_response(violation: &LeaderboardViolation) -> Response {
+    debug!(
+        code = violation.code(),
+        "Leaderboard entry refused by validation"
+    );
+    let body = ValidationError {
+        code: violation.code().to_string(),
+        message: violation.to_string(),
+        field: violation.field().to_string(),
+    };
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response()
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct LeaderboardGetParams {
-    pub count: Option<i64>,
-    pub ascending: Option<bool>,
-    pub value_name: Option<String>,
-    pub offset: Option<i64>,
-    pub player_slot: Option<i16>,
+/// Handles leaderboard batch HTTP post requests for the axum webserver
+#[utoipa::path(
+    post,
+    path = "/api/v1/leaderboard/batch",
+    summary = "Add several leaderboard entries in one transaction",
+    tag = "leaderboard",
+    request_body = LeaderboardBatch,
+    responses(
+        (status = 200, description = "Whether each entry was stored, in the order of the entries. Entries breaking a validation rule are rejected", body = Vec<LeaderboardBatchResult>),
+        (status = 400, description = "There are more than 100 entries", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The entries couldn't be saved")
+    )
+)]
+#[instrument(skip_all, fields(game_id, entries = payload.entries.len()))]
+pub async fn set_leaderboard_batch(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    Json(mut payload): Json<LeaderboardBatch>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+
+    if payload.entries.len() > MAX_BATCH_OPERATIONS {
+        debug!("Batch has too many entries");
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("A batch can have at most {} entries", MAX_BATCH_OPERATIONS),
+        )
+            .into_response();
+    }
+
+    let rules = match LeaderboardRules::load(&game_id, &state.database_path) {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!(error = %e, "Failed to load leaderboard rules");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    // Invalid entries are rejected in place, only the valid ones reach the database
+    let mut writes = Vec::with_capacity(payload.entries.len());
+    let rejections: Vec<Option<String>> = payload
+        .entries
+        .iter_mut()
+        .map(|entry| {
+            let (value_num, tie_breakers) = match check_entry(&rules, entry) {
+                Ok(resolved) => resolved,
+                Err(violation) => return Some(violation.to_string()),
+            };
+            match leaderboard_write(
+                entry.player_slot.to_string(),
+                entry,
+                value_num,
+                tie_breakers,
+            ) {
+                Ok(write) => {
+                    writes.push(write);
+                    None
+                }
+                Err((_, reason)) => Some(reason),
+            }
+        })
+        .collect();
+    let mut inserted = match db::insert_leaderboard_entries(&game_id, &writes, &state.database_path)
+    {
+        Ok(inserts) => inserts.into_iter(),
+        Err(e) => {
+            error!(error = %e, "Failed to insert leaderboard batch");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let inserts = rejections.into_iter().map(|rejection| match rejection {
+        Some(reason) => LeaderboardInsert::Rejected(reason),
+        None => inserted
+            .next()
+            .expect("Every valid entry should have an insert result"),
+    });
+
+    let results: Vec<LeaderboardBatchResult> = payload
+        .entries
+        .into_iter()
+        .zip(inserts)
+        .map(|(entry, insert)| {
+            let (status, reason) = match insert {
+                LeaderboardInsert::Stored => (LeaderboardEntryStatus::Stored, None),
+                LeaderboardInsert::Duplicate => (LeaderboardEntryStatus::Deduplicated, None),
+                LeaderboardInsert::Rejected(reason) => {
+                    debug!(value_name = %entry.value_name, %reason, "Rejected leaderboard entry");
+                    (LeaderboardEntryStatus::Rejected, Some(reason))
+                }
+            };
+            LeaderboardBatchResult {
+                value_name: entry.value_name,
+                player_slot: entry.player_slot,
+                status,
+                reason,
+            }
+        })
+        .collect();
+    debug!("Set leaderboard batch");
+
+    Json(results).into_response()
 }
 
 /// Handles HTTP leaderboard get requests for the axum webserver
+#[utoipa::path(
+    get,
+    path = "/api/v1/leaderboard",
+    summary = "Get leaderboard entries, highest value first",
+    tag = "leaderboard",
+    params(LeaderboardGetParams),
+    responses(
+        (status = 200, description = "Leaderboard entries of the running game", body = Vec<LeaderboardEntry>),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 413, description = "More than 100 entries were requested")
+    )
+)]
+#[instrument(
+    skip_all,
+    fields(game_id, player_slot = ?params.player_slot, value_name = ?params.value_name)
+)]
 pub async fn get_leaderboard(
     State(state): State<ApiState>,
     State(game_state): State<GameStateShared>,
     params: Query<LeaderboardGetParams>,
 ) -> impl IntoResponse {
     // let game_id: String = String::from("1"); // Example for now
-    let game_id = game_state.id.read().await.unwrap().to_string();
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
     drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
     let user_id_s: Option<String>;
     // TODO: get associated player id and error check for invalid slots (negative or greater than the max)
     match params.player_slot {
@@ -111,6 +395,10 @@ pub async fn get_leaderboard(
     // TODO: add error http response to handle when count > 100
     if let Some(entry_count) = params.count {
         if entry_count > 100 {
+            debug!(
+                count = entry_count,
+                "Requested too many leaderboard entries"
+            );
             return StatusCode::PAYLOAD_TOO_LARGE.into_response();
         } else {
             count = Some(entry_count);
@@ -119,6 +407,13 @@ pub async fn get_leaderboard(
         count = Some(100);
     }
 
+    let rules = match LeaderboardRules::load(&game_id, &state.database_path) {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!(error = %e, "Failed to load leaderboard rules");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
     let leaderboard_entries = db::get_leaderboard(
         Some(game_id),
         user_id_s,
@@ -126,76 +421,1011 @@ pub async fn get_leaderboard(
         params.ascending,
         params.value_name.clone(),
         params.offset,
+        params.challenge_date.clone(),
         &state.database_path,
     )
     .await;
 
-    let mut json_response: Vec<serde_json::Value> = Vec::new();
+    debug!(
+        entries = leaderboard_entries.len(),
+        "Got leaderboard entries"
+    );
+    let entry_ids: Vec<i32> = leaderboard_entries
+        .iter()
+        .map(|entry| entry.row_id)
+        .collect();
+    let attachment_ids = match db::get_leaderboard_attachment_ids(&entry_ids, &state.database_path)
+    {
+        Ok(attachment_ids) => attachment_ids,
+        Err(e) => {
+            error!(error = %e, "Failed to get leaderboard attachments");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let mut members = match db::get_leaderboard_members(&entry_ids, &state.database_path) {
+        Ok(members) => members,
+        Err(e) => {
+            error!(error = %e, "Failed to get leaderboard team members");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let mut json_response: Vec<LeaderboardEntry> = Vec::new();
 
     for entry in leaderboard_entries {
-        json_response.push(serde_json::json!({
-            "value_name": entry.value_name,
-            "value_num": entry.value_num,
-            "player_slot": str::parse::<i16>(&entry.user_id).unwrap(),
-            "time_stamp": entry.time_stamp
-        }));
+        let metric_components = rules.components(&entry.value_name);
+        let components = (!metric_components.is_empty()).then(|| {
+            metric_components
+                .iter()
+                .zip(entry.components())
+                .map(|(component, value)| LeaderboardComponent {
+                    name: component.name.clone(),
+                    value,
+                })
+                .collect()
+        });
+        json_response.push(LeaderboardEntry {
+            id: entry.row_id,
+            value_name: entry.value_name,
+            value_num: entry.value_num,
+            player_slot: str::parse::<i16>(&entry.user_id).unwrap(),
+            time_stamp: entry.time_stamp,
+            details: entry.details.and_then(|details| from_str(&details).ok()),
+            has_attachment: attachment_ids.contains(&entry.row_id),
+            team: entry.team_name.map(|name| LeaderboardTeam {
+                name,
+                player_slots: members
+                    .remove(&entry.row_id)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|user_id| user_id.parse().ok())
+                    .collect(),
+            }),
+            components,
+            challenge_date: entry.challenge_date,
+        });
     }
 
     Json(json_response).into_response()
 }
 
+/// Handles HTTP leaderboard statistics get requests for the axum webserver
+#[utoipa::path(
+    get,
+    path = "/api/v1/leaderboard/stats",
+    summary = "Get statistics of every entry of a leaderboard metric",
+    tag = "leaderboard",
+    params(LeaderboardStatsParams),
+    responses(
+        (status = 200, description = "Statistics of the metric in the running game", body = LeaderboardStats),
+        (status = 400, description = "The percentiles or number of buckets are invalid", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The leaderboard couldn't be read")
+    )
+)]
+#[instrument(skip_all, fields(game_id, value_name = %params.value_name))]
+pub async fn get_leaderboard_stats(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    params: Query<LeaderboardStatsParams>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+
+    let (percentiles, buckets) = match (
+        parse_percentiles(params.percentiles.as_deref()),
+        check_buckets(params.buckets),
+    ) {
+        (Ok(percentiles), Ok(buckets)) => (percentiles, buckets),
+        (Err(message), _) | (_, Err(message)) => {
+            debug!(%message, "Invalid leaderboard stats request");
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+    };
+    let values = match db::get_leaderboard_values(
+        &game_id,
+        &params.value_name,
+        params.challenge_date.as_deref(),
+        &state.database_path,
+    ) {
+        Ok(values) => values,
+        Err(e) => {
+            error!(error = %e, "Failed to get leaderboard values");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    debug!(entries = values.len(), "Got leaderboard values");
+    let rules = match LeaderboardRules::load(&game_id, &state.database_path) {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!(error = %e, "Failed to load leaderboard rules");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    // a composite metric's values are those of its first component, which sets their direction
+    let ascending = match rules.components(&params.value_name).first() {
+        Some(component) => component.ascending,
+        None => params.ascending.unwrap_or(false),
+    };
+
+    Json(leaderboard_stats(
+        &values,
+        &percentiles,
+        buckets,
+        params.value,
+        ascending,
+    ))
+    .into_response()
+}
+
+/// Handles daily challenge HTTP get requests for the axum webserver
+#[utoipa::path(
+    get,
+    path = "/api/v1/daily-challenge",
+    summary = "Get today's daily challenge, with the seed every player generates its level from",
+    tag = "leaderboard",
+    responses(
+        (status = 200, description = "Today's daily challenge of the running game", body = DailyChallenge),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The console secret couldn't be read")
+    )
+)]
+#[instrument(skip_all, fields(game_id))]
+pub async fn get_daily_challenge(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+
+    let secret = match db::get_console_secret(SECRET_NAME, &state.database_path) {
+        Ok(secret) => secret,
+        Err(e) => {
+            error!(error = %e, "Failed to get the daily challenge secret");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let date = today();
+    debug!(%date, "Got daily challenge");
+
+    Json(DailyChallenge {
+        date: format_date(date),
+        seed: challenge_seed(&secret, &game_id, date),
+    })
+    .into_response()
+}
+
+/// Handles HTTP leaderboard attachment get requests for the axum webserver
+#[utoipa::path(
+    get,
+    path = "/api/v1/leaderboard/{id}/attachment",
+    summary = "Get the attachment of a leaderboard entry, such as a replay",
+    tag = "leaderboard",
+    params(
+        ("id" = i32, Path, description = "Id of the leaderboard entry")
+    ),
+    responses(
+        (status = 200, description = "The attachment as it was posted", content_type = "application/octet-stream", body = Vec<u8>),
+        (status = 404, description = "The running game has no entry with this id, or it has no attachment"),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The attachment couldn't be read")
+    )
+)]
+#[instrument(skip_all, fields(game_id, id))]
+pub async fn get_leaderboard_attachment(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    Path(id): Path<i32>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+    Span::current().record("id", id);
+
+    match db::get_leaderboard_attachment(&game_id, id, &state.database_path) {
+        Ok(Some(attachment)) => {
+            debug!(bytes = attachment.len(), "Got leaderboard attachment");
+            ([(CONTENT_TYPE, "application/octet-stream")], attachment).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!(error = %e, "Failed to get leaderboard attachment");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 // Handles save-data HTTP post requests for the axum webserver
+#[utoipa::path(
+    post,
+    path = "/api/v1/save-data",
+    summary = "Create or replace a save file for a player",
+    tag = "save-data",
+    request_body = SaveDataPost,
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only write the save file if its `ETag` is one of these, or if it exists at all for `*`")
+    ),
+    responses(
+        (status = 200, description = "The save file was created or replaced", body = SaveDataPost,
+            headers(("ETag" = String, description = "Revision of the save file that was written"))),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 412, description = "The save file doesn't match `If-Match`, so it was written since it was read", body = String,
+            headers(("ETag" = String, description = "Current revision of the save file, if it exists"))),
+        (status = 413, description = "The save file is larger than the game's `max_save_bytes`", body = QuotaError),
+        (status = 507, description = "The player already has `max_saves_per_player` save files, or the game's save files would take up more than `max_bytes`", body = QuotaError)
+    )
+)]
+#[instrument(
+    skip_all,
+    fields(game_id, player_slot = payload.player_slot, file_name = %payload.file_name)
+)]
 pub async fn set_save_data(
     State(state): State<ApiState>,
     State(game_state): State<GameStateShared>,
+    headers: HeaderMap,
     Json(payload): Json<SaveDataPost>,
 ) -> impl IntoResponse {
     // let game_id = "0";x
-    let game_id = game_state.id.read().await.unwrap().to_string();
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
     drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
     let user_id = payload.player_slot.to_string();
 
     // Save entry to database;
     // TODO: more elegant error handling for converting json data to vec of bytes
-    db::set_save(
+    let save = match db::set_save_if(
         &user_id,
         &game_id,
         payload.file_name.as_str(),
         &serde_json::to_vec(&payload.data).unwrap(),
+        if_match(&headers).as_ref(),
         &state.database_path,
     )
-    .await;
+    .await
+    {
+        Ok(save) => save,
+        Err(e) => return save_error_response(e, "Failed to set save data"),
+    };
+    debug!(revision = save.revision, "Set save data");
+
+    ([(ETAG, etag(save.revision))], Json(payload)).into_response()
+}
+
+/// `ETag` header of a save at the given revision
+pub(crate) fn etag(revision: i32) -> HeaderValue {
+    HeaderValue::from_str(&quackbox_client::etag(revision)).expect("ETag is a valid header")
+}
+
+/// Reads the `If-Match` header of a save write. Our tags are always strong, so weak tags and
+/// tags that aren't revisions can never match. Bare revisions without quotes are accepted too.
+pub(crate) fn if_match(headers: &HeaderMap) -> Option<IfRevision> {
+    let mut revisions = Vec::new();
+    for value in headers.get_all(IF_MATCH) {
+        let value = value.to_str().unwrap_or_default();
+        if value.trim() == "*" {
+            return Some(IfRevision::Any);
+        }
+        revisions.extend(value.split(',').filter_map(|tag| {
+            let tag = tag.trim();
+            let tag = tag
+                .strip_prefix('"')
+                .and_then(|tag| tag.strip_suffix('"'))
+                .unwrap_or(tag);
+            tag.parse::<i32>().ok()
+        }));
+    }
+    if headers.contains_key(IF_MATCH) {
+        Some(IfRevision::OneOf(revisions))
+    } else {
+        None
+    }
+}
 
-    Json(serde_json::json!({
-        "file_name": payload.file_name,
-        "data": payload.data,
-        "player_slot": payload.player_slot,
-    }))
+/// Handles save-data HTTP patch requests for the axum webserver
+#[utoipa::path(
+    patch,
+    path = "/api/v1/save-data",
+    summary = "Update part of a JSON save file with a JSON Patch or JSON Merge Patch",
+    tag = "save-data",
+    params(
+        SaveDataPatchParams,
+        ("If-Match" = Option<String>, Header, description = "Only patch the save file if its `ETag` is one of these")
+    ),
+    request_body(
+        description = "An RFC 6902 JSON Patch or an RFC 7396 JSON Merge Patch, told apart by the content type",
+        content(
+            (Vec<serde_json::Value> = "application/json-patch+json"),
+            (serde_json::Value = "application/merge-patch+json")
+        )
+    ),
+    responses(
+        (status = 200, description = "The patched save file", body = SaveDataEntry,
+            headers(("ETag" = String, description = "Revision of the patched save file"))),
+        (status = 400, description = "The body isn't a valid patch", body = String),
+        (status = 404, description = "The save file doesn't exist", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 412, description = "The save file doesn't match `If-Match`", body = String),
+        (status = 413, description = "The patched save file is larger than the game's `max_save_bytes`", body = QuotaError),
+        (status = 415, description = "The content type isn't one of the patch content types", body = String),
+        (status = 422, description = "The save file isn't JSON, or an operation of the patch failed", body = String),
+        (status = 507, description = "The game's save files would take up more than `max_bytes`", body = QuotaError)
+    )
+)]
+#[instrument(
+    skip_all,
+    fields(game_id, player_slot = params.player_slot, file_name = %params.file_name)
+)]
+pub async fn patch_save_data(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    params: Query<SaveDataPatchParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+
+    let save_patch = match parse_save_patch(&headers, &body) {
+        Ok(save_patch) => save_patch,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let user_id = params.player_slot.to_string();
+    let save = match db::patch_save(
+        &user_id,
+        &game_id,
+        &params.file_name,
+        &save_patch,
+        if_match(&headers).as_ref(),
+        &state.database_path,
+    ) {
+        Ok(save) => save,
+        Err(e) => return save_error_response(e, "Failed to patch save data"),
+    };
+    debug!(revision = save.revision, "Patched save data");
+
+    let entry = SaveDataEntry {
+        data: serde_json::from_slice::<Value>(&save.data).expect("Patched save should be JSON"),
+        file_name: save.file_name,
+        player_slot: params.player_slot,
+        time_stamp: save.time_stamp,
+        revision: save.revision,
+    };
+    ([(ETAG, etag(save.revision))], Json(entry)).into_response()
 }
 
-#[derive(Deserialize, Serialize)]
-pub struct SaveDataGetParams {
-    pub file_name: Option<String>,
-    pub regex: Option<String>,
-    pub player_slot: Option<i16>,
+/// Reads a patch body, picking the kind of patch from the content type
+fn parse_save_patch(headers: &HeaderMap, body: &[u8]) -> Result<SavePatch, (StatusCode, String)> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let save_patch = match content_type.as_str() {
+        JSON_PATCH_CONTENT_TYPE => serde_json::from_slice(body).map(SavePatch::Json),
+        MERGE_PATCH_CONTENT_TYPE => serde_json::from_slice(body).map(SavePatch::Merge),
+        _ => {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!(
+                    "Patches have to be sent as {} or {}",
+                    JSON_PATCH_CONTENT_TYPE, MERGE_PATCH_CONTENT_TYPE
+                ),
+            ))
+        }
+    };
+    save_patch.map_err(|e| {
+        debug!(error = %e, "Invalid patch");
+        (StatusCode::BAD_REQUEST, format!("Invalid patch: {}", e))
+    })
+}
+
+/// Status code of a save refused because of the given quota. A save that is too large on its
+/// own is 413, and running out of room is 507.
+pub(crate) fn quota_status(exceeded: &QuotaExceeded) -> StatusCode {
+    match exceeded.quota {
+        Quota::SaveBytes => StatusCode::PAYLOAD_TOO_LARGE,
+        Quota::SavesPerUser | Quota::GameBytes => StatusCode::INSUFFICIENT_STORAGE,
+    }
+}
+
+/// Handles save-data batch HTTP post requests for the axum webserver
+#[utoipa::path(
+    post,
+    path = "/api/v1/save-data/batch",
+    summary = "Write, patch and delete several save files in one transaction",
+    tag = "save-data",
+    request_body = SaveDataBatch,
+    responses(
+        (status = 200, description = "Every operation was applied. Results are in the order of the operations", body = Vec<SaveDataBatchResult>),
+        (status = 400, description = "There are more than 100 operations", body = String),
+        (status = 404, description = "A merge patched save file doesn't exist, and no operation was applied", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 412, description = "A save file isn't at its operation's `if_revision`, and no operation was applied", body = String),
+        (status = 413, description = "A save file is larger than the game's `max_save_bytes`, and no operation was applied", body = QuotaError),
+        (status = 422, description = "A merge patched save file isn't JSON, and no operation was applied", body = String),
+        (status = 507, description = "The save files would go over the game's storage quotas, and no operation was applied", body = QuotaError)
+    )
+)]
+#[instrument(skip_all, fields(game_id, operations = payload.operations.len()))]
+pub async fn set_save_data_batch(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    Json(payload): Json<SaveDataBatch>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+
+    if payload.operations.len() > MAX_BATCH_OPERATIONS {
+        debug!("Batch has too many operations");
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "A batch can have at most {} operations",
+                MAX_BATCH_OPERATIONS
+            ),
+        )
+            .into_response();
+    }
+
+    let mut results = Vec::with_capacity(payload.operations.len());
+    let mut writes = Vec::with_capacity(payload.operations.len());
+    for operation in payload.operations {
+        let (file_name, player_slot, action, if_revision) = match operation {
+            SaveDataOperation::Set {
+                file_name,
+                player_slot,
+                data,
+                if_revision,
+            } => (
+                file_name,
+                player_slot,
+                SaveAction::Set(serde_json::to_vec(&data).unwrap()),
+                if_revision,
+            ),
+            SaveDataOperation::Merge {
+                file_name,
+                player_slot,
+                patch,
+                if_revision,
+            } => (
+                file_name,
+                player_slot,
+                SaveAction::Patch(SavePatch::Merge(patch)),
+                if_revision,
+            ),
+            SaveDataOperation::Delete {
+                file_name,
+                player_slot,
+                if_revision,
+            } => (file_name, player_slot, SaveAction::Delete, if_revision),
+        };
+        results.push(SaveDataBatchResult {
+            file_name: file_name.clone(),
+            player_slot,
+            revision: None,
+        });
+        writes.push(SaveWrite {
+            user_id: player_slot.to_string(),
+            file_name,
+            action,
+            if_revision: if_revision.map(|revision| IfRevision::OneOf(vec![revision])),
+        });
+    }
+
+    let revisions = match db::write_saves(&game_id, &writes, &state.database_path) {
+        Ok(revisions) => revisions,
+        Err(e) => return save_error_response(e, "Failed to write save data batch"),
+    };
+    for (result, revision) in results.iter_mut().zip(revisions) {
+        result.revision = revision;
+    }
+    debug!("Wrote save data batch");
+
+    Json(results).into_response()
+}
+
+/// Response to a save write the database refused. The message includes the context of the
+/// error, such as which operation of a batch failed.
+fn save_error_response(e: anyhow::Error, message: &str) -> Response {
+    if let Some(mismatch) = e.downcast_ref::<RevisionMismatch>() {
+        debug!(current = ?mismatch.current, "Save refused by If-Match");
+        let mut response = (StatusCode::PRECONDITION_FAILED, format!("{:#}", e)).into_response();
+        if let Some(current) = mismatch.current {
+            response.headers_mut().insert(ETAG, etag(current));
+        }
+        return response;
+    }
+    if let Some(patch_error) = e.downcast_ref::<SavePatchError>() {
+        debug!(error = %patch_error, "Patch refused");
+        let status = match patch_error {
+            SavePatchError::NotFound => StatusCode::NOT_FOUND,
+            SavePatchError::NotJson | SavePatchError::Failed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        return (status, format!("{:#}", e)).into_response();
+    }
+    match e.downcast_ref::<QuotaExceeded>() {
+        Some(exceeded) => quota_exceeded_response(exceeded),
+        None => {
+            error!(error = %e, "{}", message);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn quota_exceeded_response(exceeded: &QuotaExceeded) -> Response {
+    debug!(
+        code = exceeded.code(),
+        limit = exceeded.limit,
+        requested = exceeded.requested,
+        "Save refused by quota"
+    );
+    let body = QuotaError {
+        code: exceeded.code().to_string(),
+        message: exceeded.to_string(),
+        limit: exceeded.limit,
+        requested: exceeded.requested,
+    };
+    (quota_status(exceeded), Json(body)).into_response()
+}
+
+/// Handles storage HTTP get requests for the axum webserver
+#[utoipa::path(
+    get,
+    path = "/api/v1/storage",
+    summary = "Get how much of its storage quotas the game uses",
+    tag = "save-data",
+    params(StorageGetParams),
+    responses(
+        (status = 200, description = "Storage used by the running game and its quotas", body = StorageUsage),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The storage usage couldn't be read")
+    )
+)]
+#[instrument(skip_all, fields(game_id, player_slot = ?params.player_slot))]
+pub async fn get_storage(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    params: Query<StorageGetParams>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+
+    let user_id = params.player_slot.map(|slot| slot.to_string());
+    let (quotas, save_count, used_bytes) =
+        match db::get_storage_usage(&game_id, user_id.as_deref(), &state.database_path) {
+            Ok(usage) => usage,
+            Err(e) => {
+                error!(error = %e, "Failed to get storage usage");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+    debug!(used_bytes, save_count, "Got storage usage");
+
+    Json(StorageUsage {
+        used_bytes,
+        max_bytes: quotas.max_game_bytes,
+        save_count,
+        max_saves_per_player: quotas.max_saves_per_user,
+        max_save_bytes: quotas.max_save_bytes,
+    })
+    .into_response()
+}
+
+/// Handles global HTTP get requests for the axum webserver
+#[utoipa::path(
+    get,
+    path = "/api/v1/globals/{key}",
+    summary = "Get a global shared by every player of the game",
+    tag = "globals",
+    params(("key" = String, Path, description = "Name of the global")),
+    responses(
+        (status = 200, description = "The global, with its revision as the `ETag`", body = GlobalEntry),
+        (status = 400, description = "The key is invalid", body = String),
+        (status = 404, description = "The global was never set", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The global couldn't be read")
+    )
+)]
+#[instrument(skip_all, fields(game_id, %key))]
+pub async fn get_global(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    Path(key): Path<String>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+    if let Err(message) = check_global_key(&key) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    match db::get_global(&game_id, &key, &state.database_path) {
+        Ok(Some(global)) => global_response(global),
+        Ok(None) => (StatusCode::NOT_FOUND, "Global not found").into_response(),
+        Err(e) => {
+            error!(error = %e, "Failed to get global");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handles global HTTP post requests for the axum webserver. Sessions updating the same global
+/// can use `If-Match` to compare-and-swap, or `If-None-Match: *` to only set it once.
+#[utoipa::path(
+    post,
+    path = "/api/v1/globals/{key}",
+    summary = "Set a global shared by every player of the game",
+    tag = "globals",
+    request_body = GlobalPost,
+    params(
+        ("key" = String, Path, description = "Name of the global"),
+        ("If-Match" = Option<String>, Header, description = "Only set the global if its `ETag` is one of these"),
+        ("If-None-Match" = Option<String>, Header, description = "`*` to only set the global if it was never set")
+    ),
+    responses(
+        (status = 200, description = "The global was set", body = GlobalEntry),
+        (status = 400, description = "The key is invalid", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 412, description = "The global doesn't match `If-Match` or `If-None-Match`. The `ETag` header has its current revision.", body = String),
+        (status = 413, description = "The value is larger than 64 KiB as JSON", body = String),
+        (status = 500, description = "The global couldn't be written")
+    )
+)]
+#[instrument(skip_all, fields(game_id, %key))]
+pub async fn set_global(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<GlobalPost>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+    if let Err(message) = check_global_key(&key) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    let value = payload.value.to_string();
+    if value.len() > MAX_GLOBAL_BYTES {
+        debug!(bytes = value.len(), "Global too large");
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("A global can be at most {} bytes", MAX_GLOBAL_BYTES),
+        )
+            .into_response();
+    }
+
+    let if_revision = match headers.get(IF_NONE_MATCH) {
+        Some(value) if value.to_str().is_ok_and(|value| value.trim() == "*") => {
+            Some(IfRevision::Absent)
+        }
+        _ => if_match(&headers),
+    };
+    match db::set_global(
+        &game_id,
+        &key,
+        &value,
+        if_revision.as_ref(),
+        &state.database_path,
+    ) {
+        Ok(global) => {
+            debug!(revision = global.revision, "Set global");
+            global_response(global)
+        }
+        Err(e) => global_error_response(e, "Failed to set global"),
+    }
+}
+
+/// Handles global increment HTTP post requests for the axum webserver
+#[utoipa::path(
+    post,
+    path = "/api/v1/globals/{key}/increment",
+    summary = "Add to a global holding a number, starting from 0 if it was never set",
+    tag = "globals",
+    request_body = GlobalIncrement,
+    params(("key" = String, Path, description = "Name of the global")),
+    responses(
+        (status = 200, description = "The global after adding to it", body = GlobalEntry),
+        (status = 400, description = "The key is invalid", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 422, description = "The global isn't a number or would become too large", body = String),
+        (status = 500, description = "The global couldn't be written")
+    )
+)]
+#[instrument(skip_all, fields(game_id, %key, by = payload.by))]
+pub async fn increment_global(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    Path(key): Path<String>,
+    Json(payload): Json<GlobalIncrement>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+    if let Err(message) = check_global_key(&key) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+
+    match db::increment_global(&game_id, &key, payload.by, &state.database_path) {
+        Ok(global) => {
+            debug!(revision = global.revision, "Incremented global");
+            global_response(global)
+        }
+        Err(e) => global_error_response(e, "Failed to increment global"),
+    }
+}
+
+/// Checks that a global key is made of letters, digits, `_`, `-` and `.`, so it can be used in a
+/// url as is
+fn check_global_key(key: &str) -> Result<(), String> {
+    let valid = key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if key.is_empty() || key.len() > MAX_GLOBAL_KEY_LEN || !valid {
+        debug!("Invalid global key");
+        return Err(format!(
+            "A global key has to be 1 to {} letters, digits, `_`, `-` or `.`",
+            MAX_GLOBAL_KEY_LEN
+        ));
+    }
+    Ok(())
+}
+
+/// Response with a global and its revision as the `ETag`
+fn global_response(global: GameGlobal) -> Response {
+    let value = serde_json::from_str(&global.value).expect("Globals are stored as JSON");
+    (
+        [(ETAG, etag(global.revision))],
+        Json(GlobalEntry {
+            key: global.name,
+            value,
+            revision: global.revision,
+            time_stamp: global.time_stamp,
+        }),
+    )
+        .into_response()
+}
+
+/// Response to a global write the database refused
+fn global_error_response(e: anyhow::Error, message: &str) -> Response {
+    if let Some(mismatch) = e.downcast_ref::<RevisionMismatch>() {
+        debug!(current = ?mismatch.current, "Global write refused by precondition");
+        return match mismatch.current {
+            Some(current) => (
+                StatusCode::PRECONDITION_FAILED,
+                [(ETAG, etag(current))],
+                format!("Global is at revision {}", current),
+            )
+                .into_response(),
+            None => (StatusCode::PRECONDITION_FAILED, "Global doesn't exist").into_response(),
+        };
+    }
+    match e.downcast_ref::<GlobalIncrementError>() {
+        Some(increment_error) => {
+            debug!(error = %increment_error, "Increment refused");
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                increment_error.to_string(),
+            )
+                .into_response()
+        }
+        None => {
+            error!(error = %e, "{}", message);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handles stat increment HTTP post requests for the axum webserver
+#[utoipa::path(
+    post,
+    path = "/api/v1/stats/increment",
+    summary = "Add to a lifetime stat of a player, starting from 0 if it was never set",
+    tag = "stats",
+    request_body = StatIncrement,
+    responses(
+        (status = 200, description = "The stat after adding to it", body = StatEntry),
+        (status = 400, description = "The name or amount is invalid", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The stat couldn't be written")
+    )
+)]
+#[instrument(
+    skip_all,
+    fields(game_id, player_slot = payload.player_slot, name = %payload.name, by = payload.by)
+)]
+pub async fn increment_stat(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    Json(payload): Json<StatIncrement>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+    if let Err(message) = check_stat_name(&payload.name) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    // unsigned, as i64::MIN has no absolute value
+    if payload.by.unsigned_abs() > MAX_STAT_DELTA as u64 {
+        debug!("Stat increment too large");
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "A stat can be changed by at most {} at once",
+                MAX_STAT_DELTA
+            ),
+        )
+            .into_response();
+    }
+
+    let user_id = payload.player_slot.to_string();
+    match db::increment_player_stat(
+        &user_id,
+        &game_id,
+        &payload.name,
+        payload.by,
+        &state.database_path,
+    ) {
+        Ok(stat) => {
+            debug!(value = stat.value, "Incremented stat");
+            Json(stat_entry(stat)).into_response()
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to increment stat");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Handles stats HTTP get requests for the axum webserver
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats",
+    summary = "Get lifetime stats of the players",
+    tag = "stats",
+    params(StatsGetParams),
+    responses(
+        (status = 200, description = "Stats of the running game, by player slot and then by name", body = Vec<StatEntry>),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The stats couldn't be read")
+    )
+)]
+#[instrument(skip_all, fields(game_id, player_slot = ?params.player_slot, name = ?params.name))]
+pub async fn get_stats(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    params: Query<StatsGetParams>,
+) -> impl IntoResponse {
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
+    drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
+
+    let user_id = params.player_slot.map(|slot| slot.to_string());
+    match db::get_player_stats(
+        &game_id,
+        user_id.as_deref(),
+        params.name.as_deref(),
+        &state.database_path,
+    ) {
+        Ok(stats) => {
+            debug!(stats = stats.len(), "Got stats");
+            Json(stats.into_iter().map(stat_entry).collect::<Vec<_>>()).into_response()
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to get stats");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Checks that a stat name follows the same rules as a leaderboard `value_name`
+fn check_stat_name(name: &str) -> Result<(), String> {
+    validate_value_name(name).map_err(|violation| {
+        debug!(%violation, "Invalid stat name");
+        format!(
+            "A stat name has to be 1 to {} letters, digits, spaces, `_`, `-` or `.`",
+            MAX_VALUE_NAME_LEN
+        )
+    })
+}
+
+fn stat_entry(stat: PlayerStat) -> StatEntry {
+    StatEntry {
+        player_slot: str::parse::<i16>(&stat.user_id).unwrap(),
+        name: stat.name,
+        value: stat.value,
+        time_stamp: stat.time_stamp,
+    }
 }
 
 /// Handles save-data HTTP get requests for the axum webserver.
 /// Can either get a list of save files for current user or
 /// get a specific file by user and name.
+#[utoipa::path(
+    get,
+    path = "/api/v1/save-data",
+    summary = "Get save files by name or by a regular expression",
+    tag = "save-data",
+    params(SaveDataGetParams),
+    responses(
+        (status = 200, description = "Save files of the running game", body = Vec<SaveDataEntry>,
+            headers(("ETag" = String, description = "Revision of the save file when getting one by `file_name`"))),
+        (status = 400, description = "Both `file_name` and `regex` were given, or `regex` is invalid", body = String),
+        (status = 409, description = "No game is currently running", body = String),
+        (status = 500, description = "The save files couldn't be read")
+    )
+)]
+#[instrument(
+    skip_all,
+    fields(
+        game_id,
+        player_slot = ?params.player_slot,
+        file_name = ?params.file_name,
+        regex = ?params.regex
+    )
+)]
 pub async fn get_save_data(
     State(state): State<ApiState>,
     State(game_state): State<GameStateShared>,
     params: Query<SaveDataGetParams>,
 ) -> impl IntoResponse {
-    println!("Getting save data!");
-    let game_id = game_state.id.read().await.unwrap().to_string();
+    let game_id = match current_game_id(&game_state).await {
+        Ok(game_id) => game_id,
+        Err(response) => return response,
+    };
     drop(game_state);
+    Span::current().record("game_id", game_id.as_str());
 
     let user_id_s: Option<String> = match params.player_slot {
         Some(slot) => Some(slot.to_string()),
         None => None,
     };
+    if let Err(e) = db::validate_save_data_params(&params.file_name, &params.regex) {
+        debug!(error = %e, "Invalid save data request");
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
 
     let save_data_entries = db::get_save_data(
         &Some(game_id),
@@ -208,20 +1438,29 @@ pub async fn get_save_data(
 
     match save_data_entries {
         Ok(save_data) => {
-            let mut json_response = Vec::new();
+            debug!(entries = save_data.len(), "Got save data");
+            let mut json_response: Vec<SaveDataEntry> = Vec::new();
 
-            // TODO: add time_stamp
             // TODO: fix player slot to not use hardcoded values
             for entry in save_data {
-                json_response.push(serde_json::json!({
-                            "data":serde_json::from_slice::<Value>(&entry.data).expect("Failed to deserialize BSON data"),
-                            "file_name": entry.file_name,
-                            "player_slot": str::parse::<i16>(&entry.user_id).unwrap(),
-                            "time_stamp": entry.time_stamp
-                        }));
+                json_response.push(SaveDataEntry {
+                    data: serde_json::from_slice::<Value>(&entry.data)
+                        .expect("Failed to deserialize BSON data"),
+                    file_name: entry.file_name,
+                    player_slot: str::parse::<i16>(&entry.user_id).unwrap(),
+                    time_stamp: entry.time_stamp,
+                    revision: entry.revision,
+                });
+            }
+            // a single save file can be written back with If-Match
+            if let ([entry], Some(_)) = (json_response.as_slice(), &params.file_name) {
+                return ([(ETAG, etag(entry.revision))], Json(json_response)).into_response();
             }
             return Json(json_response).into_response();
         }
-        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) => {
+            error!(error = %e, "Failed to get save data");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
     }
 }
@@ -1,19 +1,35 @@
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 use handlers::{
-    get_leaderboard, get_save_data, set_leaderboard, set_save_data, ApiState, AppState,
-    GameStateShared,
+    get_daily_challenge, get_global, get_leaderboard, get_leaderboard_attachment,
+    get_leaderboard_stats, get_save_data, get_stats, get_storage, increment_global, increment_stat,
+    patch_save_data, set_global, set_leaderboard, set_leaderboard_batch, set_save_data,
+    set_save_data_batch, ApiState, AppState, GameStateShared,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::Notify;
+use tracing::{debug, info};
 
-const VERSION: u8 = 1;
+/// The first version of the API, which shipped games use. Its routes and response shapes are
+/// frozen, so changes that would break games go in a newer version instead.
+pub const V1: u8 = 1;
+/// Wraps responses in envelopes and identifies players by user id, see [`v2`]
+pub const V2: u8 = 2;
 
+pub mod daily;
 pub mod handlers;
+pub mod openapi;
+pub mod stats;
+pub mod v2;
+pub mod validation;
 
 /// Listens to and updates the current shared game state
 /// by synchronizing the current game ID with the latest from a watch channel
 async fn handle_game_state_updates(game_state: GameStateShared) {
-    println!("Started listener to watch in the router");
+    debug!("Started listener to watch in the router");
     let current_game = game_state.id.clone();
     let mut watch = game_state.channel.clone();
     let mut i = 0;
@@ -24,10 +40,9 @@ async fn handle_game_state_updates(game_state: GameStateShared) {
     loop {
         let mut game_id = current_game.write().await;
         *game_id = *watch.borrow_and_update();
-        println!("set game_id {:?}: {:?}", i, game_id);
+        debug!(update = i, game_id = ?*game_id, "Set current game");
         drop(game_id);
         game_state.notifier.notify_one();
-        println!("Sent notification");
         if watch.changed().await.is_err() {
             // the watch channel transmitter should never
             // be destroyed before the application closes
@@ -42,7 +57,8 @@ async fn handle_game_state_updates(game_state: GameStateShared) {
     }
 }
 
-/// Creates an Axum router with leaderboard and save-data post and get handlers.
+/// Creates an Axum router with leaderboard and save-data post and get handlers for every
+/// API version, and the OpenAPI document describing them at `/api/v1/openapi.json`.
 ///
 /// # Arguments
 ///
@@ -79,7 +95,6 @@ async fn handle_game_state_updates(game_state: GameStateShared) {
 /// }
 /// ```
 pub async fn create_router(db_path: &str, game_state: GameStateShared) -> Router {
-    let route_prefix: String = format!("/api/v{}", VERSION.to_string());
     let api_state = ApiState {
         database_path: db_path.to_owned(),
     };
@@ -87,35 +102,62 @@ pub async fn create_router(db_path: &str, game_state: GameStateShared) -> Router
 
     tokio::spawn(handle_game_state_updates(game_state.clone()));
     game_state.notifier.notified().await; // wait for the first notification
-    println!("Received first notification");
+    debug!("Received first notification");
 
     let app_state = AppState {
         api_state,
         game_state,
     };
 
+    // every version is served side by side so games keep working when a new version is added
     Router::new()
+        .nest(&version_prefix(V1), v1_routes())
+        .nest(&version_prefix(V2), v2::routes())
+        .with_state(app_state)
+}
+
+/// Returns the path every route of the given API version is nested under, such as `/api/v1`
+pub fn version_prefix(version: u8) -> String {
+    format!("/api/v{}", version)
+}
+
+/// Routes of the v1 API, relative to `/api/v1`
+fn v1_routes() -> Router<AppState> {
+    Router::new()
+        .route("/leaderboard", post(set_leaderboard).get(get_leaderboard))
+        .route("/leaderboard/batch", post(set_leaderboard_batch))
+        .route("/leaderboard/stats", get(get_leaderboard_stats))
+        .route("/daily-challenge", get(get_daily_challenge))
         .route(
-            &format!("{}/leaderboard", route_prefix),
-            post(set_leaderboard).get(get_leaderboard),
+            "/leaderboard/{id}/attachment",
+            get(get_leaderboard_attachment),
         )
-        // .with_state(app_state.clone()) // TODO: wrap the state in an ARC to avoid cloning???
         .route(
-            &format!("{}/save-data", route_prefix),
-            post(set_save_data).get(get_save_data),
+            "/save-data",
+            post(set_save_data)
+                .get(get_save_data)
+                .patch(patch_save_data),
         )
-        .with_state(app_state)
+        .route("/save-data/batch", post(set_save_data_batch))
+        .route("/storage", get(get_storage))
+        .route("/globals/{key}", get(get_global).post(set_global))
+        .route("/globals/{key}/increment", post(increment_global))
+        .route("/stats", get(get_stats))
+        .route("/stats/increment", post(increment_stat))
+        .route("/openapi.json", get(openapi::openapi_json))
 }
 
 /// This function should be called in tauri builder to setup the http API for game
 /// developers to read and write game data.
-pub async fn setup_game_dev_api(db_path: String, game_state: GameStateShared) {
+///
+/// The address comes from the `dev_api` section of the console settings.
+pub async fn setup_game_dev_api(db_path: String, game_state: GameStateShared, address: SocketAddr) {
     let app = create_router(&db_path, game_state).await;
 
-    println!("Server started successfully!!!");
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:6174")
+    let listener = tokio::net::TcpListener::bind(address)
         .await
-        .unwrap(); // TODO make the port configurable
+        .unwrap_or_else(|e| panic!("Failed to bind the game dev API to {}: {}", address, e));
+    info!(%address, "Game dev API started");
     axum::serve(listener, app).await.unwrap();
 }
 
@@ -0,0 +1,186 @@
+use super::{
+    handlers::{
+        self, DailyChallenge, GlobalEntry, GlobalIncrement, GlobalPost, HistogramBucket,
+        LeaderboardBatch, LeaderboardBatchResult, LeaderboardComponent, LeaderboardEntry,
+        LeaderboardEntryStatus, LeaderboardGetParams, LeaderboardPercentile, LeaderboardPost,
+        LeaderboardStats, LeaderboardTeam, QuotaError, SaveDataBatch, SaveDataBatchResult,
+        SaveDataEntry, SaveDataGetParams, SaveDataOperation, SaveDataPatchParams, SaveDataPost,
+        StatEntry, StatIncrement, StorageUsage, ValidationError,
+    },
+    v2,
+};
+use axum::Json;
+use utoipa::OpenApi;
+
+/// OpenAPI 3 document of the game dev API, generated from the handlers and their types.
+///
+/// A copy is checked in at `src-tauri/game-dev-api.openapi.json` for game developers. Run
+/// `UPDATE_OPENAPI=1 cargo test openapi` to regenerate it after changing the API.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "QuackBox Game Dev API",
+        version = "1",
+        license(name = "MIT"),
+        description = "Leaderboard and save data storage for games running on the QuackBox. \
+            Every request applies to the game the launcher is currently running."
+    ),
+    paths(
+        handlers::set_leaderboard,
+        handlers::get_leaderboard,
+        handlers::set_leaderboard_batch,
+        handlers::get_leaderboard_stats,
+        handlers::get_daily_challenge,
+        handlers::get_leaderboard_attachment,
+        handlers::set_save_data,
+        handlers::get_save_data,
+        handlers::patch_save_data,
+        handlers::set_save_data_batch,
+        handlers::get_storage,
+        handlers::get_global,
+        handlers::set_global,
+        handlers::increment_global,
+        handlers::increment_stat,
+        handlers::get_stats,
+        openapi_json,
+        v2::set_leaderboard,
+        v2::get_leaderboard,
+        v2::set_save_data,
+        v2::get_save_data,
+        v2::get_save_revisions,
+        v2::restore_save_revision
+    ),
+    components(schemas(
+        LeaderboardPost,
+        LeaderboardTeam,
+        LeaderboardComponent,
+        LeaderboardGetParams,
+        LeaderboardEntry,
+        LeaderboardBatch,
+        LeaderboardEntryStatus,
+        LeaderboardBatchResult,
+        LeaderboardStats,
+        LeaderboardPercentile,
+        HistogramBucket,
+        DailyChallenge,
+        SaveDataPost,
+        SaveDataGetParams,
+        SaveDataPatchParams,
+        SaveDataEntry,
+        SaveDataOperation,
+        SaveDataBatch,
+        SaveDataBatchResult,
+        StorageUsage,
+        GlobalPost,
+        GlobalIncrement,
+        GlobalEntry,
+        StatIncrement,
+        StatEntry,
+        QuotaError,
+        ValidationError,
+        v2::types::LeaderboardPost,
+        v2::types::LeaderboardEntry,
+        v2::types::LeaderboardComponent,
+        v2::types::SaveDataPost,
+        v2::types::SaveDataEntry,
+        v2::types::SaveRevisionEntry,
+        v2::types::SaveRestorePost,
+        v2::types::Pagination,
+        v2::types::ApiError
+    )),
+    tags(
+        (name = "leaderboard", description = "v1 leaderboard and daily challenges, frozen for \
+            shipped games"),
+        (name = "save-data", description = "v1 save data, frozen for shipped games"),
+        (name = "globals", description = "v1 values of a game shared by every player, such as \
+            community goals"),
+        (name = "stats", description = "v1 lifetime stats of each player, such as total jumps"),
+        (name = "v2", description = "v2 wraps every response in an envelope with `data`, \
+            `pagination` and `errors`, and identifies players by user id")
+    )
+)]
+pub struct ApiDoc;
+
+/// Serves the OpenAPI document of the game dev API
+#[utoipa::path(
+    get,
+    path = "/api/v1/openapi.json",
+    tag = "meta",
+    summary = "Get this OpenAPI document",
+    responses((status = 200, description = "This OpenAPI document", content_type = "application/json"))
+)]
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, path::Path};
+
+    const SPEC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/game-dev-api.openapi.json");
+
+    /// Fails when the checked in spec no longer matches the handlers
+    #[test]
+    fn openapi_spec_is_up_to_date() {
+        let spec = ApiDoc::openapi()
+            .to_pretty_json()
+            .expect("Failed to serialize OpenAPI spec")
+            + "\n";
+
+        if env::var("UPDATE_OPENAPI").is_ok() {
+            fs::write(SPEC_PATH, &spec).expect("Failed to write OpenAPI spec");
+            return;
+        }
+
+        let checked_in = fs::read_to_string(Path::new(SPEC_PATH))
+            .expect("Missing OpenAPI spec, run `UPDATE_OPENAPI=1 cargo test openapi`");
+        assert!(
+            checked_in.replace("\r\n", "\n") == spec,
+            "The OpenAPI spec is out of date, run `UPDATE_OPENAPI=1 cargo test openapi` \
+            and commit game-dev-api.openapi.json"
+        );
+    }
+
+    #[test]
+    fn openapi_spec_documents_every_route() {
+        let spec = ApiDoc::openapi();
+        let paths: Vec<&String> = spec.paths.paths.keys().collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/api/v1/daily-challenge",
+                "/api/v1/globals/{key}",
+                "/api/v1/globals/{key}/increment",
+                "/api/v1/leaderboard",
+                "/api/v1/leaderboard/batch",
+                "/api/v1/leaderboard/stats",
+                "/api/v1/leaderboard/{id}/attachment",
+                "/api/v1/openapi.json",
+                "/api/v1/save-data",
+                "/api/v1/save-data/batch",
+                "/api/v1/stats",
+                "/api/v1/stats/increment",
+                "/api/v1/storage",
+                "/api/v2/leaderboard",
+                "/api/v2/save-data",
+                "/api/v2/save-data/restore",
+                "/api/v2/save-data/revisions"
+            ]
+        );
+
+        let leaderboard = &spec.paths.paths["/api/v1/leaderboard"];
+        assert!(leaderboard.get.is_some() && leaderboard.post.is_some());
+        let save_data = &spec.paths.paths["/api/v1/save-data"];
+        assert!(save_data.get.is_some() && save_data.post.is_some());
+        assert!(save_data.patch.is_some());
+        let leaderboard = &spec.paths.paths["/api/v2/leaderboard"];
+        assert!(leaderboard.get.is_some() && leaderboard.post.is_some());
+        let save_data = &spec.paths.paths["/api/v2/save-data"];
+        assert!(save_data.get.is_some() && save_data.post.is_some());
+        assert!(spec.paths.paths["/api/v2/save-data/revisions"]
+            .get
+            .is_some());
+        assert!(spec.paths.paths["/api/v2/save-data/restore"].post.is_some());
+    }
+}
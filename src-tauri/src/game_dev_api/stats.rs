@@ -0,0 +1,179 @@
+//! Statistics of every entry of a leaderboard metric, such as the percentile a new score beats.
+//!
+//! Games can only download a page of entries at a time, so the console works these out for them
+//! from the sorted values of the metric.
+
+use quackbox_client::types::{HistogramBucket, LeaderboardPercentile, LeaderboardStats};
+
+/// Percentiles computed when a request doesn't ask for any
+pub const DEFAULT_PERCENTILES: [f64; 5] = [25.0, 50.0, 75.0, 90.0, 99.0];
+
+/// Most percentiles a single request can ask for
+pub const MAX_PERCENTILES: usize = 20;
+
+/// Number of histogram buckets when a request doesn't ask for a number
+pub const DEFAULT_BUCKETS: u32 = 10;
+
+/// Most histogram buckets a single request can ask for
+pub const MAX_BUCKETS: u32 = 100;
+
+/// Parses the comma separated `percentiles` query parameter, returning the defaults if there is
+/// none
+pub fn parse_percentiles(percentiles: Option<&str>) -> Result<Vec<f64>, String> {
+    let Some(percentiles) = percentiles else {
+        return Ok(DEFAULT_PERCENTILES.to_vec());
+    };
+    let parsed = percentiles
+        .split(',')
+        .map(|percentile| match percentile.trim().parse::<f64>() {
+            Ok(p) if (0.0..=100.0).contains(&p) => Ok(p),
+            _ => Err(format!(
+                "Percentile {:?} isn't a number from 0 to 100",
+                percentile
+            )),
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    if parsed.len() > MAX_PERCENTILES {
+        return Err(format!(
+            "At most {} percentiles can be requested",
+            MAX_PERCENTILES
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Checks the `buckets` query parameter, returning the default if there is none
+pub fn check_buckets(buckets: Option<u32>) -> Result<u32, String> {
+    match buckets.unwrap_or(DEFAULT_BUCKETS) {
+        buckets @ 1..=MAX_BUCKETS => Ok(buckets),
+        _ => Err(format!(
+            "The number of buckets has to be from 1 to {}",
+            MAX_BUCKETS
+        )),
+    }
+}
+
+/// Returns the value at percentile `p` of non-empty sorted values, interpolated linearly between
+/// the two closest values
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Returns the percentage of sorted values that `value` beats. Higher values are better unless
+/// `ascending` is set, and equal values aren't beaten.
+pub fn value_percentile(sorted: &[f64], value: f64, ascending: bool) -> f64 {
+    let beaten = if ascending {
+        sorted.len() - sorted.partition_point(|v| *v <= value)
+    } else {
+        sorted.partition_point(|v| *v < value)
+    };
+    beaten as f64 / sorted.len() as f64 * 100.0
+}
+
+/// Counts non-empty sorted values in `buckets` equally wide buckets from the lowest to the
+/// highest value. All values end up in a single bucket if they are equal.
+pub fn histogram(sorted: &[f64], buckets: u32) -> Vec<HistogramBucket> {
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    if min == max {
+        return vec![HistogramBucket {
+            min,
+            max,
+            count: sorted.len() as i64,
+        }];
+    }
+    let width = (max - min) / buckets as f64;
+    let mut histogram: Vec<HistogramBucket> = (0..buckets)
+        .map(|i| HistogramBucket {
+            min: min + width * i as f64,
+            max: if i + 1 == buckets {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            },
+            count: 0,
+        })
+        .collect();
+    for value in sorted {
+        let bucket = (((value - min) / width) as usize).min(histogram.len() - 1);
+        histogram[bucket].count += 1;
+    }
+    histogram
+}
+
+/// Works out the statistics of the sorted values of a leaderboard metric
+pub fn leaderboard_stats(
+    sorted: &[f64],
+    percentiles: &[f64],
+    buckets: u32,
+    value: Option<f64>,
+    ascending: bool,
+) -> LeaderboardStats {
+    if sorted.is_empty() {
+        return LeaderboardStats {
+            count: 0,
+            min: None,
+            max: None,
+            mean: None,
+            median: None,
+            percentiles: Vec::new(),
+            histogram: Vec::new(),
+            value_percentile: None,
+        };
+    }
+    LeaderboardStats {
+        count: sorted.len() as i64,
+        min: sorted.first().copied(),
+        max: sorted.last().copied(),
+        mean: Some(sorted.iter().sum::<f64>() / sorted.len() as f64),
+        median: Some(percentile(sorted, 50.0)),
+        percentiles: percentiles
+            .iter()
+            .map(|p| LeaderboardPercentile {
+                percentile: *p,
+                value: percentile(sorted, *p),
+            })
+            .collect(),
+        histogram: histogram(sorted, buckets),
+        value_percentile: value.map(|value| value_percentile(sorted, value, ascending)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let sorted = [10.0, 20.0, 30.0, 40.0];
+        assert_eq!(percentile(&sorted, 0.0), 10.0);
+        assert_eq!(percentile(&sorted, 50.0), 25.0);
+        assert_eq!(percentile(&sorted, 100.0), 40.0);
+        assert_eq!(percentile(&[7.0], 90.0), 7.0);
+
+        assert_eq!(value_percentile(&sorted, 30.0, false), 50.0);
+        assert_eq!(value_percentile(&sorted, 30.0, true), 25.0);
+        assert_eq!(value_percentile(&sorted, 5.0, true), 100.0);
+
+        assert_eq!(parse_percentiles(Some("50, 99.9")), Ok(vec![50.0, 99.9]));
+        assert!(parse_percentiles(Some("101")).is_err());
+        assert!(parse_percentiles(Some("50,")).is_err());
+        assert!(check_buckets(Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let buckets = histogram(&[0.0, 1.0, 5.0, 9.0, 10.0], 2);
+        let counts: Vec<(f64, f64, i64)> = buckets
+            .iter()
+            .map(|bucket| (bucket.min, bucket.max, bucket.count))
+            .collect();
+        assert_eq!(counts, vec![(0.0, 5.0, 2), (5.0, 10.0, 3)]);
+
+        let buckets = histogram(&[3.0, 3.0], 10);
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].count, 2);
+    }
+}
@@ -0,0 +1,43 @@
+//! Opaque cursors for paging through listings.
+//!
+//! A cursor is the position of the last item of a page, encoded so games treat it as an opaque
+//! string and pass it back unchanged to get the next page.
+
+use super::types::ErrorResponse;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub fn encode<T: Serialize>(position: &T) -> String {
+    let json = serde_json::to_vec(position).expect("Positions should always serialize");
+    URL_SAFE_NO_PAD.encode(json)
+}
+
+/// # Errors
+/// * If the cursor wasn't created by [`encode`] for the same kind of position
+pub fn decode<T: DeserializeOwned>(cursor: &str) -> Result<T, ErrorResponse> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| ErrorResponse::invalid_request("cursor is invalid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::LeaderboardPosition;
+
+    #[test]
+    fn cursor_round_trip() {
+        let position = LeaderboardPosition {
+            value_num: 12.5,
+            tie_breakers: vec![80.0],
+            row_id: 3,
+        };
+        let cursor = encode(&position);
+        assert_eq!(decode::<LeaderboardPosition>(&cursor).unwrap(), position);
+
+        assert!(decode::<LeaderboardPosition>("not a cursor").is_err());
+        assert!(decode::<LeaderboardPosition>(&encode(&"a string")).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+//! Version 2 of the game dev API.
+//!
+//! Compared to v1, every response is wrapped in an [`Envelope`], players are identified by their
+//! user id instead of a player slot, and time stamps are RFC 3339 date times. Listings are paged
+//! with opaque cursors and report the total number of matching items.
+
+use super::handlers::{
+    etag, if_match, quota_status, running_game_id, ApiState, AppState, GameStateShared,
+};
+use super::validation::LeaderboardRules;
+use crate::db::{
+    self, LeaderboardFilter, LeaderboardPosition, LeaderboardWrite, QuotaExceeded,
+    RevisionMismatch, SaveFilter, SavePosition,
+};
+use axum::{
+    extract::{rejection::JsonRejection, rejection::QueryRejection, Query, State},
+    http::{header::ETAG, HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use diesel::result::DatabaseErrorKind;
+use tracing::{debug, error, instrument, Span};
+
+pub mod cursor;
+pub mod types;
+
+use types::{
+    parse_time_stamp, Envelope, ErrorResponse, LeaderboardComponent, LeaderboardEntry,
+    LeaderboardGetParams, LeaderboardPost, Pagination, SaveDataEntry, SaveDataGetParams,
+    SaveDataPost, SaveRestorePost, SaveRevisionEntry, SaveRevisionsGetParams,
+};
+
+/// Largest number of leaderboard entries or save files returned at once
+const MAX_LIMIT: i64 = 100;
+
+/// Routes of the v2 API, relative to `/api/v2`
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/leaderboard", post(set_leaderboard).get(get_leaderboard))
+        .route("/save-data", post(set_save_data).get(get_save_data))
+        .route("/save-data/revisions", get(get_save_revisions))
+        .route("/save-data/restore", post(restore_save_revision))
+}
+
+/// Returns the id of the running game, or a `no_game_running` error
+async fn current_game_id(game_state: &GameStateShared) -> Result<String, ErrorResponse> {
+    let game_id = running_game_id(game_state).await.ok_or_else(|| {
+        debug!("Rejected request because no game is running");
+        ErrorResponse::no_game_running()
+    })?;
+    Span::current().record("game_id", game_id.as_str());
+    Ok(game_id)
+}
+
+/// Returns the page size, defaulting to [`MAX_LIMIT`]
+fn validate_limit(limit: Option<i64>) -> Result<i64, ErrorResponse> {
+    let limit = limit.unwrap_or(MAX_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ErrorResponse::invalid_request(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    Ok(limit)
+}
+
+/// Turns an error from writing a save into a quota error, or an internal error with the given
+/// message
+fn save_error(e: anyhow::Error, message: &str) -> ErrorResponse {
+    if let Some(mismatch) = e.downcast_ref::<RevisionMismatch>() {
+        debug!(current = ?mismatch.current, "Save refused by If-Match");
+        return ErrorResponse::new(
+            StatusCode::PRECONDITION_FAILED,
+            "revision_mismatch",
+            mismatch,
+        );
+    }
+    match e.downcast_ref::<QuotaExceeded>() {
+        Some(exceeded) => {
+            debug!(
+                code = exceeded.code(),
+                limit = exceeded.limit,
+                requested = exceeded.requested,
+                "Save refused by quota"
+            );
+            ErrorResponse::new(quota_status(exceeded), exceeded.code(), exceeded)
+        }
+        None => {
+            error!(error = %e, "{}", message);
+            ErrorResponse::internal(message)
+        }
+    }
+}
+
+/// Whether a write failed because its `user_id` isn't a user of the console
+fn is_unknown_user(e: &diesel::result::Error) -> bool {
+    matches!(
+        e,
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)
+    )
+}
+
+fn validate_user_id(user_id: &str) -> Result<(), ErrorResponse> {
+    if user_id.is_empty() {
+        return Err(ErrorResponse::invalid_request("user_id must not be empty"));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/leaderboard",
+    tag = "v2",
+    summary = "Add a leaderboard entry for a user",
+    request_body = LeaderboardPost,
+    responses(
+        (status = 200, description = "The entry was added", body = Envelope<LeaderboardPost>),
+        (status = 400, description = "The body is invalid", body = Envelope<serde_json::Value>),
+        (status = 409, description = "No game is currently running", body = Envelope<serde_json::Value>),
+        (status = 422, description = "The entry breaks a validation rule, with a code such as `value_not_finite`, `value_name_too_long` or `component_missing`, or no user has the `user_id`, with code `unknown_user`", body = Envelope<serde_json::Value>)
+    )
+)]
+#[instrument(skip_all, fields(game_id))]
+pub async fn set_leaderboard(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    payload: Result<Json<LeaderboardPost>, JsonRejection>,
+) -> Result<Json<Envelope<LeaderboardPost>>, ErrorResponse> {
+    let Json(mut payload) = payload.map_err(|e| ErrorResponse::invalid_request(e.body_text()))?;
+    let game_id = current_game_id(&game_state).await?;
+    validate_user_id(&payload.user_id)?;
+    let rules = LeaderboardRules::load(&game_id, &state.database_path).map_err(|e| {
+        error!(error = %e, "Failed to load leaderboard rules");
+        ErrorResponse::internal("Failed to save the leaderboard entry")
+    })?;
+    let (value_num, tie_breakers) = rules
+        .resolve(
+            &payload.value_name,
+            payload.value_num,
+            payload.values.as_ref(),
+        )
+        .and_then(|(value_num, tie_breakers)| {
+            rules.check(&payload.value_name, value_num)?;
+            Ok((value_num, tie_breakers))
+        })
+        .map_err(|violation| {
+            debug!(
+                code = violation.code(),
+                "Leaderboard entry refused by validation"
+            );
+            ErrorResponse::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                violation.code(),
+                violation,
+            )
+        })?;
+    payload.value_num = Some(value_num);
+
+    let write = LeaderboardWrite {
+        user_id: payload.user_id.clone(),
+        value_name: payload.value_name.clone(),
+        value_num,
+        tie_breakers,
+        ..LeaderboardWrite::default()
+    };
+    db::insert_leaderboard_write(&game_id, &write, &state.database_path).map_err(|e| {
+        if is_unknown_user(&e) {
+            debug!(user_id = %payload.user_id, "Leaderboard entry refused for an unknown user");
+            return ErrorResponse::unknown_user(&payload.user_id);
+        }
+        error!(error = %e, "Failed to insert leaderboard entry");
+        ErrorResponse::internal("Failed to save the leaderboard entry")
+    })?;
+    debug!(user_id = %payload.user_id, value_name = %payload.value_name, "Set leaderboard entry");
+
+    Ok(Json(Envelope::new(payload)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/leaderboard",
+    tag = "v2",
+    summary = "Get leaderboard entries, highest value first",
+    params(LeaderboardGetParams),
+    responses(
+        (status = 200, description = "Leaderboard entries of the running game", body = Envelope<Vec<LeaderboardEntry>>),
+        (status = 400, description = "The parameters are invalid", body = Envelope<serde_json::Value>),
+        (status = 409, description = "No game is currently running", body = Envelope<serde_json::Value>)
+    )
+)]
+#[instrument(skip_all, fields(game_id))]
+pub async fn get_leaderboard(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    params: Result<Query<LeaderboardGetParams>, QueryRejection>,
+) -> Result<Json<Envelope<Vec<LeaderboardEntry>>>, ErrorResponse> {
+    let Query(params) = params.map_err(|e| ErrorResponse::invalid_request(e.body_text()))?;
+    let game_id = current_game_id(&game_state).await?;
+
+    let limit = validate_limit(params.limit)?;
+    let after = params
+        .cursor
+        .as_deref()
+        .map(cursor::decode::<LeaderboardPosition>)
+        .transpose()?;
+
+    let rules = LeaderboardRules::load(&game_id, &state.database_path).map_err(|e| {
+        error!(error = %e, "Failed to load leaderboard rules");
+        ErrorResponse::internal("Failed to read the leaderboard")
+    })?;
+    let filter = LeaderboardFilter {
+        game_id,
+        user_id: params.user_id,
+        value_name: params.value_name,
+        ascending: params.ascending.unwrap_or(false),
+        // daily challenge entries are only listed by v1
+        challenge_date: None,
+    };
+    // get one extra entry to find out if there is a next page
+    let (mut entries, total) =
+        db::get_leaderboard_page(&filter, after.as_ref(), limit + 1, &state.database_path)
+            .map_err(|e| {
+                error!(error = %e, "Failed to get leaderboard");
+                ErrorResponse::internal("Failed to read the leaderboard")
+            })?;
+    let has_next_page = entries.len() as i64 > limit;
+    entries.truncate(limit as usize);
+    debug!(entries = entries.len(), total, "Got leaderboard entries");
+
+    let next_cursor = entries.last().filter(|_| has_next_page).map(|last| {
+        // the tie-breakers only rank entries of a single composite metric
+        let ranked = filter
+            .value_name
+            .as_deref()
+            .map_or(0, |value_name| rules.components(value_name).len());
+        cursor::encode(&LeaderboardPosition {
+            value_num: last.value_num,
+            tie_breakers: last.components()[1..ranked.max(1)].to_vec(),
+            row_id: last.row_id,
+        })
+    });
+
+    let entries = entries
+        .into_iter()
+        .map(|entry| {
+            let metric_components = rules.components(&entry.value_name);
+            let components = (!metric_components.is_empty()).then(|| {
+                metric_components
+                    .iter()
+                    .zip(entry.components())
+                    .map(|(component, value)| LeaderboardComponent {
+                        name: component.name.clone(),
+                        value,
+                    })
+                    .collect()
+            });
+            Ok(LeaderboardEntry {
+                time_stamp: parse_time_stamp(&entry.time_stamp).map_err(|e| {
+                    error!(error = %e, time_stamp = %entry.time_stamp, "Invalid time stamp");
+                    ErrorResponse::internal("Failed to read the leaderboard")
+                })?,
+                user_id: entry.user_id,
+                value_name: entry.value_name,
+                value_num: entry.value_num,
+                components,
+            })
+        })
+        .collect::<Result<Vec<LeaderboardEntry>, ErrorResponse>>()?;
+
+    Ok(Json(Envelope::with_pagination(
+        entries,
+        Pagination {
+            limit,
+            total,
+            next_cursor,
+        },
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/save-data",
+    tag = "v2",
+    summary = "Create or replace a save file for a user",
+    request_body = SaveDataPost,
+    params(
+        ("If-Match" = Option<String>, Header, description = "Only write the save file if its revision is one of these entity tags, such as `\"3\"`, or if it exists at all for `*`")
+    ),
+    responses(
+        (status = 200, description = "The save file was created or replaced", body = Envelope<SaveDataPost>,
+            headers(("ETag" = String, description = "Revision of the save file that was written"))),
+        (status = 400, description = "The body is invalid", body = Envelope<serde_json::Value>),
+        (status = 409, description = "No game is currently running", body = Envelope<serde_json::Value>),
+        (status = 412, description = "The save file doesn't match `If-Match`, with code `revision_mismatch`", body = Envelope<serde_json::Value>),
+        (status = 413, description = "The save file is too large, with code `save_too_large`", body = Envelope<serde_json::Value>),
+        (status = 422, description = "No user has the `user_id`, with code `unknown_user`", body = Envelope<serde_json::Value>),
+        (status = 507, description = "The user has too many save files or the game is out of storage, with code `too_many_saves` or `storage_full`", body = Envelope<serde_json::Value>)
+    )
+)]
+#[instrument(skip_all, fields(game_id))]
+pub async fn set_save_data(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    headers: HeaderMap,
+    payload: Result<Json<SaveDataPost>, JsonRejection>,
+) -> Result<(HeaderMap, Json<Envelope<SaveDataPost>>), ErrorResponse> {
+    let Json(payload) = payload.map_err(|e| ErrorResponse::invalid_request(e.body_text()))?;
+    let game_id = current_game_id(&game_state).await?;
+    validate_user_id(&payload.user_id)?;
+
+    let data = serde_json::to_vec(&payload.data).map_err(ErrorResponse::internal)?;
+    let save = db::set_save_if(
+        &payload.user_id,
+        &game_id,
+        &payload.file_name,
+        &data,
+        if_match(&headers).as_ref(),
+        &state.database_path,
+    )
+    .await
+    .map_err(|e| match e.downcast_ref::<diesel::result::Error>() {
+        Some(db_error) if is_unknown_user(db_error) => {
+            debug!(user_id = %payload.user_id, "Save refused for an unknown user");
+            ErrorResponse::unknown_user(&payload.user_id)
+        }
+        _ => save_error(e, "Failed to save the save file"),
+    })?;
+    debug!(
+        user_id = %payload.user_id,
+        file_name = %payload.file_name,
+        revision = save.revision,
+        "Set save data"
+    );
+
+    let headers = HeaderMap::from_iter([(ETAG, etag(save.revision))]);
+    Ok((headers, Json(Envelope::new(payload))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/save-data",
+    tag = "v2",
+    summary = "Get save files by name, regular expression, glob or prefix",
+    params(SaveDataGetParams),
+    responses(
+        (status = 200, description = "Save files of the running game, ordered by file name", body = Envelope<Vec<SaveDataEntry>>),
+        (status = 400, description = "More than one of `file_name`, `regex`, `glob` and `prefix` were given, or `regex` is invalid", body = Envelope<serde_json::Value>),
+        (status = 409, description = "No game is currently running", body = Envelope<serde_json::Value>)
+    )
+)]
+#[instrument(skip_all, fields(game_id))]
+pub async fn get_save_data(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    params: Result<Query<SaveDataGetParams>, QueryRejection>,
+) -> Result<Json<Envelope<Vec<SaveDataEntry>>>, ErrorResponse> {
+    let Query(params) = params.map_err(|e| ErrorResponse::invalid_request(e.body_text()))?;
+    let game_id = current_game_id(&game_state).await?;
+
+    let limit = validate_limit(params.limit)?;
+    let after = params
+        .cursor
+        .as_deref()
+        .map(cursor::decode::<SavePosition>)
+        .transpose()?;
+    let include_data = !params.metadata_only.unwrap_or(false);
+
+    let filter = SaveFilter {
+        game_id,
+        user_id: params.user_id,
+        file_name: params.file_name,
+        regex: params.regex,
+        glob: params.glob,
+        prefix: params.prefix,
+    };
+    filter.validate().map_err(ErrorResponse::invalid_request)?;
+    // get one extra save to find out if there is a next page
+    let (mut saves, total) = db::get_save_data_page(
+        &filter,
+        after.as_ref(),
+        limit as usize + 1,
+        include_data,
+        &state.database_path,
+    )
+    .map_err(|e| {
+        error!(error = %e, "Failed to get save data");
+        ErrorResponse::internal("Failed to get the save data")
+    })?;
+    let has_next_page = saves.len() as i64 > limit;
+    saves.truncate(limit as usize);
+    debug!(entries = saves.len(), total, include_data, "Got save data");
+
+    let next_cursor = saves.last().filter(|_| has_next_page).map(|(last, _)| {
+        cursor::encode(&SavePosition {
+            file_name: last.file_name.clone(),
+            row_id: last.row_id,
+        })
+    });
+
+    let entries = saves
+        .into_iter()
+        .map(|(save, data)| {
+            let data = data
+                .map(|data| serde_json::from_slice(&data))
+                .transpose()
+                .map_err(|e| {
+                    error!(error = %e, file_name = %save.file_name, "Invalid save data");
+                    ErrorResponse::internal("Failed to read save data")
+                })?;
+            let time_stamp = parse_time_stamp(&save.time_stamp).map_err(|e| {
+                error!(error = %e, time_stamp = %save.time_stamp, "Invalid time stamp");
+                ErrorResponse::internal("Failed to read save data")
+            })?;
+            Ok(SaveDataEntry {
+                user_id: save.user_id,
+                file_name: save.file_name,
+                data,
+                time_stamp,
+                revision: save.revision,
+            })
+        })
+        .collect::<Result<Vec<SaveDataEntry>, ErrorResponse>>()?;
+
+    Ok(Json(Envelope::with_pagination(
+        entries,
+        Pagination {
+            limit,
+            total,
+            next_cursor,
+        },
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/save-data/revisions",
+    tag = "v2",
+    summary = "List the kept revisions of a save file, newest first",
+    params(SaveRevisionsGetParams),
+    responses(
+        (status = 200, description = "Revisions of the save file. The newest one is the current data.", body = Envelope<Vec<SaveRevisionEntry>>),
+        (status = 400, description = "The parameters are invalid", body = Envelope<serde_json::Value>),
+        (status = 409, description = "No game is currently running", body = Envelope<serde_json::Value>)
+    )
+)]
+#[instrument(skip_all, fields(game_id))]
+pub async fn get_save_revisions(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    params: Result<Query<SaveRevisionsGetParams>, QueryRejection>,
+) -> Result<Json<Envelope<Vec<SaveRevisionEntry>>>, ErrorResponse> {
+    let Query(params) = params.map_err(|e| ErrorResponse::invalid_request(e.body_text()))?;
+    let game_id = current_game_id(&game_state).await?;
+    validate_user_id(&params.user_id)?;
+
+    let revisions = db::get_save_revisions(
+        &params.user_id,
+        &game_id,
+        &params.file_name,
+        &state.database_path,
+    )
+    .map_err(|e| {
+        error!(error = %e, "Failed to get save revisions");
+        ErrorResponse::internal("Failed to read save revisions")
+    })?;
+    debug!(revisions = revisions.len(), "Got save revisions");
+
+    let revisions = revisions
+        .into_iter()
+        .map(|revision| {
+            Ok(SaveRevisionEntry {
+                time_stamp: parse_time_stamp(&revision.time_stamp).map_err(|e| {
+                    error!(error = %e, time_stamp = %revision.time_stamp, "Invalid time stamp");
+                    ErrorResponse::internal("Failed to read save revisions")
+                })?,
+                revision: revision.revision,
+            })
+        })
+        .collect::<Result<Vec<SaveRevisionEntry>, ErrorResponse>>()?;
+
+    Ok(Json(Envelope::new(revisions)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v2/save-data/restore",
+    tag = "v2",
+    summary = "Restore an earlier revision of a save file",
+    description = "The restored data is written as a new revision, so restoring can be undone by restoring the revision before it.",
+    request_body = SaveRestorePost,
+    responses(
+        (status = 200, description = "The save file after restoring", body = Envelope<SaveDataEntry>),
+        (status = 400, description = "The body is invalid", body = Envelope<serde_json::Value>),
+        (status = 404, description = "The revision isn't kept", body = Envelope<serde_json::Value>),
+        (status = 409, description = "No game is currently running", body = Envelope<serde_json::Value>),
+        (status = 507, description = "Restoring would use more storage than the game has left, with code `storage_full`", body = Envelope<serde_json::Value>)
+    )
+)]
+#[instrument(skip_all, fields(game_id))]
+pub async fn restore_save_revision(
+    State(state): State<ApiState>,
+    State(game_state): State<GameStateShared>,
+    payload: Result<Json<SaveRestorePost>, JsonRejection>,
+) -> Result<Json<Envelope<SaveDataEntry>>, ErrorResponse> {
+    let Json(payload) = payload.map_err(|e| ErrorResponse::invalid_request(e.body_text()))?;
+    let game_id = current_game_id(&game_state).await?;
+    validate_user_id(&payload.user_id)?;
+
+    let save = db::restore_save_revision(
+        &payload.user_id,
+        &game_id,
+        &payload.file_name,
+        payload.revision,
+        &state.database_path,
+    )
+    .map_err(|e| save_error(e, "Failed to restore the save revision"))?
+    .ok_or_else(|| {
+        ErrorResponse::not_found(format!(
+            "Revision {} of {} is not kept",
+            payload.revision, payload.file_name
+        ))
+    })?;
+    debug!(file_name = %save.file_name, revision = save.revision, restored = payload.revision, "Restored save revision");
+
+    let data = serde_json::from_slice(&save.data).map_err(|e| {
+        error!(error = %e, file_name = %save.file_name, "Invalid save data");
+        ErrorResponse::internal("Failed to read save data")
+    })?;
+    let time_stamp = parse_time_stamp(&save.time_stamp).map_err(|e| {
+        error!(error = %e, time_stamp = %save.time_stamp, "Invalid time stamp");
+        ErrorResponse::internal("Failed to read save data")
+    })?;
+    Ok(Json(Envelope::new(SaveDataEntry {
+        user_id: save.user_id,
+        file_name: save.file_name,
+        data: Some(data),
+        time_stamp,
+        revision: save.revision,
+    })))
+}
@@ -0,0 +1,255 @@
+//! Request and response bodies of version 2 of the game dev API
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
+
+/// Every v2 response has the same shape. `data` is null when the request failed, `pagination` is
+/// only set for listings, and `errors` is empty when the request succeeded.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Envelope<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub pagination: Option<Pagination>,
+    #[serde(default)]
+    pub errors: Vec<ApiError>,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(data: T) -> Self {
+        Envelope {
+            data: Some(data),
+            pagination: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn with_pagination(data: T, pagination: Pagination) -> Self {
+        Envelope {
+            pagination: Some(pagination),
+            ..Envelope::new(data)
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Pagination {
+    pub limit: i64,
+    /// Number of items matching the request across every page
+    pub total: i64,
+    /// Pass this as `cursor` to get the next page. Null if this is the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// A machine readable error code along with a message for developers
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct ApiError {
+    /// One of `no_game_running`, `invalid_request`, `not_found`, `save_too_large`,
+    /// `too_many_saves`, `storage_full` or `internal`
+    pub code: String,
+    pub message: String,
+}
+
+/// An error response, sent as an [`Envelope`] without data
+#[derive(Debug)]
+pub struct ErrorResponse {
+    pub status: StatusCode,
+    pub error: ApiError,
+}
+
+impl ErrorResponse {
+    pub fn new(status: StatusCode, code: &str, message: impl ToString) -> Self {
+        ErrorResponse {
+            status,
+            error: ApiError {
+                code: code.to_string(),
+                message: message.to_string(),
+            },
+        }
+    }
+
+    pub fn no_game_running() -> Self {
+        ErrorResponse::new(
+            StatusCode::CONFLICT,
+            "no_game_running",
+            "No game is currently running",
+        )
+    }
+
+    pub fn invalid_request(message: impl ToString) -> Self {
+        ErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_request", message)
+    }
+
+    pub fn not_found(message: impl ToString) -> Self {
+        ErrorResponse::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn unknown_user(user_id: &str) -> Self {
+        ErrorResponse::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unknown_user",
+            format!("No user has the id {:?}", user_id),
+        )
+    }
+
+    pub fn internal(message: impl ToString) -> Self {
+        ErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, "internal", message)
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        let envelope: Envelope<serde_json::Value> = Envelope {
+            data: None,
+            pagination: None,
+            errors: vec![self.error],
+        };
+        (self.status, Json(envelope)).into_response()
+    }
+}
+
+/// Body of a leaderboard post request. The API responds with the same fields.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(as = v2::LeaderboardPost)]
+pub struct LeaderboardPost {
+    /// Id of the user the entry belongs to
+    pub user_id: String,
+    /// Name of the value, such as `score` or `time`
+    pub value_name: String,
+    /// Has to be given unless the metric is composite, where it is set to the value of its
+    /// first component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_num: Option<f64>,
+    /// Every value of an entry for a composite metric by name, such as
+    /// `{"score": 1200, "time": 83.2}`. The console declares which values a composite metric
+    /// is ranked by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<BTreeMap<String, f64>>,
+}
+
+/// Query parameters of a leaderboard get request. Every parameter is optional.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LeaderboardGetParams {
+    /// Number of entries to return, at most 100. Defaults to 100.
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page. Pages stay stable when new entries are posted.
+    pub cursor: Option<String>,
+    /// Sort from lowest to highest value instead of highest to lowest. Entries of a composite
+    /// metric are sorted by each of its values in turn, and this reverses all of them.
+    pub ascending: Option<bool>,
+    pub value_name: Option<String>,
+    /// Only return entries of this user
+    pub user_id: Option<String>,
+}
+
+/// A single leaderboard entry
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(as = v2::LeaderboardEntry)]
+pub struct LeaderboardEntry {
+    pub user_id: String,
+    pub value_name: String,
+    pub value_num: f64,
+    /// When the entry was posted
+    pub time_stamp: DateTime<Utc>,
+    /// Every value of an entry for a composite metric, in the order the entries are ranked by
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<LeaderboardComponent>>,
+}
+
+/// One of the values of a leaderboard entry for a composite metric
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(as = v2::LeaderboardComponent)]
+pub struct LeaderboardComponent {
+    pub name: String,
+    pub value: f64,
+}
+
+/// Body of a save data post request. The API responds with the same fields.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(as = v2::SaveDataPost)]
+pub struct SaveDataPost {
+    /// Id of the user the save file belongs to
+    pub user_id: String,
+    /// Name of the save file. Posting the same file name again replaces the file.
+    pub file_name: String,
+    /// Any JSON value
+    pub data: serde_json::Value,
+}
+
+/// Query parameters of a save data get request. Every parameter is optional, but only one of
+/// `file_name`, `regex`, `glob` and `prefix` can be used at once.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SaveDataGetParams {
+    pub file_name: Option<String>,
+    /// Only return save files with names matching this regular expression
+    pub regex: Option<String>,
+    /// Only return save files with names matching this case sensitive glob pattern, where `*`
+    /// matches any characters and `?` matches one character. Cheaper than `regex`.
+    pub glob: Option<String>,
+    /// Only return save files with names starting with this
+    pub prefix: Option<String>,
+    /// Only return save files of this user
+    pub user_id: Option<String>,
+    /// Number of save files to return, at most 100. Defaults to 100.
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Leave out `data`, such as for showing a list of save slots
+    pub metadata_only: Option<bool>,
+}
+
+/// A single save file. Save files are listed in order of file name.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(as = v2::SaveDataEntry)]
+pub struct SaveDataEntry {
+    pub user_id: String,
+    pub file_name: String,
+    /// Left out when `metadata_only` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    /// When the save file was first written
+    pub time_stamp: DateTime<Utc>,
+    /// Increases by one every time the save file is written. Send it as `If-Match: "<revision>"`
+    /// to only replace the save file if nothing else wrote it since.
+    pub revision: i32,
+}
+
+/// Query parameters of a save revisions get request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SaveRevisionsGetParams {
+    pub user_id: String,
+    pub file_name: String,
+}
+
+/// A kept revision of a save file
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(as = v2::SaveRevisionEntry)]
+pub struct SaveRevisionEntry {
+    pub revision: i32,
+    /// When this revision was written
+    pub time_stamp: DateTime<Utc>,
+}
+
+/// Body of a save restore post request
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(as = v2::SaveRestorePost)]
+pub struct SaveRestorePost {
+    pub user_id: String,
+    pub file_name: String,
+    /// The revision to restore, from the save revisions listing
+    pub revision: i32,
+}
+
+/// Parses a time stamp stored by SQLite's `CURRENT_TIMESTAMP`, which is always in UTC
+pub fn parse_time_stamp(time_stamp: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+    NaiveDateTime::parse_from_str(time_stamp, "%Y-%m-%d %H:%M:%S").map(|time| time.and_utc())
+}
@@ -0,0 +1,311 @@
+//! Rules leaderboard entries have to follow before they are stored.
+//!
+//! Entries breaking a rule are refused with a 422 response naming the rule, so a game can tell
+//! a bad submission apart from the console being unavailable.
+
+use crate::db::{self, models::MetricComponent};
+use diesel::QueryResult;
+use quackbox_client::types::LeaderboardTeam;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Longest `value_name` accepted, in characters
+pub const MAX_VALUE_NAME_LEN: usize = 64;
+
+/// Longest team name accepted, in characters
+pub const MAX_TEAM_NAME_LEN: usize = 64;
+
+/// Most players in a team, one per player slot
+pub const MAX_TEAM_SIZE: usize = 8;
+
+/// Most values a composite metric is ranked by, `value_num` and three tie-breakers
+pub const MAX_METRIC_COMPONENTS: usize = 4;
+
+/// A rule a leaderboard entry broke
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeaderboardViolation {
+    /// The entry has no `value_num` and its metric isn't composite
+    MissingValue,
+    /// The value is NaN or infinite, which breaks sorting the leaderboard
+    NotFinite,
+    EmptyName,
+    /// The name is longer than [`MAX_VALUE_NAME_LEN`], with its length
+    NameTooLong(usize),
+    /// The name has a character other than letters, digits, spaces, `_`, `-` and `.`
+    InvalidNameCharacter(char),
+    /// The game only allows whole numbers for this value
+    NotInteger(f64),
+    EmptyTeamName,
+    /// The team name is longer than [`MAX_TEAM_NAME_LEN`], with its length
+    TeamNameTooLong(usize),
+    /// The team has more than [`MAX_TEAM_SIZE`] members, with their number
+    TeamTooLarge(usize),
+    /// The entry is for a composite metric but leaves out this component
+    MissingComponent(String),
+    /// The entry has a value that isn't a component of the composite metric
+    UnknownComponent(String),
+    /// A component's value is NaN or infinite
+    ComponentNotFinite(String),
+    /// The entry has `values` but its metric isn't composite
+    ValuesNotAllowed,
+    /// The challenge date isn't a date like `2026-10-19`
+    InvalidChallengeDate(String),
+    /// The daily challenge of the date isn't running anymore, or hasn't started yet
+    ChallengeClosed(String),
+}
+
+impl LeaderboardViolation {
+    /// Machine readable name of the broken rule
+    pub fn code(&self) -> &'static str {
+        match self {
+            LeaderboardViolation::MissingValue => "value_missing",
+            LeaderboardViolation::NotFinite => "value_not_finite",
+            LeaderboardViolation::EmptyName => "value_name_empty",
+            LeaderboardViolation::NameTooLong(_) => "value_name_too_long",
+            LeaderboardViolation::InvalidNameCharacter(_) => "value_name_invalid",
+            LeaderboardViolation::NotInteger(_) => "value_not_integer",
+            LeaderboardViolation::EmptyTeamName => "team_name_empty",
+            LeaderboardViolation::TeamNameTooLong(_) => "team_name_too_long",
+            LeaderboardViolation::TeamTooLarge(_) => "team_too_large",
+            LeaderboardViolation::MissingComponent(_) => "component_missing",
+            LeaderboardViolation::UnknownComponent(_) => "component_unknown",
+            LeaderboardViolation::ComponentNotFinite(_) => "value_not_finite",
+            LeaderboardViolation::ValuesNotAllowed => "values_not_allowed",
+            LeaderboardViolation::InvalidChallengeDate(_) => "challenge_date_invalid",
+            LeaderboardViolation::ChallengeClosed(_) => "challenge_closed",
+        }
+    }
+
+    /// Field of the entry that broke the rule
+    pub fn field(&self) -> &'static str {
+        match self {
+            LeaderboardViolation::MissingValue
+            | LeaderboardViolation::NotFinite
+            | LeaderboardViolation::NotInteger(_) => "value_num",
+            LeaderboardViolation::EmptyTeamName
+            | LeaderboardViolation::TeamNameTooLong(_)
+            | LeaderboardViolation::TeamTooLarge(_) => "team",
+            LeaderboardViolation::MissingComponent(_)
+            | LeaderboardViolation::UnknownComponent(_)
+            | LeaderboardViolation::ComponentNotFinite(_)
+            | LeaderboardViolation::ValuesNotAllowed => "values",
+            LeaderboardViolation::InvalidChallengeDate(_)
+            | LeaderboardViolation::ChallengeClosed(_) => "challenge_date",
+            _ => "value_name",
+        }
+    }
+}
+
+impl std::fmt::Display for LeaderboardViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeaderboardViolation::MissingValue => write!(
+                f,
+                "value_num is required, unless the value is ranked by several values"
+            ),
+            LeaderboardViolation::NotFinite => write!(f, "value_num must be a finite number"),
+            LeaderboardViolation::EmptyName => write!(f, "value_name must not be empty"),
+            LeaderboardViolation::NameTooLong(len) => write!(
+                f,
+                "value_name is {} characters long, but can be at most {}",
+                len, MAX_VALUE_NAME_LEN
+            ),
+            LeaderboardViolation::InvalidNameCharacter(c) => write!(
+                f,
+                "value_name can't contain {:?}, only letters, digits, spaces, '_', '-' and '.'",
+                c
+            ),
+            LeaderboardViolation::NotInteger(value) => write!(
+                f,
+                "value_num must be a whole number for this value, but is {}",
+                value
+            ),
+            LeaderboardViolation::EmptyTeamName => write!(f, "The team name must not be empty"),
+            LeaderboardViolation::TeamNameTooLong(len) => write!(
+                f,
+                "The team name is {} characters long, but can be at most {}",
+                len, MAX_TEAM_NAME_LEN
+            ),
+            LeaderboardViolation::TeamTooLarge(size) => write!(
+                f,
+                "The team has {} players, but can have at most {}",
+                size, MAX_TEAM_SIZE
+            ),
+            LeaderboardViolation::MissingComponent(name) => {
+                write!(f, "values is missing {:?}, which this value is ranked by", name)
+            }
+            LeaderboardViolation::UnknownComponent(name) => {
+                write!(f, "values has {:?}, which this value isn't ranked by", name)
+            }
+            LeaderboardViolation::ComponentNotFinite(name) => {
+                write!(f, "values.{} must be a finite number", name)
+            }
+            LeaderboardViolation::ValuesNotAllowed => write!(
+                f,
+                "values can only be used for a value ranked by several values, use value_num instead"
+            ),
+            LeaderboardViolation::InvalidChallengeDate(date) => write!(
+                f,
+                "challenge_date must be a date like 2026-10-19, but is {:?}",
+                date
+            ),
+            LeaderboardViolation::ChallengeClosed(date) => write!(
+                f,
+                "The daily challenge of {} is closed, entries can only be set in today's or yesterday's",
+                date
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LeaderboardViolation {}
+
+/// Checks a leaderboard value name on its own, such as when an admin configures a metric
+pub fn validate_value_name(value_name: &str) -> Result<(), LeaderboardViolation> {
+    if value_name.is_empty() {
+        return Err(LeaderboardViolation::EmptyName);
+    }
+    let len = value_name.chars().count();
+    if len > MAX_VALUE_NAME_LEN {
+        return Err(LeaderboardViolation::NameTooLong(len));
+    }
+    match value_name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.')))
+    {
+        Some(c) => Err(LeaderboardViolation::InvalidNameCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+/// Checks the team of a leaderboard entry. `player_slot` is the slot of the player posting the
+/// entry, who is a member even if the team's slots leave them out.
+pub fn validate_team(team: &LeaderboardTeam, player_slot: i16) -> Result<(), LeaderboardViolation> {
+    if team.name.trim().is_empty() {
+        return Err(LeaderboardViolation::EmptyTeamName);
+    }
+    let len = team.name.chars().count();
+    if len > MAX_TEAM_NAME_LEN {
+        return Err(LeaderboardViolation::TeamNameTooLong(len));
+    }
+    let size = team_slots(team, player_slot).len();
+    if size > MAX_TEAM_SIZE {
+        return Err(LeaderboardViolation::TeamTooLarge(size));
+    }
+    Ok(())
+}
+
+/// Player slots of every member of a team, sorted and without duplicates
+pub fn team_slots(team: &LeaderboardTeam, player_slot: i16) -> Vec<i16> {
+    let mut slots = team.player_slots.clone();
+    slots.push(player_slot);
+    slots.sort_unstable();
+    slots.dedup();
+    slots
+}
+
+/// The leaderboard rules of a game, loaded once per request
+#[derive(Debug, Clone, Default)]
+pub struct LeaderboardRules {
+    integer_metrics: HashSet<String>,
+    composite_metrics: HashMap<String, Vec<MetricComponent>>,
+}
+
+impl LeaderboardRules {
+    pub fn load(game_id: &str, db_path: &str) -> QueryResult<LeaderboardRules> {
+        let mut rules = LeaderboardRules::default();
+        for metric in db::get_leaderboard_metrics(game_id, db_path)? {
+            let components = metric.components();
+            if !components.is_empty() {
+                rules
+                    .composite_metrics
+                    .insert(metric.value_name.clone(), components);
+            }
+            if metric.integer_only {
+                rules.integer_metrics.insert(metric.value_name);
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Values a metric is ranked by, which is empty unless the metric is composite
+    pub fn components(&self, value_name: &str) -> &[MetricComponent] {
+        self.composite_metrics
+            .get(value_name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Picks the `value_num` and tie-breakers of an entry. An entry for a composite metric has
+    /// to give every component in `values`, in which case `value_num` is ignored. Any other entry
+    /// has to give `value_num`.
+    pub fn resolve(
+        &self,
+        value_name: &str,
+        value_num: Option<f64>,
+        values: Option<&BTreeMap<String, f64>>,
+    ) -> Result<(f64, Vec<f64>), LeaderboardViolation> {
+        let components = self.components(value_name);
+        let values = match values {
+            None if components.is_empty() => {
+                let value_num = value_num.ok_or(LeaderboardViolation::MissingValue)?;
+                return Ok((value_num, Vec::new()));
+            }
+            Some(_) if components.is_empty() => return Err(LeaderboardViolation::ValuesNotAllowed),
+            None => {
+                return Err(LeaderboardViolation::MissingComponent(
+                    components[0].name.clone(),
+                ))
+            }
+            Some(values) => values,
+        };
+        if let Some(unknown) = values
+            .keys()
+            .find(|name| !components.iter().any(|component| &component.name == *name))
+        {
+            return Err(LeaderboardViolation::UnknownComponent(unknown.clone()));
+        }
+        let mut ordered = Vec::with_capacity(components.len());
+        for component in components {
+            let value = *values
+                .get(&component.name)
+                .ok_or_else(|| LeaderboardViolation::MissingComponent(component.name.clone()))?;
+            if !value.is_finite() {
+                return Err(LeaderboardViolation::ComponentNotFinite(
+                    component.name.clone(),
+                ));
+            }
+            ordered.push(value);
+        }
+        let tie_breakers = ordered.split_off(1);
+        Ok((ordered[0], tie_breakers))
+    }
+
+    /// Checks an entry against every rule, returning the first one it breaks
+    pub fn check(&self, value_name: &str, value_num: f64) -> Result<(), LeaderboardViolation> {
+        validate_value_name(value_name)?;
+        if !value_num.is_finite() {
+            return Err(LeaderboardViolation::NotFinite);
+        }
+        if self.integer_metrics.contains(value_name) && value_num.fract() != 0.0 {
+            return Err(LeaderboardViolation::NotInteger(value_num));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_non_finite() {
+        let rules = LeaderboardRules::default();
+        for value_num in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(
+                rules.check("score", value_num),
+                Err(LeaderboardViolation::NotFinite)
+            );
+        }
+        assert_eq!(rules.check("score", -12.5), Ok(()));
+    }
+}
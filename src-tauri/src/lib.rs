@@ -1,3 +1,5 @@
 pub mod db;
 pub mod frontend_api;
 pub mod game_dev_api;
+pub mod logging;
+pub mod settings;
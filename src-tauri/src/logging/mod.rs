@@ -0,0 +1,251 @@
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::Level;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::settings::LoggingSettings;
+
+/// Log files are named `quackbox.<date>.log`
+pub const LOG_FILE_PREFIX: &str = "quackbox";
+pub const LOG_FILE_SUFFIX: &str = "log";
+
+/// Tauri managed state that keeps the log writer alive for as long as the app runs
+pub struct LogState {
+    pub dir: PathBuf,
+    _guard: WorkerGuard,
+}
+
+/// Sets up the global tracing subscriber.
+///
+/// Logs are written as JSON lines to a file in `log_dir` that rotates daily, keeping at most
+/// `settings.max_files` files. Logs are also printed to stdout for development. The
+/// `RUST_LOG` environment variable overrides the level from the settings.
+///
+/// # Errors
+/// * If the log directory can't be created
+/// * If the log level is not a valid filter
+/// * If a global subscriber has already been set
+pub fn init(log_dir: &Path, settings: &LoggingSettings) -> Result<LogState, Error> {
+    fs::create_dir_all(log_dir)?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(settings.max_files)
+        .build(log_dir)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&settings.level)?,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(false)
+                .with_writer(writer),
+        )
+        .with(fmt::layer().with_writer(std::io::stdout))
+        .try_init()?;
+
+    Ok(LogState {
+        dir: log_dir.to_path_buf(),
+        _guard: guard,
+    })
+}
+
+/// A single line of a log file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Fields of the event and of the span it happened in, such as `game_id` or `player_slot`
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The JSON layout written by the `tracing_subscriber` JSON formatter
+#[derive(Deserialize)]
+struct LogLine {
+    timestamp: String,
+    level: String,
+    target: String,
+    #[serde(default)]
+    fields: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    span: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<LogLine> for LogEntry {
+    fn from(mut line: LogLine) -> Self {
+        let message = match line.fields.remove("message") {
+            Some(serde_json::Value::String(message)) => message,
+            Some(message) => message.to_string(),
+            None => String::new(),
+        };
+        // span fields come first so that event fields with the same name win
+        let mut fields = line.span;
+        fields.remove("name");
+        fields.extend(line.fields);
+
+        LogEntry {
+            timestamp: line.timestamp,
+            level: line.level,
+            target: line.target,
+            message,
+            fields,
+        }
+    }
+}
+
+/// Returns up to `limit` of the most recent log entries, newest first.
+///
+/// If `min_level` is given, only entries at that level or more severe are returned, so
+/// `"warn"` returns warnings and errors. Lines that can't be parsed are skipped.
+///
+/// # Errors
+/// * If `min_level` is not a valid level
+/// * If the log directory or a log file can't be read
+pub fn read_recent_logs(
+    log_dir: &Path,
+    limit: usize,
+    min_level: Option<&str>,
+) -> Result<Vec<LogEntry>, Error> {
+    let min_level = min_level.map(Level::from_str).transpose()?;
+
+    let mut log_files: Vec<PathBuf> = fs::read_dir(log_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+                })
+        })
+        .collect();
+    // file names end with the date, so sorting by name sorts by date
+    log_files.sort();
+
+    let mut entries = Vec::new();
+    for log_file in log_files.iter().rev() {
+        let lines = BufReader::new(File::open(log_file)?)
+            .lines()
+            .collect::<Result<Vec<String>, _>>()?;
+
+        for line in lines.iter().rev() {
+            let Ok(line) = serde_json::from_str::<LogLine>(line) else {
+                continue;
+            };
+            let level_matches = match (min_level, Level::from_str(&line.level)) {
+                (Some(min_level), Ok(level)) => level <= min_level,
+                (Some(_), Err(_)) => false,
+                (None, _) => true,
+            };
+            if !level_matches {
+                continue;
+            }
+
+            entries.push(LogEntry::from(line));
+            if entries.len() >= limit {
+                return Ok(entries);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn log_line(timestamp: &str, level: &str, message: &str) -> String {
+        serde_json::json!({
+            "timestamp": timestamp,
+            "level": level,
+            "target": "quackbox_backend::game_dev_api::handlers",
+            "fields": { "message": message, "file_name": "save1" },
+            "span": { "name": "set_save_data", "game_id": "12", "player_slot": 1 }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn reads_recent_logs_newest_first() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("quackbox.2025-01-01.log"),
+            [
+                log_line("2025-01-01T10:00:00Z", "ERROR", "old error"),
+                log_line("2025-01-01T11:00:00Z", "INFO", "old info"),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("quackbox.2025-01-02.log"),
+            [
+                log_line("2025-01-02T10:00:00Z", "WARN", "new warning"),
+                "not json".to_string(),
+                log_line("2025-01-02T11:00:00Z", "DEBUG", "new debug"),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        fs::write(dir.path().join("other.txt"), "ignored").unwrap();
+
+        let entries = read_recent_logs(dir.path(), 10, None).expect("Failed to read logs");
+        let messages: Vec<&str> = entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["new debug", "new warning", "old info", "old error"]
+        );
+
+        let entry = &entries[0];
+        assert_eq!(entry.level, "DEBUG");
+        assert_eq!(entry.fields.get("game_id").unwrap(), "12");
+        assert_eq!(entry.fields.get("player_slot").unwrap(), 1);
+        assert_eq!(entry.fields.get("file_name").unwrap(), "save1");
+        assert!(entry.fields.get("name").is_none());
+
+        let entries = read_recent_logs(dir.path(), 2, None).expect("Failed to read logs");
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[test]
+    fn filters_logs_by_level() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("quackbox.2025-01-01.log"),
+            [
+                log_line("2025-01-01T10:00:00Z", "ERROR", "error"),
+                log_line("2025-01-01T11:00:00Z", "WARN", "warning"),
+                log_line("2025-01-01T12:00:00Z", "INFO", "info"),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let entries = read_recent_logs(dir.path(), 10, Some("warn")).expect("Failed to read logs");
+        let messages: Vec<&str> = entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["warning", "error"]);
+
+        assert!(read_recent_logs(dir.path(), 10, Some("loud")).is_err());
+    }
+}
@@ -2,23 +2,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use db::setup_db;
-use frontend_api::{get_game_info, get_leaderboard_data, play_game, AppState, GameSenderState};
+use frontend_api::{
+    get_daily_leaderboards, get_game_info, get_leaderboard_data, get_player_stats, get_recent_logs,
+    get_save_revisions, get_settings, get_storage_usage, play_game, restore_save_revision,
+    set_game_enabled, set_leaderboard_components, set_leaderboard_metric, set_save_revision_limit,
+    set_storage_quotas, uninstall_game, update_settings, AppState, GameSenderState,
+};
 use game_dev_api::handlers::GameState;
 use game_dev_api::handlers::GameStateShared;
 use game_dev_api::setup_game_dev_api;
 use quackbox_backend::db::create_default_guest;
+use settings::{Settings, SettingsState};
 use tauri::Manager;
 use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
 use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tokio::sync::Notify;
 use tokio::sync::RwLock;
+use tracing::warn;
 
 use std::sync::Arc;
 
 mod db;
 mod frontend_api;
 mod game_dev_api;
+mod logging;
+mod settings;
 
 fn main() {
     tauri::Builder::default()
@@ -29,15 +38,23 @@ fn main() {
             Some(vec![""]),
         ))
         .setup(|app| {
-            let db_path = app
-                .path()
-                .app_data_dir()
-                .unwrap()
-                .join("local")
-                .with_extension("db")
+            let app_data_dir = app.path().app_data_dir()?;
+            let (settings, settings_error) = Settings::load_or_recover(&app_data_dir);
+            app.manage(logging::init(
+                &app_data_dir.join("logs"),
+                &settings.logging,
+            )?);
+            if let Some(e) = settings_error {
+                warn!("Failed to load or save the settings: {:#}", e);
+            }
+            let db_path = settings
+                .database_path(&app_data_dir)
                 .into_os_string()
                 .into_string()
                 .unwrap();
+            let dev_api_address = settings.dev_api.address();
+            let autostart = settings.autostart;
+            app.manage(SettingsState::new(settings, app_data_dir));
             app.manage(Mutex::new(AppState::new(db_path.clone())));
             // tauri::async_runtime::spawn(db::test_db());
 
@@ -56,18 +73,34 @@ fn main() {
             tauri::async_runtime::spawn({
                 setup_db(db_path.as_str());
                 create_default_guest(db_path.as_str());
-                setup_game_dev_api(db_path, game_state_shared)
+                setup_game_dev_api(db_path, game_state_shared, dev_api_address)
             });
-            if cfg!(feature = "autostart") {
-                // Only enable autolaunch on raspberry pi
+            // autostart defaults to on only for raspberry pi builds
+            if autostart {
                 app.autolaunch().enable()?;
+            } else if app.autolaunch().is_enabled()? {
+                app.autolaunch().disable()?;
             }
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_game_info,
             play_game,
-            get_leaderboard_data
+            get_leaderboard_data,
+            uninstall_game,
+            set_game_enabled,
+            get_save_revisions,
+            restore_save_revision,
+            set_save_revision_limit,
+            get_storage_usage,
+            get_player_stats,
+            get_daily_leaderboards,
+            set_storage_quotas,
+            set_leaderboard_metric,
+            set_leaderboard_components,
+            get_settings,
+            update_settings,
+            get_recent_logs
         ])
         .on_page_load(|window, _| {
             window.show().expect("Failed to show window");
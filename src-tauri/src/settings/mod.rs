@@ -0,0 +1,432 @@
+use anyhow::{bail, Context, Error};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+use tokio::sync::Mutex;
+use tracing_subscriber::EnvFilter;
+
+/// Name of the settings file inside the app data directory
+pub const SETTINGS_FILE_NAME: &str = "settings.toml";
+
+/// Name a settings file that can't be loaded is moved to, so it can be fixed by hand
+pub const BAD_SETTINGS_FILE_NAME: &str = "settings.toml.bad";
+
+/// Version of the settings file layout. Bump this and add an entry to [`MIGRATIONS`] whenever a
+/// change to [`Settings`] can't be handled by serde defaults alone.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades a settings table from one schema version to the next. The migration at index `i`
+/// upgrades a table from version `i + 1` to version `i + 2`.
+type Migration = fn(&mut toml::Table) -> Result<(), Error>;
+const MIGRATIONS: &[Migration] = &[];
+
+/// Console wide settings, stored as TOML in the app data directory.
+///
+/// Missing fields are filled in with their defaults, so older settings files keep working
+/// when new settings are added.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub schema_version: u32,
+    /// Whether the launcher starts when the console boots. Defaults to true on the QuackBox.
+    pub autostart: bool,
+    /// Seconds without input before the launcher goes idle. 0 disables the timeout.
+    pub idle_timeout_secs: u64,
+    /// Master volume from 0 to 100
+    pub volume: u8,
+    pub dev_api: DevApiSettings,
+    pub database: DatabaseSettings,
+    pub logging: LoggingSettings,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DevApiSettings {
+    pub host: IpAddr,
+    pub port: u16,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct DatabaseSettings {
+    /// Path of the SQLite database. Defaults to `local.db` in the app data directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LoggingSettings {
+    /// Minimum level or filter directives, such as `info` or `info,quackbox_backend::db=debug`
+    pub level: String,
+    /// Number of daily log files to keep
+    pub max_files: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            autostart: cfg!(feature = "autostart"),
+            idle_timeout_secs: 300,
+            volume: 80,
+            dev_api: DevApiSettings::default(),
+            database: DatabaseSettings::default(),
+            logging: LoggingSettings::default(),
+        }
+    }
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        LoggingSettings {
+            level: "info".to_string(),
+            max_files: 7,
+        }
+    }
+}
+
+impl Default for DevApiSettings {
+    fn default() -> Self {
+        DevApiSettings {
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 6174,
+        }
+    }
+}
+
+impl DevApiSettings {
+    /// The address the game dev API listens on
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    /// The base url games use to reach the game dev API. If the API listens on every
+    /// interface, games are pointed at the loopback address.
+    pub fn api_url(&self) -> String {
+        let host = match self.host {
+            IpAddr::V4(host) if host.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(host) if host.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            host => host,
+        };
+        // the Rust client SDK speaks v1
+        format!(
+            "http://{}{}",
+            SocketAddr::new(host, self.port),
+            crate::game_dev_api::version_prefix(crate::game_dev_api::V1)
+        )
+    }
+}
+
+impl Settings {
+    /// Loads the settings file from the given directory, writing the default settings to it if
+    /// it doesn't exist yet.
+    ///
+    /// # Errors
+    /// * If the settings file can't be read, parsed or written
+    /// * If the settings file was written by a newer version of the launcher
+    /// * If the settings are invalid
+    pub fn load_or_create(dir: &Path) -> Result<Settings, Error> {
+        let path = dir.join(SETTINGS_FILE_NAME);
+        if !path.exists() {
+            let settings = Settings::default();
+            settings.save(dir)?;
+            return Ok(settings);
+        }
+
+        // write the settings back so migrations and newly added settings end up in the file
+        let settings = Settings::load(&path)?;
+        settings.save(dir)?;
+        Ok(settings)
+    }
+
+    /// Loads the settings like [`Settings::load_or_create`], but never fails, so a console
+    /// starting on its own always comes up.
+    ///
+    /// A settings file that can't be loaded is moved to [`BAD_SETTINGS_FILE_NAME`] and the
+    /// default settings are used instead. The error is returned along with the settings, to be
+    /// logged once logging is set up.
+    pub fn load_or_recover(dir: &Path) -> (Settings, Option<Error>) {
+        let path = dir.join(SETTINGS_FILE_NAME);
+        let loaded = if path.exists() {
+            Settings::load(&path)
+        } else {
+            Ok(Settings::default())
+        };
+        let (settings, error) = match loaded {
+            Ok(settings) => (settings, None),
+            Err(e) => {
+                let bad_path = dir.join(BAD_SETTINGS_FILE_NAME);
+                let e = match fs::rename(&path, &bad_path) {
+                    Ok(()) => e.context(format!("Moved the settings to {}", bad_path.display())),
+                    Err(rename_error) => e.context(format!(
+                        "Failed to move the settings to {}: {}",
+                        bad_path.display(),
+                        rename_error
+                    )),
+                };
+                (Settings::default(), Some(e))
+            }
+        };
+        // a settings file that can't be written is only reported, the settings still work
+        match settings.save(dir) {
+            Ok(()) => (settings, error),
+            Err(e) => (settings, Some(error.unwrap_or(e))),
+        }
+    }
+
+    /// Reads and validates a settings file, migrating it to the current schema version
+    pub fn load(path: &Path) -> Result<Settings, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings at {}", path.display()))?;
+        let mut table: toml::Table = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse settings at {}", path.display()))?;
+
+        let version = match table.get("schema_version") {
+            Some(version) => version
+                .as_integer()
+                .and_then(|version| u32::try_from(version).ok())
+                .context("schema_version must be a positive integer")?,
+            None => CURRENT_SCHEMA_VERSION,
+        };
+        if version == 0 {
+            bail!("schema_version must be a positive integer");
+        }
+        if version > CURRENT_SCHEMA_VERSION {
+            bail!(
+                "Settings schema version {} is newer than the supported version {}",
+                version,
+                CURRENT_SCHEMA_VERSION
+            );
+        }
+        for migration in &MIGRATIONS[(version - 1) as usize..] {
+            migration(&mut table)?;
+        }
+        table.insert(
+            "schema_version".to_string(),
+            toml::Value::Integer(CURRENT_SCHEMA_VERSION.into()),
+        );
+
+        let settings: Settings = table.try_into()?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Validates and writes the settings to the settings file in the given directory
+    pub fn save(&self, dir: &Path) -> Result<(), Error> {
+        self.validate()?;
+        fs::create_dir_all(dir)?;
+
+        // write to a temporary file first so a crash can't leave a half written settings file
+        let path = dir.join(SETTINGS_FILE_NAME);
+        let temp_path = path.with_extension("toml.tmp");
+        fs::write(&temp_path, toml::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    /// # Errors
+    /// * If the dev API port is 0
+    /// * If the volume is greater than 100
+    /// * If the idle timeout is longer than a day
+    /// * If the database path is empty
+    /// * If the log level is not a valid filter or no log files are kept
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.schema_version != CURRENT_SCHEMA_VERSION {
+            bail!(
+                "schema_version must be {}, found {}",
+                CURRENT_SCHEMA_VERSION,
+                self.schema_version
+            );
+        }
+        if self.dev_api.port == 0 {
+            bail!("dev_api.port must be between 1 and 65535");
+        }
+        if self.volume > 100 {
+            bail!("volume must be between 0 and 100, found {}", self.volume);
+        }
+        if self.idle_timeout_secs > 24 * 60 * 60 {
+            bail!(
+                "idle_timeout_secs must be at most a day, found {}",
+                self.idle_timeout_secs
+            );
+        }
+        if self
+            .database
+            .path
+            .as_ref()
+            .is_some_and(|path| path.as_os_str().is_empty())
+        {
+            bail!("database.path must not be empty");
+        }
+        if let Err(e) = EnvFilter::try_new(&self.logging.level) {
+            bail!("logging.level is not a valid filter: {}", e);
+        }
+        if self.logging.max_files == 0 {
+            bail!("logging.max_files must be at least 1");
+        }
+        Ok(())
+    }
+
+    /// Returns the database path, falling back to `local.db` in the app data directory
+    pub fn database_path(&self, app_data_dir: &Path) -> PathBuf {
+        match &self.database.path {
+            Some(path) => app_data_dir.join(path),
+            None => app_data_dir.join("local").with_extension("db"),
+        }
+    }
+
+    /// Returns a copy of these settings with the given JSON object merged in. Fields missing
+    /// from `changes` keep their current values.
+    pub fn merged_with(&self, changes: serde_json::Value) -> Result<Settings, Error> {
+        let mut settings = serde_json::to_value(self)?;
+        merge_json(&mut settings, changes);
+        let settings: Settings = serde_json::from_value(settings)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+/// Recursively merges `changes` into `target`. Objects are merged key by key, and any other
+/// value replaces the value in `target`.
+fn merge_json(target: &mut serde_json::Value, changes: serde_json::Value) {
+    match (target, changes) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(changes)) => {
+            for (key, value) in changes {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, changes) => *target = changes,
+    }
+}
+
+/// Tauri managed state holding the current settings and the directory they are saved in
+pub struct SettingsState {
+    pub settings: Mutex<Settings>,
+    pub dir: PathBuf,
+}
+
+impl SettingsState {
+    pub fn new(settings: Settings, dir: PathBuf) -> Self {
+        SettingsState {
+            settings: Mutex::new(settings),
+            dir,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn creates_default_settings() {
+        let dir = tempdir().unwrap();
+
+        let settings = Settings::load_or_create(dir.path()).expect("Failed to create settings");
+        assert_eq!(settings, Settings::default());
+        assert!(dir.path().join(SETTINGS_FILE_NAME).exists());
+
+        let reloaded = Settings::load_or_create(dir.path()).expect("Failed to load settings");
+        assert_eq!(reloaded, settings);
+    }
+
+    #[test]
+    fn fills_in_missing_settings() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(SETTINGS_FILE_NAME),
+            "volume = 20\n[dev_api]\nport = 7000\n",
+        )
+        .unwrap();
+
+        let settings = Settings::load_or_create(dir.path()).expect("Failed to load settings");
+        assert_eq!(settings.volume, 20);
+        assert_eq!(settings.dev_api.port, 7000);
+        assert_eq!(settings.dev_api.host, DevApiSettings::default().host);
+        assert_eq!(settings.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(
+            settings.database_path(dir.path()),
+            dir.path().join("local.db")
+        );
+        assert_eq!(settings.dev_api.api_url(), "http://127.0.0.1:7000/api/v1");
+    }
+
+    #[test]
+    fn recovers_from_corrupt_settings() {
+        let dir = tempdir().unwrap();
+        let corrupt = "volume = 20\n[dev_api\nport = ";
+        fs::write(dir.path().join(SETTINGS_FILE_NAME), corrupt).unwrap();
+
+        let (settings, error) = Settings::load_or_recover(dir.path());
+        assert_eq!(settings, Settings::default());
+        assert!(error.is_some());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(BAD_SETTINGS_FILE_NAME)).unwrap(),
+            corrupt
+        );
+        let reloaded = Settings::load_or_create(dir.path()).expect("Failed to load settings");
+        assert_eq!(reloaded, Settings::default());
+
+        let (settings, error) = Settings::load_or_recover(dir.path());
+        assert_eq!(settings, Settings::default());
+        assert!(error.is_none());
+    }
+
+    #[test]
+    fn rejects_newer_schema_version() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join(SETTINGS_FILE_NAME),
+            format!("schema_version = {}\n", CURRENT_SCHEMA_VERSION + 1),
+        )
+        .unwrap();
+
+        assert!(Settings::load_or_create(dir.path()).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join(SETTINGS_FILE_NAME), "volume = 101\n").unwrap();
+        assert!(Settings::load_or_create(dir.path()).is_err());
+
+        let settings = Settings {
+            dev_api: DevApiSettings {
+                port: 0,
+                ..DevApiSettings::default()
+            },
+            ..Settings::default()
+        };
+        assert!(settings.save(dir.path()).is_err());
+    }
+
+    #[test]
+    fn merges_partial_updates() {
+        let settings = Settings::default();
+
+        let updated = settings
+            .merged_with(serde_json::json!({ "dev_api": { "port": 7000 }, "volume": 10 }))
+            .expect("Failed to merge settings");
+        assert_eq!(updated.dev_api.port, 7000);
+        assert_eq!(updated.dev_api.host, settings.dev_api.host);
+        assert_eq!(updated.volume, 10);
+        assert_eq!(updated.idle_timeout_secs, settings.idle_timeout_secs);
+
+        assert!(settings
+            .merged_with(serde_json::json!({ "volume": 200 }))
+            .is_err());
+        assert!(settings
+            .merged_with(serde_json::json!({ "logging": { "level": "info,=[" } }))
+            .is_err());
+    }
+}
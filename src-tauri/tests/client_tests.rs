@@ -0,0 +1,327 @@
+use quackbox_backend::db::test_context::{setup_initial_data, TestContext};
+use quackbox_client::{
+    types::{
+        LeaderboardBatch, LeaderboardEntryStatus, LeaderboardGetParams, LeaderboardPost,
+        LeaderboardStatsParams, SaveDataBatch, SaveDataGetParams, SaveDataOperation, SaveDataPatch,
+        SaveDataPatchParams, SaveDataPost, StatIncrement, StatsGetParams, StorageGetParams,
+    },
+    Error,
+};
+
+/// Returns the base url of the game dev API served by the test context
+fn api_url(test_context: &TestContext) -> String {
+    let address = test_context
+        .server
+        .server_address()
+        .expect("Test server should use the http transport");
+    format!("{}api/v1", address)
+}
+
+async fn set_current_game(test_context: &TestContext, game_id: Option<u64>) {
+    test_context
+        .current_game_tx
+        .send(game_id)
+        .expect("No subscriber to the current game sender");
+    test_context.notifier.notified().await;
+}
+
+#[tokio::test]
+async fn async_client_reads_and_writes_data() {
+    let test_context = TestContext::new_with_http("async_client_reads_and_writes_data").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(1)).await;
+
+    let client = quackbox_client::Client::new(&api_url(&test_context)).unwrap();
+
+    let entry = LeaderboardPost {
+        value_name: String::from("score"),
+        value_num: Some(250.0),
+        player_slot: 1,
+        ..Default::default()
+    };
+    let posted = client
+        .set_leaderboard(&entry)
+        .await
+        .expect("Failed to post leaderboard entry");
+    assert_eq!(posted, entry);
+
+    let entries = client
+        .get_leaderboard(&LeaderboardGetParams {
+            value_name: Some(String::from("score")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .expect("Failed to get leaderboard");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].value_num, 250.0);
+    assert_eq!(entries[0].player_slot, 1);
+
+    let stats = client
+        .get_leaderboard_stats(&LeaderboardStatsParams {
+            value_name: String::from("score"),
+            value: Some(300.0),
+            ..LeaderboardStatsParams::default()
+        })
+        .await
+        .expect("Failed to get leaderboard stats");
+    assert_eq!(stats.count, 1);
+    assert_eq!(stats.value_percentile, Some(100.0));
+
+    let challenge = client
+        .get_daily_challenge()
+        .await
+        .expect("Failed to get daily challenge");
+    client
+        .set_leaderboard(&LeaderboardPost {
+            challenge_date: Some(challenge.date.clone()),
+            ..entry.clone()
+        })
+        .await
+        .expect("Failed to post daily challenge entry");
+    let daily_entries = client
+        .get_leaderboard(&LeaderboardGetParams {
+            challenge_date: Some(challenge.date.clone()),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .expect("Failed to get daily leaderboard");
+    assert_eq!(daily_entries.len(), 1);
+    assert_eq!(daily_entries[0].challenge_date, Some(challenge.date));
+
+    let results = client
+        .set_leaderboard_batch(&LeaderboardBatch {
+            entries: vec![
+                entry.clone(),
+                LeaderboardPost {
+                    player_slot: 2,
+                    ..entry.clone()
+                },
+            ],
+        })
+        .await
+        .expect("Failed to post leaderboard batch");
+    assert_eq!(results[0].status, LeaderboardEntryStatus::Deduplicated);
+    assert_eq!(results[1].status, LeaderboardEntryStatus::Stored);
+
+    client
+        .set_leaderboard(&LeaderboardPost {
+            value_name: String::from("lap"),
+            value_num: Some(30.0),
+            player_slot: 1,
+            details: Some(serde_json::json!({ "kart": "mallard" })),
+            // base64 of [1, 2, 3]
+            attachment: Some(String::from("AQID")),
+            ..Default::default()
+        })
+        .await
+        .expect("Failed to post leaderboard entry");
+    let laps = client
+        .get_leaderboard(&LeaderboardGetParams {
+            value_name: Some(String::from("lap")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .expect("Failed to get leaderboard");
+    assert!(laps[0].has_attachment);
+    let ghost = client
+        .get_leaderboard_attachment(laps[0].id)
+        .await
+        .expect("Failed to get leaderboard attachment");
+    assert_eq!(ghost, vec![1, 2, 3]);
+    let result = client.get_leaderboard_attachment(laps[0].id + 100).await;
+    assert!(matches!(result, Err(Error::Api { status: 404, .. })));
+
+    let seed = serde_json::json!(1234);
+    client
+        .create_global("seed", &seed)
+        .await
+        .expect("Failed to create global");
+    let result = client.create_global("seed", &serde_json::json!(5678)).await;
+    assert!(matches!(result, Err(Error::RevisionMismatch)));
+    let global = client
+        .get_global("seed")
+        .await
+        .expect("Failed to get global");
+    assert_eq!(global.value, seed);
+    client
+        .set_global_if("seed", &serde_json::json!(5678), global.revision)
+        .await
+        .expect("Failed to swap global");
+    let ducks = client
+        .increment_global("ducks", 3.0)
+        .await
+        .expect("Failed to increment global");
+    assert_eq!(ducks.value, serde_json::json!(3));
+
+    let jumps = client
+        .increment_stat(&StatIncrement {
+            player_slot: 1,
+            name: String::from("jumps"),
+            by: 12,
+        })
+        .await
+        .expect("Failed to increment stat");
+    assert_eq!(jumps.value, 12);
+    let stats = client
+        .get_stats(&StatsGetParams::default())
+        .await
+        .expect("Failed to get stats");
+    assert_eq!(stats, vec![jumps]);
+
+    let save = SaveDataPost {
+        file_name: String::from("save1"),
+        data: serde_json::json!({ "level": 3, "items": ["sword"] }),
+        player_slot: 2,
+    };
+    let posted = client
+        .set_save_data(&save)
+        .await
+        .expect("Failed to post save data");
+    assert_eq!(posted, save);
+
+    let saves = client
+        .get_save_data(&SaveDataGetParams {
+            file_name: Some(String::from("save1")),
+            player_slot: Some(2),
+            ..SaveDataGetParams::default()
+        })
+        .await
+        .expect("Failed to get save data");
+    assert_eq!(saves.len(), 1);
+    assert_eq!(saves[0].data, save.data);
+
+    let revision = saves[0].revision;
+    client
+        .set_save_data_if(&save, revision)
+        .await
+        .expect("Failed to post save data");
+    let result = client.set_save_data_if(&save, revision).await;
+    assert!(matches!(result, Err(Error::RevisionMismatch)));
+
+    let patched = client
+        .patch_save_data(
+            &SaveDataPatchParams {
+                file_name: String::from("save1"),
+                player_slot: 2,
+            },
+            &SaveDataPatch::JsonPatch(serde_json::json!([
+                { "op": "replace", "path": "/level", "value": 4 }
+            ])),
+        )
+        .await
+        .expect("Failed to patch save data");
+    assert_eq!(
+        patched.data,
+        serde_json::json!({ "level": 4, "items": ["sword"] })
+    );
+    assert_eq!(patched.revision, revision + 2);
+
+    let results = client
+        .write_save_data_batch(&SaveDataBatch {
+            operations: vec![
+                SaveDataOperation::Delete {
+                    file_name: String::from("save1"),
+                    player_slot: 2,
+                    if_revision: Some(patched.revision),
+                },
+                SaveDataOperation::Set {
+                    file_name: String::from("save2"),
+                    player_slot: 2,
+                    data: serde_json::json!({ "level": 1 }),
+                    if_revision: None,
+                },
+            ],
+        })
+        .await
+        .expect("Failed to write save data batch");
+    assert_eq!(results[0].revision, None);
+    assert_eq!(results[1].revision, Some(1));
+}
+
+#[tokio::test]
+async fn async_client_errors() {
+    let test_context = TestContext::new_with_http("async_client_errors").await;
+    setup_initial_data(test_context.get_db_path()).await;
+
+    let client = quackbox_client::Client::new(&api_url(&test_context)).unwrap();
+
+    // no game has been started yet
+    let result = client
+        .get_leaderboard(&LeaderboardGetParams::default())
+        .await;
+    assert!(matches!(result, Err(Error::NoGameRunning)));
+
+    set_current_game(&test_context, Some(0)).await;
+
+    let result = client
+        .get_leaderboard(&LeaderboardGetParams {
+            count: Some(101),
+            ..LeaderboardGetParams::default()
+        })
+        .await;
+    assert!(matches!(result, Err(Error::Api { status: 413, .. })));
+
+    let result = client
+        .get_save_data(&SaveDataGetParams {
+            file_name: Some(String::from("save1")),
+            regex: Some(String::from("save.*")),
+            player_slot: None,
+        })
+        .await;
+    assert!(matches!(result, Err(Error::Api { status: 400, .. })));
+
+    let result = client
+        .set_save_data(&SaveDataPost {
+            file_name: String::from("save1"),
+            data: serde_json::json!("x".repeat(1536 * 1024)),
+            player_slot: 1,
+        })
+        .await;
+    match result {
+        Err(Error::QuotaExceeded(error)) => assert_eq!(error.code, "save_too_large"),
+        other => panic!("Expected a quota error, got {:?}", other),
+    }
+    let usage = client
+        .get_storage(&StorageGetParams::default())
+        .await
+        .expect("Failed to get storage usage");
+    assert_eq!(usage.save_count, 0);
+
+    assert!(matches!(
+        quackbox_client::Client::new("not a url"),
+        Err(Error::InvalidUrl(_))
+    ));
+}
+
+#[tokio::test]
+async fn blocking_client_reads_and_writes_data() {
+    let test_context = TestContext::new_with_http("blocking_client_reads_and_writes_data").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    let api_url = api_url(&test_context);
+    // the blocking client can't run on the async runtime the test server uses
+    let entries = tokio::task::spawn_blocking(move || {
+        let client = quackbox_client::blocking::Client::new(&api_url).unwrap();
+        client
+            .set_leaderboard(&LeaderboardPost {
+                value_name: String::from("Score"),
+                value_num: Some(150.0),
+                player_slot: 2,
+                ..Default::default()
+            })
+            .expect("Failed to post leaderboard entry");
+        client
+            .get_leaderboard(&LeaderboardGetParams {
+                value_name: Some(String::from("Score")),
+                ascending: Some(false),
+                ..LeaderboardGetParams::default()
+            })
+            .expect("Failed to get leaderboard")
+    })
+    .await
+    .unwrap();
+
+    let values: Vec<f64> = entries.iter().map(|entry| entry.value_num).collect();
+    assert_eq!(values, vec![150.0, 125.0, 100.0]);
+}
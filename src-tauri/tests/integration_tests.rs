@@ -1,16 +1,29 @@
 use quackbox_backend::{
     db::{
         create_user, get_user,
+        models::{MetricComponent, StorageQuotas},
+        set_leaderboard_components, set_leaderboard_metric, set_storage_quotas,
         test_context::{setup_initial_data, TestContext},
     },
     game_dev_api::{
-        create_router,
+        create_router, daily,
         handlers::{
-            GameStateShared, LeaderboardGetParams, LeaderboardPost, SaveDataGetParams, SaveDataPost,
+            DailyChallenge, GameStateShared, GlobalEntry, GlobalIncrement, GlobalPost,
+            LeaderboardBatch, LeaderboardBatchResult, LeaderboardComponent, LeaderboardEntry,
+            LeaderboardEntryStatus, LeaderboardGetParams, LeaderboardPost, LeaderboardStats,
+            LeaderboardStatsParams, LeaderboardTeam, QuotaError, SaveDataBatch,
+            SaveDataBatchResult, SaveDataEntry, SaveDataGetParams, SaveDataOperation,
+            SaveDataPatchParams, SaveDataPost, StatEntry, StatIncrement, StatsGetParams,
+            StorageGetParams, StorageUsage, ValidationError, JSON_PATCH_CONTENT_TYPE,
+            MAX_ATTACHMENT_BYTES, MAX_GLOBAL_BYTES, MAX_STAT_DELTA, MERGE_PATCH_CONTENT_TYPE,
         },
+        openapi::ApiDoc,
     },
 };
 
+use base64::{engine::general_purpose::STANDARD, Engine};
+use utoipa::OpenApi;
+
 extern crate diesel_migrations;
 
 const SAVE_DATA_PATH: &str = "/api/v1/save-data";
@@ -54,8 +67,9 @@ async fn read_and_write_leaderboard_data() {
         .post(leaderboard_path)
         .json(&LeaderboardPost {
             value_name: value_name.clone(),
-            value_num: value_num,
+            value_num: Some(value_num),
             player_slot: player,
+            ..Default::default()
         })
         .await;
 
@@ -63,7 +77,7 @@ async fn read_and_write_leaderboard_data() {
     let post_response_entry: LeaderboardPost = post_response.json::<LeaderboardPost>();
 
     assert_eq!(post_response_entry.value_name, value_name);
-    assert_eq!(post_response_entry.value_num, value_num);
+    assert_eq!(post_response_entry.value_num, Some(value_num));
 
     let get_response: axum_test::TestResponse = test_context
         .server
@@ -74,6 +88,7 @@ async fn read_and_write_leaderboard_data() {
             ascending: None,
             value_name: Some(value_name.clone()),
             offset: None,
+            challenge_date: None,
         })
         .await;
 
@@ -84,7 +99,7 @@ async fn read_and_write_leaderboard_data() {
         .expect("No entries in leaderboard get response");
 
     assert_eq!(get_response_entry.value_name, value_name);
-    assert_eq!(get_response_entry.value_num, value_num);
+    assert_eq!(get_response_entry.value_num, Some(value_num));
 }
 
 #[tokio::test]
@@ -212,6 +227,26 @@ async fn get_save_data_error() {
         .await;
 
     get_invalid_params_error_response.assert_status_bad_request();
+
+    // a save that can't be read is the console's fault, not the game's
+    {
+        use diesel::RunQueryDsl;
+        diesel::sql_query("UPDATE saves SET codec = 'unknown'")
+            .execute(&mut quackbox_backend::db::establish_connection(
+                test_context.get_db_path(),
+            ))
+            .expect("Failed to corrupt the save");
+    }
+    test_context
+        .server
+        .get(save_data_path)
+        .add_query_params(SaveDataGetParams {
+            file_name: Some(file_name),
+            regex: None,
+            player_slot: Some(player_slot),
+        })
+        .await
+        .assert_status_internal_server_error();
 }
 
 #[tokio::test]
@@ -237,8 +272,9 @@ async fn get_leaderboard_data_error() {
         .post(leaderboard_path)
         .json(&LeaderboardPost {
             value_name: value_name.clone(),
-            value_num: value_num,
+            value_num: Some(value_num),
             player_slot: player,
+            ..Default::default()
         })
         .await;
 
@@ -247,7 +283,7 @@ async fn get_leaderboard_data_error() {
     let post_response_entry: LeaderboardPost = post_response.json::<LeaderboardPost>();
 
     assert_eq!(post_response_entry.value_name, value_name);
-    assert_eq!(post_response_entry.value_num, value_num);
+    assert_eq!(post_response_entry.value_num, Some(value_num));
 
     let get_response: axum_test::TestResponse = test_context
         .server
@@ -258,6 +294,7 @@ async fn get_leaderboard_data_error() {
             ascending: None,
             value_name: Some(value_name.clone()),
             offset: None,
+            challenge_date: None,
         })
         .await;
 
@@ -338,3 +375,1326 @@ async fn upsert_save_data() {
     assert_eq!(updated_entry.data, updated_data);
     assert_ne!(updated_entry.data, data)
 }
+
+#[tokio::test]
+async fn requests_without_running_game() {
+    let test_context = TestContext::new("requests_without_running_game").await;
+
+    setup_initial_data(test_context.get_db_path()).await;
+
+    // no game has been started, so there is no game to store data for
+    let post_response: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&SaveDataPost {
+            file_name: String::from("test data"),
+            data: serde_json::json!({"level": 1}),
+            player_slot: 1,
+        })
+        .await;
+
+    post_response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let get_response: axum_test::TestResponse =
+        test_context.server.get("/api/v1/leaderboard").await;
+
+    get_response.assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn serves_openapi_spec() {
+    let test_context = TestContext::new("serves_openapi_spec").await;
+
+    // the spec is available even when no game is running
+    let response: axum_test::TestResponse = test_context.server.get("/api/v1/openapi.json").await;
+
+    response.assert_status_ok();
+    response.assert_json(&serde_json::to_value(ApiDoc::openapi()).unwrap());
+}
+
+#[tokio::test]
+async fn storage_quotas() {
+    let test_context = TestContext::new("storage_quotas").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    let quotas = StorageQuotas {
+        max_save_bytes: 64,
+        max_saves_per_user: 2,
+        max_game_bytes: 100,
+    };
+    set_storage_quotas("0", &quotas, test_context.get_db_path()).expect("Failed to set quotas");
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let save = |player_slot: i16, file_name: &str, data: serde_json::Value| SaveDataPost {
+        file_name: String::from(file_name),
+        data,
+        player_slot,
+    };
+
+    let too_large: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&save(1, "save1", serde_json::json!("x".repeat(100))))
+        .await;
+    too_large.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+    let error = too_large.json::<QuotaError>();
+    assert_eq!(error.code, "save_too_large");
+    assert_eq!(error.limit, 64);
+    assert_eq!(error.requested, 102);
+
+    // each save is 30 bytes
+    for file_name in ["save1", "save2"] {
+        test_context
+            .server
+            .post(SAVE_DATA_PATH)
+            .json(&save(1, file_name, serde_json::json!("x".repeat(28))))
+            .await
+            .assert_status_ok();
+    }
+    let too_many: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&save(1, "save3", serde_json::json!("x".repeat(28))))
+        .await;
+    too_many.assert_status(axum::http::StatusCode::INSUFFICIENT_STORAGE);
+    assert_eq!(too_many.json::<QuotaError>().code, "too_many_saves");
+
+    // replacing a save doesn't count against the number of saves
+    test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&save(1, "save2", serde_json::json!("x".repeat(28))))
+        .await
+        .assert_status_ok();
+
+    test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&save(2, "other1", serde_json::json!("x".repeat(28))))
+        .await
+        .assert_status_ok();
+    let full: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&save(2, "other2", serde_json::json!("x".repeat(28))))
+        .await;
+    full.assert_status(axum::http::StatusCode::INSUFFICIENT_STORAGE);
+    let error = full.json::<QuotaError>();
+    assert_eq!(error.code, "storage_full");
+    assert_eq!(error.requested, 120);
+
+    let usage = test_context
+        .server
+        .get("/api/v1/storage")
+        .await
+        .json::<StorageUsage>();
+    assert_eq!(
+        usage,
+        StorageUsage {
+            used_bytes: 90,
+            max_bytes: 100,
+            save_count: 3,
+            max_saves_per_player: 2,
+            max_save_bytes: 64,
+        }
+    );
+    let usage = test_context
+        .server
+        .get("/api/v1/storage")
+        .add_query_params(StorageGetParams {
+            player_slot: Some(2),
+        })
+        .await
+        .json::<StorageUsage>();
+    assert_eq!(usage.save_count, 1);
+}
+
+#[tokio::test]
+async fn conditional_save_data() {
+    let test_context = TestContext::new("conditional_save_data").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let save = |level: i32| SaveDataPost {
+        file_name: String::from("save1"),
+        data: serde_json::json!({ "level": level }),
+        player_slot: 1,
+    };
+
+    // If-Match: * only replaces existing saves
+    let missing: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .add_header(axum::http::header::IF_MATCH, "*")
+        .json(&save(1))
+        .await;
+    missing.assert_status(axum::http::StatusCode::PRECONDITION_FAILED);
+
+    let created: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&save(1))
+        .await;
+    created.assert_status_ok();
+    assert_eq!(created.header(axum::http::header::ETAG), "\"1\"");
+
+    let read: axum_test::TestResponse = test_context
+        .server
+        .get(SAVE_DATA_PATH)
+        .add_query_params(SaveDataGetParams {
+            file_name: Some(String::from("save1")),
+            player_slot: Some(1),
+            regex: None,
+        })
+        .await;
+    let etag = read.header(axum::http::header::ETAG);
+    assert_eq!(etag, "\"1\"");
+    assert_eq!(read.json::<Vec<SaveDataEntry>>()[0].revision, 1);
+
+    let written: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .add_header(axum::http::header::IF_MATCH, etag.clone())
+        .json(&save(2))
+        .await;
+    written.assert_status_ok();
+    assert_eq!(written.header(axum::http::header::ETAG), "\"2\"");
+
+    // a second writer with the same ETag lost the race
+    let stale: axum_test::TestResponse = test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .add_header(axum::http::header::IF_MATCH, etag)
+        .json(&save(3))
+        .await;
+    stale.assert_status(axum::http::StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stale.header(axum::http::header::ETAG), "\"2\"");
+
+    // weak tags never match, but any of several tags can
+    for (if_match, status) in [
+        ("W/\"2\"", axum::http::StatusCode::PRECONDITION_FAILED),
+        ("\"1\", \"2\"", axum::http::StatusCode::OK),
+    ] {
+        test_context
+            .server
+            .post(SAVE_DATA_PATH)
+            .add_header(axum::http::header::IF_MATCH, if_match)
+            .json(&save(3))
+            .await
+            .assert_status(status);
+    }
+}
+
+#[tokio::test]
+async fn patch_save_data() {
+    let test_context = TestContext::new("patch_save_data").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let params = SaveDataPatchParams {
+        file_name: String::from("save1"),
+        player_slot: 1,
+    };
+    let patch = |body: serde_json::Value, content_type: &str| {
+        test_context
+            .server
+            .patch(SAVE_DATA_PATH)
+            .add_query_params(params.clone())
+            .json(&body)
+            .content_type(content_type)
+    };
+
+    patch(serde_json::json!({ "level": 2 }), MERGE_PATCH_CONTENT_TYPE)
+        .await
+        .assert_status_not_found();
+
+    test_context
+        .server
+        .post(SAVE_DATA_PATH)
+        .json(&SaveDataPost {
+            file_name: String::from("save1"),
+            data: serde_json::json!({ "level": 1, "items": ["sword"], "gold": 10 }),
+            player_slot: 1,
+        })
+        .await
+        .assert_status_ok();
+
+    let merged: axum_test::TestResponse = patch(
+        serde_json::json!({ "level": 2, "gold": null }),
+        MERGE_PATCH_CONTENT_TYPE,
+    )
+    .await;
+    merged.assert_status_ok();
+    assert_eq!(merged.header(axum::http::header::ETAG), "\"2\"");
+    let entry = merged.json::<SaveDataEntry>();
+    assert_eq!(
+        entry.data,
+        serde_json::json!({ "level": 2, "items": ["sword"] })
+    );
+    assert_eq!(entry.revision, 2);
+
+    let patched = patch(
+        serde_json::json!([{ "op": "add", "path": "/items/-", "value": "shield" }]),
+        JSON_PATCH_CONTENT_TYPE,
+    )
+    .await
+    .json::<SaveDataEntry>();
+    assert_eq!(
+        patched.data,
+        serde_json::json!({ "level": 2, "items": ["sword", "shield"] })
+    );
+
+    let failed: axum_test::TestResponse = patch(
+        serde_json::json!([{ "op": "remove", "path": "/gold" }]),
+        JSON_PATCH_CONTENT_TYPE,
+    )
+    .await;
+    failed.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    patch(serde_json::json!({ "op": "add" }), JSON_PATCH_CONTENT_TYPE)
+        .await
+        .assert_status_bad_request();
+    patch(serde_json::json!({ "level": 3 }), "application/json")
+        .await
+        .assert_status(axum::http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let saves = test_context
+        .server
+        .get(SAVE_DATA_PATH)
+        .add_query_params(SaveDataGetParams {
+            file_name: Some(String::from("save1")),
+            player_slot: Some(1),
+            regex: None,
+        })
+        .await
+        .json::<Vec<SaveDataEntry>>();
+    assert_eq!(saves[0].data, patched.data);
+    assert_eq!(saves[0].revision, 3);
+}
+
+#[tokio::test]
+async fn save_data_batch() {
+    let test_context = TestContext::new("save_data_batch").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let batch: axum_test::TestResponse = test_context
+        .server
+        .post("/api/v1/save-data/batch")
+        .json(&SaveDataBatch {
+            operations: vec![
+                SaveDataOperation::Set {
+                    file_name: String::from("profile"),
+                    player_slot: 1,
+                    data: serde_json::json!({ "name": "duck" }),
+                    if_revision: None,
+                },
+                SaveDataOperation::Set {
+                    file_name: String::from("profile"),
+                    player_slot: 2,
+                    data: serde_json::json!({ "name": "goose" }),
+                    if_revision: None,
+                },
+                SaveDataOperation::Merge {
+                    file_name: String::from("profile"),
+                    player_slot: 1,
+                    patch: serde_json::json!({ "level": 2 }),
+                    if_revision: Some(1),
+                },
+            ],
+        })
+        .await;
+    batch.assert_status_ok();
+    assert_eq!(
+        batch.json::<Vec<SaveDataBatchResult>>(),
+        vec![
+            SaveDataBatchResult {
+                file_name: String::from("profile"),
+                player_slot: 1,
+                revision: Some(1),
+            },
+            SaveDataBatchResult {
+                file_name: String::from("profile"),
+                player_slot: 2,
+                revision: Some(1),
+            },
+            SaveDataBatchResult {
+                file_name: String::from("profile"),
+                player_slot: 1,
+                revision: Some(2),
+            },
+        ]
+    );
+
+    // the second operation is stale, so the first one isn't applied either
+    let failed: axum_test::TestResponse = test_context
+        .server
+        .post("/api/v1/save-data/batch")
+        .json(&SaveDataBatch {
+            operations: vec![
+                SaveDataOperation::Delete {
+                    file_name: String::from("profile"),
+                    player_slot: 2,
+                    if_revision: None,
+                },
+                SaveDataOperation::Set {
+                    file_name: String::from("profile"),
+                    player_slot: 1,
+                    data: serde_json::json!({}),
+                    if_revision: Some(1),
+                },
+            ],
+        })
+        .await;
+    failed.assert_status(axum::http::StatusCode::PRECONDITION_FAILED);
+    assert!(failed.text().starts_with("Operation 1 on profile failed"));
+
+    let saves = test_context
+        .server
+        .get(SAVE_DATA_PATH)
+        .await
+        .json::<Vec<SaveDataEntry>>();
+    assert_eq!(saves.len(), 2);
+    assert!(saves
+        .iter()
+        .any(|save| save.data == serde_json::json!({ "name": "duck", "level": 2 })));
+
+    let too_many = SaveDataBatch {
+        operations: vec![
+            SaveDataOperation::Delete {
+                file_name: String::from("profile"),
+                player_slot: 1,
+                if_revision: None,
+            };
+            101
+        ],
+    };
+    test_context
+        .server
+        .post("/api/v1/save-data/batch")
+        .json(&too_many)
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn leaderboard_batch() {
+    let test_context = TestContext::new("leaderboard_batch").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let entry = |player_slot: i16, value_name: &str, value_num: f64| LeaderboardPost {
+        value_name: String::from(value_name),
+        value_num: Some(value_num),
+        player_slot,
+        ..Default::default()
+    };
+    let entries = vec![
+        entry(1, "score", 100.0),
+        entry(2, "score", 200.0),
+        entry(1, "time", 92.5),
+        entry(2, "score", 200.0),
+        // only players 1 and 2 exist
+        entry(3, "score", 300.0),
+    ];
+
+    let response: axum_test::TestResponse = test_context
+        .server
+        .post("/api/v1/leaderboard/batch")
+        .json(&LeaderboardBatch { entries })
+        .await;
+    response.assert_status_ok();
+    let results = response.json::<Vec<LeaderboardBatchResult>>();
+    let statuses: Vec<LeaderboardEntryStatus> = results.iter().map(|r| r.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            LeaderboardEntryStatus::Stored,
+            LeaderboardEntryStatus::Stored,
+            LeaderboardEntryStatus::Stored,
+            LeaderboardEntryStatus::Deduplicated,
+            LeaderboardEntryStatus::Rejected,
+        ]
+    );
+    assert_eq!(results[3].reason, None);
+    assert_eq!(results[4].player_slot, 3);
+    assert_eq!(results[4].reason.as_deref(), Some("Player doesn't exist"));
+
+    let scores = test_context
+        .server
+        .get("/api/v1/leaderboard")
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("score")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Vec<LeaderboardEntry>>();
+    assert_eq!(scores.len(), 2);
+    assert_eq!(scores[0].player_slot, 2);
+}
+
+#[tokio::test]
+async fn leaderboard_validation() {
+    let test_context = TestContext::new("leaderboard_validation").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+    set_leaderboard_metric("0", "coins", true, test_context.get_db_path())
+        .expect("Failed to set leaderboard metric");
+
+    let entry = |value_name: &str, value_num: f64| LeaderboardPost {
+        value_name: String::from(value_name),
+        value_num: Some(value_num),
+        player_slot: 1,
+        ..Default::default()
+    };
+    let cases = [
+        (entry("", 1.0), "value_name_empty", "value_name"),
+        (
+            entry(&"a".repeat(65), 1.0),
+            "value_name_too_long",
+            "value_name",
+        ),
+        (entry("score!", 1.0), "value_name_invalid", "value_name"),
+        (entry("coins", 1.5), "value_not_integer", "value_num"),
+    ];
+    for (post, code, field) in cases {
+        let response = test_context
+            .server
+            .post("/api/v1/leaderboard")
+            .json(&post)
+            .await;
+        response.assert_status_unprocessable_entity();
+        let error = response.json::<ValidationError>();
+        assert_eq!(error.code, code);
+        assert_eq!(error.field, field);
+        assert!(!error.message.is_empty());
+    }
+
+    // JSON has no way to write NaN or infinity, so out of range numbers are the closest a game
+    // can get and are refused while parsing the body
+    let response = test_context
+        .server
+        .post("/api/v1/leaderboard")
+        .content_type("application/json")
+        .text(r#"{"value_name": "score", "value_num": 1e400, "player_slot": 1}"#)
+        .await;
+    assert!(response.status_code().is_client_error());
+
+    for post in [
+        entry(&"a".repeat(64), 1.0),
+        entry("Best lap-time_2.0", 1.5),
+        entry("coins", 3.0),
+    ] {
+        test_context
+            .server
+            .post("/api/v1/leaderboard")
+            .json(&post)
+            .await
+            .assert_status_ok();
+    }
+
+    let response: axum_test::TestResponse = test_context
+        .server
+        .post("/api/v1/leaderboard/batch")
+        .json(&LeaderboardBatch {
+            entries: vec![entry("coins", 2.5), entry("score", 10.0), entry("", 1.0)],
+        })
+        .await;
+    response.assert_status_ok();
+    let results = response.json::<Vec<LeaderboardBatchResult>>();
+    let statuses: Vec<LeaderboardEntryStatus> = results.iter().map(|r| r.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            LeaderboardEntryStatus::Rejected,
+            LeaderboardEntryStatus::Stored,
+            LeaderboardEntryStatus::Rejected,
+        ]
+    );
+    assert!(results[0]
+        .reason
+        .as_deref()
+        .unwrap()
+        .contains("whole number"));
+    assert!(results[2].reason.as_deref().unwrap().contains("empty"));
+
+    let coins = test_context
+        .server
+        .get("/api/v1/leaderboard")
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("coins")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Vec<LeaderboardEntry>>();
+    assert_eq!(coins.len(), 1);
+    assert_eq!(coins[0].value_num, 3.0);
+}
+
+#[tokio::test]
+async fn leaderboard_entries_need_a_value() {
+    let test_context = TestContext::new("leaderboard_entries_need_a_value").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let response = test_context
+        .server
+        .post("/api/v1/leaderboard")
+        .json(&serde_json::json!({ "value_name": "score", "player_slot": 1 }))
+        .await;
+    response.assert_status_unprocessable_entity();
+    let error = response.json::<ValidationError>();
+    assert_eq!(error.code, "value_missing");
+    assert_eq!(error.field, "value_num");
+
+    let response = test_context
+        .server
+        .post("/api/v1/leaderboard/batch")
+        .json(&LeaderboardBatch {
+            entries: vec![LeaderboardPost {
+                value_name: String::from("score"),
+                player_slot: 1,
+                ..Default::default()
+            }],
+        })
+        .await;
+    response.assert_status_ok();
+    let results = response.json::<Vec<LeaderboardBatchResult>>();
+    assert_eq!(results[0].status, LeaderboardEntryStatus::Rejected);
+
+    let scores = test_context
+        .server
+        .get("/api/v1/leaderboard")
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("score")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Vec<LeaderboardEntry>>();
+    assert!(scores.is_empty());
+}
+
+#[tokio::test]
+async fn leaderboard_attachments() {
+    let test_context = TestContext::new("leaderboard_attachments").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let ghost: Vec<u8> = (0..=255).collect();
+    let post = LeaderboardPost {
+        value_name: String::from("lap"),
+        value_num: Some(42.5),
+        player_slot: 1,
+        details: Some(serde_json::json!({ "character": "duck", "level": 3 })),
+        attachment: Some(STANDARD.encode(&ghost)),
+        ..Default::default()
+    };
+    test_context
+        .server
+        .post("/api/v1/leaderboard")
+        .json(&post)
+        .await
+        .assert_status_ok();
+    test_context
+        .server
+        .post("/api/v1/leaderboard")
+        .json(&LeaderboardPost {
+            value_name: String::from("lap"),
+            value_num: Some(50.0),
+            player_slot: 2,
+            ..LeaderboardPost::default()
+        })
+        .await
+        .assert_status_ok();
+
+    let entries = test_context
+        .server
+        .get("/api/v1/leaderboard")
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("lap")),
+            ascending: Some(true),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Vec<LeaderboardEntry>>();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].details, post.details);
+    assert!(entries[0].has_attachment);
+    assert_eq!(entries[1].details, None);
+    assert!(!entries[1].has_attachment);
+
+    let response = test_context
+        .server
+        .get(&format!("/api/v1/leaderboard/{}/attachment", entries[0].id))
+        .await;
+    response.assert_status_ok();
+    response.assert_header("content-type", "application/octet-stream");
+    assert_eq!(response.as_bytes().to_vec(), ghost);
+    test_context
+        .server
+        .get(&format!("/api/v1/leaderboard/{}/attachment", entries[1].id))
+        .await
+        .assert_status_not_found();
+
+    // attachments belong to the game that posted them
+    test_context.current_game_tx.send(Some(1)).unwrap();
+    test_context.notifier.notified().await;
+    test_context
+        .server
+        .get(&format!("/api/v1/leaderboard/{}/attachment", entries[0].id))
+        .await
+        .assert_status_not_found();
+
+    let invalid = test_context
+        .server
+        .post("/api/v1/leaderboard")
+        .json(&LeaderboardPost {
+            attachment: Some(String::from("not base64!")),
+            ..post.clone()
+        })
+        .await;
+    invalid.assert_status_bad_request();
+    let too_large = test_context
+        .server
+        .post("/api/v1/leaderboard")
+        .json(&LeaderboardPost {
+            attachment: Some(STANDARD.encode(vec![0; MAX_ATTACHMENT_BYTES + 1])),
+            ..post
+        })
+        .await;
+    too_large.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn team_leaderboard_entries() {
+    let test_context = TestContext::new("team_leaderboard_entries").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let team = LeaderboardTeam {
+        name: String::from("Ducklings"),
+        // the posting player is a member even when left out
+        player_slots: vec![2],
+    };
+    let post = LeaderboardPost {
+        value_name: String::from("coop"),
+        value_num: Some(1200.0),
+        player_slot: 1,
+        team: Some(team.clone()),
+        ..Default::default()
+    };
+    let response = test_context
+        .server
+        .post("/api/v1/leaderboard")
+        .json(&post)
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<LeaderboardPost>(), post);
+
+    // the entry is listed once, for every member of the team
+    for player_slot in [1, 2] {
+        let entries = test_context
+            .server
+            .get("/api/v1/leaderboard")
+            .add_query_params(LeaderboardGetParams {
+                value_name: Some(String::from("coop")),
+                player_slot: Some(player_slot),
+                ..LeaderboardGetParams::default()
+            })
+            .await
+            .json::<Vec<LeaderboardEntry>>();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].player_slot, 1);
+        assert_eq!(
+            entries[0].team,
+            Some(LeaderboardTeam {
+                name: String::from("Ducklings"),
+                player_slots: vec![1, 2],
+            })
+        );
+    }
+
+    let invalid_teams = [
+        (
+            LeaderboardTeam {
+                name: String::from(" "),
+                player_slots: vec![2],
+            },
+            "team_name_empty",
+        ),
+        (
+            LeaderboardTeam {
+                name: "a".repeat(65),
+                player_slots: vec![2],
+            },
+            "team_name_too_long",
+        ),
+        (
+            LeaderboardTeam {
+                name: String::from("Everyone"),
+                player_slots: (1..=9).collect(),
+            },
+            "team_too_large",
+        ),
+    ];
+    for (team, code) in invalid_teams {
+        let response = test_context
+            .server
+            .post("/api/v1/leaderboard")
+            .json(&LeaderboardPost {
+                team: Some(team),
+                ..post.clone()
+            })
+            .await;
+        response.assert_status_unprocessable_entity();
+        let error = response.json::<ValidationError>();
+        assert_eq!(error.code, code);
+        assert_eq!(error.field, "team");
+    }
+
+    // every member has to be a player
+    let response: axum_test::TestResponse = test_context
+        .server
+        .post("/api/v1/leaderboard/batch")
+        .json(&LeaderboardBatch {
+            entries: vec![LeaderboardPost {
+                value_num: Some(900.0),
+                team: Some(LeaderboardTeam {
+                    name: String::from("Strangers"),
+                    player_slots: vec![3],
+                }),
+                ..post
+            }],
+        })
+        .await;
+    response.assert_status_ok();
+    let results = response.json::<Vec<LeaderboardBatchResult>>();
+    assert_eq!(results[0].status, LeaderboardEntryStatus::Rejected);
+}
+
+#[tokio::test]
+async fn game_globals() {
+    let test_context = TestContext::new("game_globals").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    test_context
+        .server
+        .get("/api/v1/globals/seed")
+        .await
+        .assert_status_not_found();
+
+    // only the first session sets the shared seed
+    let seed = GlobalPost {
+        value: serde_json::json!({ "world": 1234 }),
+    };
+    let created = test_context
+        .server
+        .post("/api/v1/globals/seed")
+        .add_header(axum::http::header::IF_NONE_MATCH, "*")
+        .json(&seed)
+        .await;
+    created.assert_status_ok();
+    assert_eq!(created.header(axum::http::header::ETAG), "\"1\"");
+    let taken = test_context
+        .server
+        .post("/api/v1/globals/seed")
+        .add_header(axum::http::header::IF_NONE_MATCH, "*")
+        .json(&GlobalPost {
+            value: serde_json::json!({ "world": 5678 }),
+        })
+        .await;
+    taken.assert_status(axum::http::StatusCode::PRECONDITION_FAILED);
+    assert_eq!(taken.header(axum::http::header::ETAG), "\"1\"");
+
+    let global = test_context
+        .server
+        .get("/api/v1/globals/seed")
+        .await
+        .json::<GlobalEntry>();
+    assert_eq!((global.key.as_str(), global.revision), ("seed", 1));
+    assert_eq!(global.value, seed.value);
+
+    // a swap from a stale revision is refused
+    for status in [
+        axum::http::StatusCode::OK,
+        axum::http::StatusCode::PRECONDITION_FAILED,
+    ] {
+        test_context
+            .server
+            .post("/api/v1/globals/seed")
+            .add_header(axum::http::header::IF_MATCH, "\"1\"")
+            .json(&seed)
+            .await
+            .assert_status(status);
+    }
+
+    for by in [5.0, 3.0] {
+        test_context
+            .server
+            .post("/api/v1/globals/ducks-rescued/increment")
+            .json(&GlobalIncrement { by })
+            .await
+            .assert_status_ok();
+    }
+    let ducks = test_context
+        .server
+        .get("/api/v1/globals/ducks-rescued")
+        .await
+        .json::<GlobalEntry>();
+    assert_eq!(ducks.value, serde_json::json!(8));
+    test_context
+        .server
+        .post("/api/v1/globals/seed/increment")
+        .json(&GlobalIncrement { by: 1.0 })
+        .await
+        .assert_status_unprocessable_entity();
+
+    test_context
+        .server
+        .get("/api/v1/globals/not%20valid")
+        .await
+        .assert_status_bad_request();
+    test_context
+        .server
+        .post("/api/v1/globals/big")
+        .json(&GlobalPost {
+            value: serde_json::json!("a".repeat(MAX_GLOBAL_BYTES)),
+        })
+        .await
+        .assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn daily_challenge() {
+    let test_context = TestContext::new("daily_challenge").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let challenge = test_context
+        .server
+        .get("/api/v1/daily-challenge")
+        .await
+        .json::<DailyChallenge>();
+    let today = daily::today();
+    assert_eq!(challenge.date, daily::format_date(today));
+    let again = test_context
+        .server
+        .get("/api/v1/daily-challenge")
+        .await
+        .json::<DailyChallenge>();
+    assert_eq!(again, challenge);
+
+    let yesterday = daily::format_date(today.pred_opt().unwrap());
+    for (date, value_num) in [(&challenge.date, 500.0), (&yesterday, 300.0)] {
+        test_context
+            .server
+            .post("/api/v1/leaderboard")
+            .json(&LeaderboardPost {
+                value_name: String::from("Score"),
+                value_num: Some(value_num),
+                player_slot: 1,
+                challenge_date: Some(date.clone()),
+                ..Default::default()
+            })
+            .await
+            .assert_status_ok();
+    }
+    let closed_date = daily::format_date(today.pred_opt().unwrap().pred_opt().unwrap());
+    for (date, code) in [
+        (closed_date.as_str(), "challenge_closed"),
+        ("today", "challenge_date_invalid"),
+    ] {
+        let response = test_context
+            .server
+            .post("/api/v1/leaderboard")
+            .json(&LeaderboardPost {
+                value_name: String::from("Score"),
+                value_num: Some(100.0),
+                player_slot: 1,
+                challenge_date: Some(String::from(date)),
+                ..Default::default()
+            })
+            .await;
+        response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        let error = response.json::<ValidationError>();
+        assert_eq!(error.code, code);
+        assert_eq!(error.field, "challenge_date");
+    }
+
+    let daily_entries = test_context
+        .server
+        .get("/api/v1/leaderboard")
+        .add_query_params(LeaderboardGetParams {
+            challenge_date: Some(challenge.date.clone()),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Vec<LeaderboardEntry>>();
+    assert_eq!(daily_entries.len(), 1);
+    assert_eq!(daily_entries[0].value_num, 500.0);
+    assert_eq!(
+        daily_entries[0].challenge_date,
+        Some(challenge.date.clone())
+    );
+    // entries of a daily challenge are kept off the game's leaderboard
+    let scores = test_context
+        .server
+        .get("/api/v1/leaderboard")
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("Score")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Vec<LeaderboardEntry>>();
+    let values: Vec<f64> = scores.iter().map(|entry| entry.value_num).collect();
+    assert_eq!(values, vec![125.0, 100.0]);
+
+    let stats = test_context
+        .server
+        .get("/api/v1/leaderboard/stats")
+        .add_query_params(LeaderboardStatsParams {
+            value_name: String::from("Score"),
+            challenge_date: Some(yesterday),
+            ..LeaderboardStatsParams::default()
+        })
+        .await
+        .json::<LeaderboardStats>();
+    assert_eq!(stats.count, 1);
+    assert_eq!(stats.max, Some(300.0));
+
+    test_context.current_game_tx.send(Some(1)).unwrap();
+    test_context.notifier.notified().await;
+    let other_game = test_context
+        .server
+        .get("/api/v1/daily-challenge")
+        .await
+        .json::<DailyChallenge>();
+    assert_eq!(other_game.date, challenge.date);
+    assert_ne!(other_game.seed, challenge.seed);
+}
+
+#[tokio::test]
+async fn player_stats() {
+    let test_context = TestContext::new("player_stats").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let increments = [
+        (1, "jumps", 3),
+        (1, "jumps", 2),
+        (2, "jumps", 1),
+        (1, "coins", 10),
+    ];
+    for (player_slot, name, by) in increments {
+        test_context
+            .server
+            .post("/api/v1/stats/increment")
+            .json(&StatIncrement {
+                player_slot,
+                name: String::from(name),
+                by,
+            })
+            .await
+            .assert_status_ok();
+    }
+    let lost_coins = test_context
+        .server
+        .post("/api/v1/stats/increment")
+        .json(&StatIncrement {
+            player_slot: 1,
+            name: String::from("coins"),
+            by: -4,
+        })
+        .await
+        .json::<StatEntry>();
+    assert_eq!(lost_coins.value, 6);
+
+    let stats = test_context
+        .server
+        .get("/api/v1/stats")
+        .add_query_params(StatsGetParams {
+            player_slot: Some(1),
+            ..StatsGetParams::default()
+        })
+        .await
+        .json::<Vec<StatEntry>>();
+    let values: Vec<(&str, i64)> = stats
+        .iter()
+        .map(|stat| (stat.name.as_str(), stat.value))
+        .collect();
+    assert_eq!(values, vec![("coins", 6), ("jumps", 5)]);
+    let jumps = test_context
+        .server
+        .get("/api/v1/stats")
+        .add_query_params(StatsGetParams {
+            name: Some(String::from("jumps")),
+            ..StatsGetParams::default()
+        })
+        .await
+        .json::<Vec<StatEntry>>();
+    let slots: Vec<i16> = jumps.iter().map(|stat| stat.player_slot).collect();
+    assert_eq!(slots, vec![1, 2]);
+
+    let invalid_increments = [
+        ("", 1),
+        ("jumps!", 1),
+        ("jumps", MAX_STAT_DELTA + 1),
+        ("jumps", -MAX_STAT_DELTA - 1),
+        ("jumps", i64::MIN),
+    ];
+    for (name, by) in invalid_increments {
+        test_context
+            .server
+            .post("/api/v1/stats/increment")
+            .json(&StatIncrement {
+                player_slot: 1,
+                name: String::from(name),
+                by,
+            })
+            .await
+            .assert_status_bad_request();
+    }
+}
+
+#[tokio::test]
+async fn leaderboard_stats() {
+    let test_context = TestContext::new("leaderboard_stats").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let entries: Vec<LeaderboardPost> = [10.0, 20.0, 30.0, 40.0, 100.0]
+        .into_iter()
+        .map(|value_num| LeaderboardPost {
+            value_name: String::from("points"),
+            value_num: Some(value_num),
+            player_slot: 1,
+            ..Default::default()
+        })
+        .collect();
+    test_context
+        .server
+        .post("/api/v1/leaderboard/batch")
+        .json(&LeaderboardBatch { entries })
+        .await
+        .assert_status_ok();
+
+    let stats = test_context
+        .server
+        .get("/api/v1/leaderboard/stats")
+        .add_query_params(LeaderboardStatsParams {
+            value_name: String::from("points"),
+            percentiles: Some(String::from("0,75")),
+            buckets: Some(2),
+            value: Some(35.0),
+            ..LeaderboardStatsParams::default()
+        })
+        .await
+        .json::<LeaderboardStats>();
+    assert_eq!(stats.count, 5);
+    assert_eq!((stats.min, stats.max), (Some(10.0), Some(100.0)));
+    assert_eq!(stats.mean, Some(40.0));
+    assert_eq!(stats.median, Some(30.0));
+    let percentiles: Vec<(f64, f64)> = stats
+        .percentiles
+        .iter()
+        .map(|percentile| (percentile.percentile, percentile.value))
+        .collect();
+    assert_eq!(percentiles, vec![(0.0, 10.0), (75.0, 40.0)]);
+    let counts: Vec<i64> = stats.histogram.iter().map(|bucket| bucket.count).collect();
+    assert_eq!(counts, vec![4, 1]);
+    // 35 beats 10, 20 and 30
+    assert_eq!(stats.value_percentile, Some(60.0));
+
+    // a lower time is better
+    let stats = test_context
+        .server
+        .get("/api/v1/leaderboard/stats")
+        .add_query_params(LeaderboardStatsParams {
+            value_name: String::from("points"),
+            value: Some(35.0),
+            ascending: Some(true),
+            ..LeaderboardStatsParams::default()
+        })
+        .await
+        .json::<LeaderboardStats>();
+    assert_eq!(stats.value_percentile, Some(40.0));
+    assert_eq!(stats.percentiles.len(), 5);
+    assert_eq!(stats.histogram.len(), 10);
+
+    let empty = test_context
+        .server
+        .get("/api/v1/leaderboard/stats")
+        .add_query_params(LeaderboardStatsParams {
+            value_name: String::from("laps"),
+            value: Some(35.0),
+            ..LeaderboardStatsParams::default()
+        })
+        .await
+        .json::<LeaderboardStats>();
+    assert_eq!(empty.count, 0);
+    assert_eq!(empty.median, None);
+    assert!(empty.histogram.is_empty());
+
+    let invalid_params = [
+        LeaderboardStatsParams {
+            value_name: String::from("points"),
+            percentiles: Some(String::from("50,150")),
+            ..LeaderboardStatsParams::default()
+        },
+        LeaderboardStatsParams {
+            value_name: String::from("points"),
+            buckets: Some(101),
+            ..LeaderboardStatsParams::default()
+        },
+    ];
+    for params in invalid_params {
+        test_context
+            .server
+            .get("/api/v1/leaderboard/stats")
+            .add_query_params(params)
+            .await
+            .assert_status_bad_request();
+    }
+}
+
+#[tokio::test]
+async fn composite_leaderboard_entries() {
+    let test_context = TestContext::new("composite_leaderboard_entries").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    test_context.current_game_tx.send(Some(0)).unwrap();
+    test_context.notifier.notified().await;
+
+    let components = vec![
+        MetricComponent {
+            name: String::from("score"),
+            ascending: false,
+        },
+        MetricComponent {
+            name: String::from("time"),
+            ascending: true,
+        },
+    ];
+    set_leaderboard_components("0", "run", &components, test_context.get_db_path()).unwrap();
+
+    let run = |player_slot: i16, score: f64, time: f64| LeaderboardPost {
+        value_name: String::from("run"),
+        player_slot,
+        values: Some([(String::from("score"), score), (String::from("time"), time)].into()),
+        ..Default::default()
+    };
+    for post in [
+        run(1, 100.0, 90.0),
+        run(2, 100.0, 80.0),
+        run(1, 120.0, 99.0),
+    ] {
+        test_context
+            .server
+            .post("/api/v1/leaderboard")
+            .json(&post)
+            .await
+            .assert_status_ok();
+    }
+
+    // ties on the score are broken by the fastest time
+    let entries = test_context
+        .server
+        .get("/api/v1/leaderboard")
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("run")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Vec<LeaderboardEntry>>();
+    let times: Vec<f64> = entries
+        .iter()
+        .map(|entry| entry.components.as_ref().unwrap()[1].value)
+        .collect();
+    assert_eq!(times, vec![99.0, 80.0, 90.0]);
+    assert_eq!(entries[0].value_num, 120.0);
+    assert_eq!(
+        entries[1].components,
+        Some(vec![
+            LeaderboardComponent {
+                name: String::from("score"),
+                value: 100.0,
+            },
+            LeaderboardComponent {
+                name: String::from("time"),
+                value: 80.0,
+            },
+        ])
+    );
+
+    let mut missing = run(1, 100.0, 0.0);
+    missing.values.as_mut().unwrap().remove("time");
+    let mut unknown = run(1, 100.0, 0.0);
+    unknown
+        .values
+        .as_mut()
+        .unwrap()
+        .insert(String::from("coins"), 3.0);
+    let not_composite = LeaderboardPost {
+        value_name: String::from("score"),
+        ..run(1, 100.0, 0.0)
+    };
+    let invalid_posts = [
+        (missing, "component_missing"),
+        (unknown, "component_unknown"),
+        (not_composite, "values_not_allowed"),
+    ];
+    for (post, code) in invalid_posts {
+        let response = test_context
+            .server
+            .post("/api/v1/leaderboard")
+            .json(&post)
+            .await;
+        response.assert_status_unprocessable_entity();
+        let error = response.json::<ValidationError>();
+        assert_eq!(error.code, code);
+        assert_eq!(error.field, "values");
+    }
+
+    // a composite metric needs its values
+    let response = test_context
+        .server
+        .post("/api/v1/leaderboard/batch")
+        .json(&LeaderboardBatch {
+            entries: vec![LeaderboardPost {
+                value_name: String::from("run"),
+                value_num: Some(100.0),
+                player_slot: 1,
+                ..Default::default()
+            }],
+        })
+        .await;
+    response.assert_status_ok();
+    let results = response.json::<Vec<LeaderboardBatchResult>>();
+    assert_eq!(results[0].status, LeaderboardEntryStatus::Rejected);
+
+    // the direction of a composite metric is that of its first component
+    let stats = test_context
+        .server
+        .get("/api/v1/leaderboard/stats")
+        .add_query_params(LeaderboardStatsParams {
+            value_name: String::from("run"),
+            value: Some(130.0),
+            ascending: Some(true),
+            ..LeaderboardStatsParams::default()
+        })
+        .await
+        .json::<LeaderboardStats>();
+    assert_eq!(stats.value_percentile, Some(100.0));
+}
@@ -0,0 +1,665 @@
+use axum::http::StatusCode;
+use quackbox_backend::{
+    db::{
+        models::MetricComponent,
+        set_leaderboard_components,
+        test_context::{setup_initial_data, TestContext},
+    },
+    game_dev_api::{
+        handlers,
+        v2::types::{
+            Envelope, LeaderboardComponent, LeaderboardEntry, LeaderboardGetParams,
+            LeaderboardPost, SaveDataEntry, SaveDataGetParams, SaveDataPost, SaveRestorePost,
+            SaveRevisionEntry, SaveRevisionsGetParams,
+        },
+    },
+};
+
+const V1_LEADERBOARD_PATH: &str = "/api/v1/leaderboard";
+const V2_LEADERBOARD_PATH: &str = "/api/v2/leaderboard";
+const V2_SAVE_DATA_PATH: &str = "/api/v2/save-data";
+const V2_SAVE_REVISIONS_PATH: &str = "/api/v2/save-data/revisions";
+const V2_SAVE_RESTORE_PATH: &str = "/api/v2/save-data/restore";
+
+async fn set_current_game(test_context: &TestContext, game_id: Option<u64>) {
+    test_context
+        .current_game_tx
+        .send(game_id)
+        .expect("No subscriber to the current game sender");
+    test_context.notifier.notified().await;
+}
+
+#[tokio::test]
+async fn read_and_write_leaderboard_data() {
+    let test_context = TestContext::new("v2_read_and_write_leaderboard_data").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(1)).await;
+
+    let entry = LeaderboardPost {
+        user_id: String::from("2"),
+        value_name: String::from("score"),
+        value_num: Some(100.0),
+        values: None,
+    };
+    let post_response = test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(&entry)
+        .await;
+
+    post_response.assert_status_ok();
+    let envelope = post_response.json::<Envelope<LeaderboardPost>>();
+    assert_eq!(envelope.data, Some(entry));
+    assert!(envelope.errors.is_empty());
+
+    let get_response = test_context
+        .server
+        .get(V2_LEADERBOARD_PATH)
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("score")),
+            limit: Some(1),
+            ..LeaderboardGetParams::default()
+        })
+        .await;
+
+    get_response.assert_status_ok();
+    let envelope = get_response.json::<Envelope<Vec<LeaderboardEntry>>>();
+    let entries = envelope.data.expect("No data in leaderboard get response");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].user_id, "2");
+    assert_eq!(entries[0].value_num, 100.0);
+
+    let pagination = envelope
+        .pagination
+        .expect("No pagination in leaderboard get response");
+    assert_eq!(pagination.limit, 1);
+    assert_eq!(pagination.total, 1);
+    assert_eq!(pagination.next_cursor, None);
+}
+
+#[tokio::test]
+async fn read_and_write_save_data() {
+    let test_context = TestContext::new("v2_read_and_write_save_data").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    let save = SaveDataPost {
+        user_id: String::from("1"),
+        file_name: String::from("save1"),
+        data: serde_json::json!({ "level": 12, "items": ["Excalibur"] }),
+    };
+    let post_response = test_context
+        .server
+        .post(V2_SAVE_DATA_PATH)
+        .json(&save)
+        .await;
+
+    post_response.assert_status_ok();
+    assert_eq!(
+        post_response.json::<Envelope<SaveDataPost>>().data,
+        Some(save.clone())
+    );
+
+    let get_response = test_context
+        .server
+        .get(V2_SAVE_DATA_PATH)
+        .add_query_params(SaveDataGetParams {
+            user_id: Some(String::from("1")),
+            regex: Some(String::from("save.*")),
+            ..SaveDataGetParams::default()
+        })
+        .await;
+
+    get_response.assert_status_ok();
+    let saves = get_response
+        .json::<Envelope<Vec<SaveDataEntry>>>()
+        .data
+        .expect("No data in save data get response");
+    assert_eq!(saves.len(), 1);
+    assert_eq!(saves[0].file_name, save.file_name);
+    assert_eq!(saves[0].data, Some(save.data));
+}
+
+#[tokio::test]
+async fn requests_without_running_game() {
+    let test_context = TestContext::new("v2_requests_without_running_game").await;
+    setup_initial_data(test_context.get_db_path()).await;
+
+    let response = test_context.server.get(V2_SAVE_DATA_PATH).await;
+
+    response.assert_status(StatusCode::CONFLICT);
+    let envelope = response.json::<Envelope<serde_json::Value>>();
+    assert_eq!(envelope.data, None);
+    assert_eq!(envelope.errors.len(), 1);
+    assert_eq!(envelope.errors[0].code, "no_game_running");
+}
+
+#[tokio::test]
+async fn invalid_requests() {
+    let test_context = TestContext::new("v2_invalid_requests").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    let too_many = test_context
+        .server
+        .get(V2_LEADERBOARD_PATH)
+        .add_query_params(LeaderboardGetParams {
+            limit: Some(101),
+            ..LeaderboardGetParams::default()
+        })
+        .await;
+    let missing_field = test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(&serde_json::json!({ "value_name": "score", "value_num": 1.0 }))
+        .await;
+    let empty_user_id = test_context
+        .server
+        .post(V2_SAVE_DATA_PATH)
+        .json(&SaveDataPost {
+            user_id: String::new(),
+            file_name: String::from("save1"),
+            data: serde_json::json!({}),
+        })
+        .await;
+
+    for response in [too_many, missing_field, empty_user_id] {
+        response.assert_status_bad_request();
+        let envelope = response.json::<Envelope<serde_json::Value>>();
+        assert_eq!(envelope.errors[0].code, "invalid_request");
+    }
+
+    let long_name = test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(
+            &serde_json::json!({ "user_id": "1", "value_name": "a".repeat(65), "value_num": 1.0 }),
+        )
+        .await;
+    long_name.assert_status_unprocessable_entity();
+    let envelope = long_name.json::<Envelope<serde_json::Value>>();
+    assert_eq!(envelope.errors[0].code, "value_name_too_long");
+
+    let missing_value = test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(&serde_json::json!({ "user_id": "1", "value_name": "score" }))
+        .await;
+    missing_value.assert_status_unprocessable_entity();
+    let envelope = missing_value.json::<Envelope<serde_json::Value>>();
+    assert_eq!(envelope.errors[0].code, "value_missing");
+}
+
+#[tokio::test]
+async fn composite_leaderboard_pages() {
+    let test_context = TestContext::new("v2_composite_leaderboard_pages").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+    let components = vec![
+        MetricComponent {
+            name: String::from("score"),
+            ascending: false,
+        },
+        MetricComponent {
+            name: String::from("time"),
+            ascending: true,
+        },
+    ];
+    set_leaderboard_components("0", "run", &components, test_context.get_db_path()).unwrap();
+
+    let run = |user_id: &str, score: f64, time: f64| LeaderboardPost {
+        user_id: String::from(user_id),
+        value_name: String::from("run"),
+        value_num: None,
+        values: Some([(String::from("score"), score), (String::from("time"), time)].into()),
+    };
+    // the fastest of the tied runs is posted last, so paging by row id alone would skip it
+    for post in [
+        run("1", 100.0, 90.0),
+        run("2", 100.0, 80.0),
+        run("1", 120.0, 99.0),
+        run("2", 100.0, 70.0),
+    ] {
+        let response = test_context
+            .server
+            .post(V2_LEADERBOARD_PATH)
+            .json(&post)
+            .await;
+        response.assert_status_ok();
+        let stored = response.json::<Envelope<LeaderboardPost>>().data.unwrap();
+        assert_eq!(stored.value_num, Some(post.values.unwrap()["score"]));
+    }
+
+    let mut times = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = test_context
+            .server
+            .get(V2_LEADERBOARD_PATH)
+            .add_query_params(LeaderboardGetParams {
+                value_name: Some(String::from("run")),
+                limit: Some(2),
+                cursor,
+                ..LeaderboardGetParams::default()
+            })
+            .await
+            .json::<Envelope<Vec<LeaderboardEntry>>>();
+        for entry in page.data.unwrap() {
+            let components = entry.components.unwrap();
+            assert_eq!(components[0].value, entry.value_num);
+            times.push(components[1].value);
+        }
+        cursor = page.pagination.unwrap().next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(times, vec![99.0, 70.0, 80.0, 90.0]);
+
+    let first = test_context
+        .server
+        .get(V2_LEADERBOARD_PATH)
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("run")),
+            limit: Some(1),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Envelope<Vec<LeaderboardEntry>>>();
+    assert_eq!(
+        first.data.unwrap()[0].components,
+        Some(vec![
+            LeaderboardComponent {
+                name: String::from("score"),
+                value: 120.0,
+            },
+            LeaderboardComponent {
+                name: String::from("time"),
+                value: 99.0,
+            },
+        ])
+    );
+
+    let mut missing = run("1", 100.0, 0.0);
+    missing.values.as_mut().unwrap().remove("time");
+    let response = test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(&missing)
+        .await;
+    response.assert_status_unprocessable_entity();
+    assert_eq!(
+        response.json::<Envelope<serde_json::Value>>().errors[0].code,
+        "component_missing"
+    );
+}
+
+#[tokio::test]
+async fn unknown_users() {
+    let test_context = TestContext::new("v2_unknown_users").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    let entry = test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(&LeaderboardPost {
+            user_id: String::from("nobody"),
+            value_name: String::from("score"),
+            value_num: Some(1.0),
+            values: None,
+        })
+        .await;
+    let save = test_context
+        .server
+        .post(V2_SAVE_DATA_PATH)
+        .json(&SaveDataPost {
+            user_id: String::from("nobody"),
+            file_name: String::from("save1"),
+            data: serde_json::json!({}),
+        })
+        .await;
+
+    for response in [entry, save] {
+        response.assert_status_unprocessable_entity();
+        let envelope = response.json::<Envelope<serde_json::Value>>();
+        assert_eq!(envelope.errors[0].code, "unknown_user");
+    }
+}
+
+#[tokio::test]
+async fn versions_share_data() {
+    let test_context = TestContext::new("v2_versions_share_data").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    // the initial data has two "Score" entries for game 0, add one through each version
+    test_context
+        .server
+        .post(V1_LEADERBOARD_PATH)
+        .json(&handlers::LeaderboardPost {
+            value_name: String::from("Score"),
+            value_num: Some(90.0),
+            player_slot: 2,
+            ..Default::default()
+        })
+        .await
+        .assert_status_ok();
+    test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(&LeaderboardPost {
+            user_id: String::from("1"),
+            value_name: String::from("Score"),
+            value_num: Some(80.0),
+            values: None,
+        })
+        .await
+        .assert_status_ok();
+
+    let v1_entries = test_context
+        .server
+        .get(V1_LEADERBOARD_PATH)
+        .add_query_params(handlers::LeaderboardGetParams {
+            count: None,
+            ascending: None,
+            value_name: Some(String::from("Score")),
+            offset: None,
+            player_slot: None,
+            challenge_date: None,
+        })
+        .await
+        .json::<Vec<handlers::LeaderboardEntry>>();
+    let v2_entries = test_context
+        .server
+        .get(V2_LEADERBOARD_PATH)
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("Score")),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Envelope<Vec<LeaderboardEntry>>>()
+        .data
+        .expect("No data in leaderboard get response");
+
+    assert_eq!(v1_entries.len(), 4);
+    assert_eq!(v2_entries.len(), 4);
+    for (v1_entry, v2_entry) in v1_entries.iter().zip(&v2_entries) {
+        assert_eq!(v1_entry.value_num, v2_entry.value_num);
+        assert_eq!(v1_entry.player_slot.to_string(), v2_entry.user_id);
+        assert_eq!(
+            v1_entry.time_stamp,
+            v2_entry.time_stamp.format("%Y-%m-%d %H:%M:%S").to_string()
+        );
+    }
+}
+
+#[tokio::test]
+async fn page_through_listings() {
+    let test_context = TestContext::new("v2_page_through_listings").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    for file_name in ["slot3", "slot1", "slot2"] {
+        test_context
+            .server
+            .post(V2_SAVE_DATA_PATH)
+            .json(&SaveDataPost {
+                user_id: String::from("1"),
+                file_name: String::from(file_name),
+                data: serde_json::json!({ "slot": file_name }),
+            })
+            .await
+            .assert_status_ok();
+    }
+
+    let mut file_names = Vec::new();
+    let mut cursor = None;
+    loop {
+        let envelope = test_context
+            .server
+            .get(V2_SAVE_DATA_PATH)
+            .add_query_params(SaveDataGetParams {
+                limit: Some(2),
+                cursor: cursor.clone(),
+                metadata_only: Some(true),
+                ..SaveDataGetParams::default()
+            })
+            .await
+            .json::<Envelope<Vec<SaveDataEntry>>>();
+        let pagination = envelope.pagination.expect("No pagination in response");
+        assert_eq!(pagination.total, 3);
+
+        for save in envelope.data.expect("No data in response") {
+            assert_eq!(save.data, None);
+            file_names.push(save.file_name);
+        }
+        cursor = pagination.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(file_names, vec!["slot1", "slot2", "slot3"]);
+
+    // the initial data has two "Score" entries for game 0
+    let first_page = test_context
+        .server
+        .get(V2_LEADERBOARD_PATH)
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("Score")),
+            limit: Some(1),
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Envelope<Vec<LeaderboardEntry>>>();
+    assert_eq!(first_page.data.unwrap()[0].value_num, 125.0);
+
+    // a new high score shouldn't push the second entry onto a later page
+    test_context
+        .server
+        .post(V2_LEADERBOARD_PATH)
+        .json(&LeaderboardPost {
+            user_id: String::from("2"),
+            value_name: String::from("Score"),
+            value_num: Some(1000.0),
+            values: None,
+        })
+        .await
+        .assert_status_ok();
+
+    let second_page = test_context
+        .server
+        .get(V2_LEADERBOARD_PATH)
+        .add_query_params(LeaderboardGetParams {
+            value_name: Some(String::from("Score")),
+            limit: Some(1),
+            cursor: first_page.pagination.unwrap().next_cursor,
+            ..LeaderboardGetParams::default()
+        })
+        .await
+        .json::<Envelope<Vec<LeaderboardEntry>>>();
+    assert_eq!(second_page.data.unwrap()[0].value_num, 100.0);
+    assert_eq!(second_page.pagination.unwrap().total, 3);
+
+    let invalid_cursor = test_context
+        .server
+        .get(V2_LEADERBOARD_PATH)
+        .add_query_params(LeaderboardGetParams {
+            cursor: Some(String::from("not a cursor")),
+            ..LeaderboardGetParams::default()
+        })
+        .await;
+    invalid_cursor.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn filter_save_files() {
+    let test_context = TestContext::new("v2_filter_save_files").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    for file_name in ["slot1", "slot2", "autosave1"] {
+        test_context
+            .server
+            .post(V2_SAVE_DATA_PATH)
+            .json(&SaveDataPost {
+                user_id: String::from("1"),
+                file_name: String::from(file_name),
+                data: serde_json::json!({}),
+            })
+            .await
+            .assert_status_ok();
+    }
+
+    let file_names = |envelope: Envelope<Vec<SaveDataEntry>>| {
+        envelope
+            .data
+            .expect("No data in response")
+            .into_iter()
+            .map(|save| save.file_name)
+            .collect::<Vec<String>>()
+    };
+    let by_glob = test_context
+        .server
+        .get(V2_SAVE_DATA_PATH)
+        .add_query_params(SaveDataGetParams {
+            glob: Some(String::from("*save?")),
+            ..SaveDataGetParams::default()
+        })
+        .await
+        .json::<Envelope<Vec<SaveDataEntry>>>();
+    assert_eq!(file_names(by_glob), vec!["autosave1"]);
+
+    let by_prefix = test_context
+        .server
+        .get(V2_SAVE_DATA_PATH)
+        .add_query_params(SaveDataGetParams {
+            prefix: Some(String::from("slot")),
+            limit: Some(1),
+            ..SaveDataGetParams::default()
+        })
+        .await
+        .json::<Envelope<Vec<SaveDataEntry>>>();
+    assert_eq!(by_prefix.pagination.as_ref().unwrap().total, 2);
+    assert_eq!(file_names(by_prefix), vec!["slot1"]);
+
+    let both = test_context
+        .server
+        .get(V2_SAVE_DATA_PATH)
+        .add_query_params(SaveDataGetParams {
+            regex: Some(String::from("^slot")),
+            prefix: Some(String::from("slot")),
+            ..SaveDataGetParams::default()
+        })
+        .await;
+    both.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn restore_save_revisions() {
+    let test_context = TestContext::new("v2_restore_save_revisions").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    for level in [1, 2, 3] {
+        test_context
+            .server
+            .post(V2_SAVE_DATA_PATH)
+            .json(&SaveDataPost {
+                user_id: String::from("1"),
+                file_name: String::from("save1"),
+                data: serde_json::json!({ "level": level }),
+            })
+            .await
+            .assert_status_ok();
+    }
+
+    let revisions = test_context
+        .server
+        .get(V2_SAVE_REVISIONS_PATH)
+        .add_query_params(SaveRevisionsGetParams {
+            user_id: String::from("1"),
+            file_name: String::from("save1"),
+        })
+        .await
+        .json::<Envelope<Vec<SaveRevisionEntry>>>()
+        .data
+        .expect("No data in save revisions response");
+    assert_eq!(
+        revisions.iter().map(|r| r.revision).collect::<Vec<i32>>(),
+        vec![3, 2, 1]
+    );
+
+    let restore_response = test_context
+        .server
+        .post(V2_SAVE_RESTORE_PATH)
+        .json(&SaveRestorePost {
+            user_id: String::from("1"),
+            file_name: String::from("save1"),
+            revision: 1,
+        })
+        .await;
+    restore_response.assert_status_ok();
+    let restored = restore_response
+        .json::<Envelope<SaveDataEntry>>()
+        .data
+        .expect("No data in restore response");
+    assert_eq!(restored.revision, 4);
+    assert_eq!(restored.data, Some(serde_json::json!({ "level": 1 })));
+
+    let missing = test_context
+        .server
+        .post(V2_SAVE_RESTORE_PATH)
+        .json(&SaveRestorePost {
+            user_id: String::from("1"),
+            file_name: String::from("save1"),
+            revision: 100,
+        })
+        .await;
+    missing.assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(
+        missing.json::<Envelope<serde_json::Value>>().errors[0].code,
+        "not_found"
+    );
+}
+
+#[tokio::test]
+async fn conditional_save_writes() {
+    let test_context = TestContext::new("v2_conditional_save_writes").await;
+    setup_initial_data(test_context.get_db_path()).await;
+    set_current_game(&test_context, Some(0)).await;
+
+    let save = SaveDataPost {
+        user_id: String::from("1"),
+        file_name: String::from("save1"),
+        data: serde_json::json!({ "level": 1 }),
+    };
+    let created = test_context
+        .server
+        .post(V2_SAVE_DATA_PATH)
+        .json(&save)
+        .await;
+    created.assert_status_ok();
+    assert_eq!(created.header(axum::http::header::ETAG), "\"1\"");
+
+    let written = test_context
+        .server
+        .post(V2_SAVE_DATA_PATH)
+        .add_header(axum::http::header::IF_MATCH, "\"1\"")
+        .json(&save)
+        .await;
+    written.assert_status_ok();
+    assert_eq!(written.header(axum::http::header::ETAG), "\"2\"");
+
+    let stale = test_context
+        .server
+        .post(V2_SAVE_DATA_PATH)
+        .add_header(axum::http::header::IF_MATCH, "\"1\"")
+        .json(&save)
+        .await;
+    stale.assert_status(StatusCode::PRECONDITION_FAILED);
+    assert_eq!(
+        stale.json::<Envelope<serde_json::Value>>().errors[0].code,
+        "revision_mismatch"
+    );
+}
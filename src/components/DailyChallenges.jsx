@@ -0,0 +1,58 @@
+import React, { useEffect, useState } from "react";
+import { Header, Table, TableData, TableRow, Text } from "quackbox-design-system";
+import { invoke } from "@tauri-apps/api/core";
+import "../styles/DailyChallenges.css";
+
+export default function DailyChallenges() {
+
+    const [leaderboards, setLeaderboards] = useState([]);
+
+    const topEntriesLimit = 3;
+
+    useEffect(() => {
+        invoke("get_daily_leaderboards").then(
+            (leaderboards) => setLeaderboards(leaderboards),
+            (err) => {
+                console.error(err);
+            }
+        );
+    }, []);
+
+    // Nothing to show until someone plays a daily challenge today
+    if (leaderboards.length == 0) return null;
+
+    return (
+        <div className="daily-challenges-container">
+            <div className="daily-challenges-header">
+                <Header level={2} fontSize={"large"}>Today's Daily Challenges</Header>
+            </div>
+            <div className="daily-challenges">
+                {leaderboards.map(({ game_id, game_name, value_name, entries }) => (
+                    <div className="daily-challenge" key={`${game_id}-${value_name}`}>
+                        <Text fontSize="medium">{game_name}</Text>
+                        <Text fontSize="small">{value_name}</Text>
+                        <Table headers={["#", "Player", "Value"]}>
+                            {entries.slice(0, topEntriesLimit).map(
+                                ({ username, team_name, usernames, value_num, components }, index) => (
+                                    <TableRow key={index}>
+                                        <TableData>{index + 1}</TableData>
+                                        <TableData>
+                                            {team_name
+                                                ? `${team_name} (${usernames.join(", ")})`
+                                                : username}
+                                        </TableData>
+                                        <TableData>
+                                            {components
+                                                ? components.map(({ value }) => value).join(" / ")
+                                                : value_num}
+                                        </TableData>
+                                    </TableRow>
+                                )
+                            )}
+                        </Table>
+                    </div>
+                ))}
+            </div>
+        </div>
+    );
+}
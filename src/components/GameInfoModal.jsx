@@ -54,9 +54,17 @@ export default function GameInfoModal({ showModal, closeModal, game }) {
   }, []);
 
   const getTopFiveLeaderboardEntries = (entries) => {
+    // entries ranked by several values are already in order
+    if (entries[0]?.components) return entries.slice(0, 5);
     return [...entries].sort((a, b) => b.value_num - a.value_num).slice(0, 5);
   };
 
+  const capitalize = (name) =>
+    name
+      .split(" ")
+      .map((word) => word.charAt(0).toUpperCase() + word.slice(1))
+      .join(" ");
+
   const startGame = async (gameId) => {
     try {
       await invoke("play_game", { id: gameId });
@@ -139,23 +147,43 @@ export default function GameInfoModal({ showModal, closeModal, game }) {
                 .length != 0 ? (
                 <Tabs dataId="leaderboard-tabs">
                   {leaderboardData.map((value_name) => {
-                    const leaderboardTitle = value_name
-                      .split(" ")
-                      .map(
-                        (word) => word.charAt(0).toUpperCase() + word.slice(1)
-                      )
-                      .join(" ");
+                    const leaderboardTitle = capitalize(value_name);
+                    const components =
+                      leaderboard.data[value_name][0]?.components;
+                    const valueHeaders = components
+                      ? components.map(({ name }) => capitalize(name))
+                      : [leaderboardTitle];
                     return (
                       <Tab label={leaderboardTitle}>
-                        <Table headers={["Username", leaderboardTitle, "Date"]}>
+                        <Table headers={["Username", ...valueHeaders, "Date"]}>
                           {getTopFiveLeaderboardEntries(
                             leaderboard.data[value_name]
                           ).map(
-                            ({ username, value_num, time_stamp }, index) => {
+                            (
+                              {
+                                username,
+                                team_name,
+                                usernames,
+                                value_num,
+                                components,
+                                time_stamp,
+                              },
+                              index
+                            ) => {
                               return (
                                 <TableRow key={index}>
-                                  <TableData>{username}</TableData>
-                                  <TableData>{value_num}</TableData>
+                                  <TableData>
+                                    {team_name
+                                      ? `${team_name} (${usernames.join(", ")})`
+                                      : username}
+                                  </TableData>
+                                  {components ? (
+                                    components.map(({ name, value }) => (
+                                      <TableData key={name}>{value}</TableData>
+                                    ))
+                                  ) : (
+                                    <TableData>{value_num}</TableData>
+                                  )}
                                   <TableData>{time_stamp}</TableData>
                                 </TableRow>
                               );
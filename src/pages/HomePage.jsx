@@ -3,6 +3,7 @@ import { useGamepadContext, usePageContext, useToastContext } from "../context/c
 import { NavigationProvider } from "../context/NavigationContext";
 import Navigation from "../components/Navigation";
 import GameGallery from "../components/GameGallery";
+import DailyChallenges from "../components/DailyChallenges";
 import Footer from "../components/Footer";
 import ControllerConnectPage from "./ControllerConnectPage";
 import "../styles/HomePage.css";
@@ -44,6 +45,7 @@ export default function HomePage() {
 				<div className="home-page">
 					<Navigation />
 					<GameGallery />
+					<DailyChallenges />
 					<Footer />
 				</div>
 			</NavigationProvider>